@@ -13,15 +13,41 @@
 mod subscriptions {
 
     use ink::{
-        prelude::{format, string::String, vec::Vec, *},
-        storage::Mapping,
+        env::hash::Blake2x256,
+        prelude::{collections::BTreeMap, format, string::String, vec::Vec, *},
+        storage::{traits::StorageKey, Lazy, Mapping},
     };
 
     pub const BLOCKS_PER_WEEK: u32 = 3600 * 24 * 7;
     pub const BLOCKS_PER_MONTH: u32 = 3600 * 24 * 7 * 30;
 
+    /// Upper bound (exclusive) of the age proven by the minimum age zero knowledge proof.
+    /// Must match `RANGE_TO` of the proof's circuit
+    pub const PROOF_MAX_AGE: u128 = 120;
+
+    /// Version of the events' shape. Must be bumped whenever any event's fields change, so
+    /// off-chain decoders can check compatibility before parsing events
+    pub const EVENTS_VERSION: u32 = 2;
+
+    /// Version of the storage layout. Must be bumped whenever the layout of stored subscriptions
+    /// changes, so `migrate` can upgrade the records stored by the previous code
+    pub const STORAGE_VERSION: u32 = 2;
+
+    /// Solvency ratio, in basis points, of a contract that can honor all refunds exactly
+    pub const FULL_SOLVENCY_BPS: u128 = 10_000;
+
+    /// Maximum number of active subscriptions returned by a single query, so the query does not
+    /// exceed the node's maximum return size
+    const MAX_RETURNED: usize = 100;
+
+    /// Maximum number of recent cancellations kept by the smart contract
+    const MAX_RECENT_CANCELLATIONS: usize = MAX_RETURNED;
+
+    /// Number of blocks covered by a single bucket of the charge schedule, i.e. a day
+    const CHARGE_BUCKET_BLOCKS: BlockNumber = 3600 * 24;
+
     /// Defines subscription payment interval
-    #[derive(Debug, Clone, Copy, PartialEq, scale::Encode, scale::Decode)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(
         feature = "std",
         derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
@@ -29,6 +55,8 @@ mod subscriptions {
     pub enum PaymentInterval {
         Week,
         Month,
+        /// Custom number of blocks per interval, e.g. for daily or annual subscriptions
+        Custom(u32),
     }
 
     /// Subscription data
@@ -53,6 +81,42 @@ mod subscriptions {
         last_payment_at: BlockNumber,
         /// External channel handle specific for the subscription, e.g. Telegram channel ID
         external_channel_handle: String,
+        /// Set when billing of the subscription is paused
+        frozen: bool,
+        /// Block at which the subscription has been frozen
+        frozen_at: BlockNumber,
+        /// Number of consecutive intervals the subscription has been short on funds for
+        missed_intervals: u32,
+    }
+
+    /// Subscription data as stored by storage version 1, i.e. by the initially deployed code.
+    /// Its payment intervals decode as `PaymentInterval`, as `Custom` interval is appended last.
+    #[derive(Debug, Clone, scale::Encode, scale::Decode)]
+    pub struct SubscriptionV1 {
+        payment_interval: PaymentInterval,
+        declared_payment_intervals: u32,
+        paid_intervals: u32,
+        price_per_interval: Balance,
+        registered_at: BlockNumber,
+        last_payment_at: BlockNumber,
+        external_channel_handle: String,
+    }
+
+    impl From<SubscriptionV1> for Subscription {
+        fn from(s: SubscriptionV1) -> Self {
+            Subscription {
+                payment_interval: s.payment_interval,
+                declared_payment_intervals: s.declared_payment_intervals,
+                paid_intervals: s.paid_intervals,
+                price_per_interval: s.price_per_interval,
+                registered_at: s.registered_at,
+                last_payment_at: s.last_payment_at,
+                external_channel_handle: s.external_channel_handle,
+                frozen: false,
+                frozen_at: 0,
+                missed_intervals: 0,
+            }
+        }
     }
 
     /// Active subscription attributes to be exposed externally
@@ -66,6 +130,82 @@ mod subscriptions {
         external_channel_handle: Vec<u8>,
     }
 
+    /// Reason why a subscription has been cancelled
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum CancellationReason {
+        /// Cancelled by the subscriber
+        UserRequested,
+        /// Cancelled by the payment settlement, because the subscription ran out of funds
+        NonPayment,
+        /// Cancelled by the owner of the smart contract
+        OwnerRevoked,
+        /// Cancelled because the subscription offering is wound down
+        Sunset,
+        /// Cancelled because the subscription's funds have been reclaimed
+        Reclaimed,
+    }
+
+    /// Result of the payment settlement of a single subscription
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct SettlementReport {
+        /// Number of intervals charged during the settlement
+        charged_intervals: u32,
+        /// Tokens transferred to the owner during the settlement
+        charged_amount: Balance,
+        /// Set when subscription has been cancelled, because of insufficient funds
+        cancelled: bool,
+    }
+
+    /// Aggregate metrics of all active subscriptions, e.g. for dashboards
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ContractStats {
+        /// Number of active subscriptions
+        active_count: u32,
+        /// Tokens held on behalf of subscribers, i.e. value of declared, but not paid yet
+        /// intervals
+        total_reserved: Balance,
+        /// Number of declared payment intervals
+        total_declared_intervals: u64,
+        /// Number of already paid intervals
+        total_paid_intervals: u64,
+    }
+
+    /// Coverage of a subscription, i.e. which blocks are paid and declared to be paid
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct CoverageSummary {
+        /// Declared payment interval
+        payment_interval: PaymentInterval,
+        /// Registered at
+        registered_at: BlockNumber,
+        /// Number of already paid intervals
+        paid_intervals: u32,
+        /// Number of declared payment intervals
+        declared_payment_intervals: u32,
+        /// Block until which the subscription is paid
+        paid_through_block: BlockNumber,
+        /// Block at which all declared payment intervals end
+        expiry_block: BlockNumber,
+    }
+
+    /// Errors returned by a PSP22 token contract
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum PSP22Error {
+        Custom(String),
+        InsufficientBalance,
+        InsufficientAllowance,
+        ZeroRecipientAddress,
+        ZeroSenderAddress,
+        SafeTransferCheckFailed(String),
+    }
+
     /// Defines the storage layout of this smart contract.
     #[ink(storage)]
     pub struct Subscriptions {
@@ -76,14 +216,64 @@ mod subscriptions {
         price_per_block: Balance,
         /// Registered and active subscriptions
         subscriptions: Mapping<AccountId, Subscription>,
-        /// List of active subscriptions
+        /// List of active subscriptions in the order of registration. The order is a part of
+        /// the `get_active_subscriptions` contract, so removals must preserve it
         active_subscriptions: Vec<AccountId>,
 
-        /// Hash of verification key used for zero knowledge proof verification
+        /// Hash of verification key the smart contract has been deployed with. It is registered in
+        /// `proof_vks` for the initial minimum required age, only kept for the storage layout
         proof_vk: Hash,
         /// Minimum required age to be allowed to setup subscription
         /// Used for zero knowledge proof verification
         proof_min_required_age: u128,
+
+        // Fields above are stored in the root cell exactly as by storage version 1, so the code
+        // deployed with it can be upgraded. Fields added later must be stored in their own cells,
+        // i.e. `Lazy` or `Mapping`, so the root cell stays readable by the new code.
+        /// Version of the storage layout, see `STORAGE_VERSION`
+        storage_version: Lazy<u32>,
+        /// Hashes of verification keys used for zero knowledge proof verification, by the
+        /// required age proven by their circuits
+        proof_vks: Mapping<u128, Hash>,
+        /// Total amount of tokens ever refunded to subscribers
+        total_refunded: Lazy<Balance>,
+        /// PSP22 token contract used for payments and refunds. Native token is used when not set
+        payment_token: Lazy<Option<AccountId>>,
+        /// Minimum number of intervals a subscriber must declare to pay
+        min_intervals: Lazy<u32>,
+        /// Maximum number of intervals a subscriber can declare to pay
+        max_intervals: Lazy<u32>,
+        /// Number of consecutive intervals a subscription can be short on funds for before being
+        /// cancelled by the payment settlement
+        grace_intervals: Lazy<u32>,
+        /// Maximum number of blocks covered by the declared intervals of a subscription
+        max_duration_blocks: Lazy<u32>,
+        /// Number of intervals each account has been charged for, across all its subscriptions
+        lifetime_intervals: Mapping<AccountId, u64>,
+        /// Block timestamp of the last payment settlement run, 0 if never run
+        last_settlement_timestamp: Lazy<Timestamp>,
+        /// Hashes of zero knowledge proofs already used to add a subscription, so proofs can't be
+        /// replayed
+        used_proofs: Mapping<Hash, ()>,
+        /// Most recent cancellations with their reasons, the oldest first, at most
+        /// `MAX_RECENT_CANCELLATIONS`
+        recent_cancellations: Lazy<Vec<(AccountId, CancellationReason)>>,
+        /// Number of subscriptions ever created, including the cancelled ones
+        total_subscriptions_created: Lazy<u64>,
+        /// Accounts of active subscriptions which are not frozen, by the bucket of
+        /// `CHARGE_BUCKET_BLOCKS` blocks their next charge falls in, so the due subscriptions can
+        /// be settled without reading the other ones. Accounts of a bucket keep the order they
+        /// have been scheduled in
+        charge_buckets: Mapping<BlockNumber, Vec<AccountId>>,
+        /// Bucket of `charge_buckets` each scheduled account is in
+        charge_bucket_of: Mapping<AccountId, BlockNumber>,
+        /// First bucket of `charge_buckets` which may contain due subscriptions, the earlier ones
+        /// have been settled and are empty
+        first_unsettled_bucket: Lazy<BlockNumber>,
+        /// New owner the ownership is being transferred to, until the new owner accepts it
+        pending_owner: Lazy<Option<AccountId>>,
+        /// Set when registration of new subscriptions is paused, e.g. during maintenance
+        paused: Lazy<bool>,
     }
 
     /// Errors returned by this smart contract
@@ -113,6 +303,48 @@ mod subscriptions {
         ProofCallerAddressNotSerializable,
         /// Returned when caller's proof is invalid
         InvalidProofForMinAgeRequired,
+        /// Returned when verification key hash length doesn't match the chain extension's key
+        /// hash length
+        InvalidVkHashLength,
+        /// Returned when PSP22 token transfer from the caller fails, e.g. because of too low
+        /// allowance
+        TokenTransferFailed,
+        /// Returned when native tokens are transferred to the smart contract accepting payments
+        /// in PSP22 tokens
+        NativeTransferNotAccepted,
+        /// Returned when number of intervals to pay is lower than the minimum. Minimum number of
+        /// intervals passed as an error parameter
+        BelowMinimumIntervals(u32),
+        /// Returned when number of intervals to pay is higher than the maximum. Maximum number of
+        /// intervals passed as an error parameter
+        AboveMaximumIntervals(u32),
+        /// Returned when subscription of a given account is already frozen
+        AlreadyFrozen(AccountId),
+        /// Returned when subscription of a given account is not frozen
+        NotFrozen(AccountId),
+        /// Returned when too many accounts have been passed. Maximum number of accounts passed
+        /// as an error parameter
+        TooManyAccounts(u32),
+        /// Returned when the code of the smart contract can't be upgraded, e.g. the code hash is
+        /// unknown
+        CodeUpgradeFailed(String),
+        /// Returned when calculation of a token value overflows, e.g. price of the subscription
+        /// is too high
+        ArithmeticOverflow,
+        /// Returned when payment interval is invalid, e.g. custom interval of 0 blocks
+        InvalidPaymentInterval,
+        /// Returned when zero knowledge proof has already been used to add a subscription
+        ProofAlreadyUsed,
+        /// Returned when the caller is not the pending owner of the smart contract, i.e. the
+        /// ownership has not been transferred to the caller
+        NotPendingOwner,
+        /// Returned when no verification key is registered for the required age
+        NoVkForAge(u128),
+        /// Returned when registration of new subscriptions is paused
+        ContractPaused,
+        /// Returned when declared intervals of a subscription cover more blocks than the maximum
+        /// duration. Carries the maximum duration in blocks
+        DurationTooLong(u32),
     }
 
     /// Converts ink::env::Error to this smart contract error
@@ -139,12 +371,161 @@ mod subscriptions {
         /// Whe cancelled the subscription.
         #[ink(topic)]
         for_account: AccountId,
+        /// Why the subscription has been cancelled
+        reason: CancellationReason,
+    }
+
+    /// Event emitted when a subscriber extends the subscription by additional intervals
+    #[ink(event)]
+    pub struct RenewedSubscription {
+        /// Who renewed the subscription
+        #[ink(topic)]
+        for_account: AccountId,
+
+        /// Total number of declared payment intervals after the renewal
+        declared_payment_intervals: u32,
+    }
+
+    /// Event emitted on payment settlement for every charged subscription which stays active
+    #[ink(event)]
+    pub struct PaymentSettled {
+        /// Whose subscription has been charged
+        #[ink(topic)]
+        for_account: AccountId,
+        /// Number of intervals charged during the settlement
+        intervals_charged: u32,
+        /// Tokens transferred to the owner during the settlement
+        amount: Balance,
+    }
+
+    /// Event emitted on payment settlement when a subscription runs out of funds for the first
+    /// time, so the subscriber can top it up before the grace period ends
+    #[ink(event)]
+    pub struct GracePeriodStarted {
+        /// Whose subscription is short on funds
+        #[ink(topic)]
+        for_account: AccountId,
+        /// Number of intervals the subscription is short on funds for
+        missed_intervals: u32,
+        /// Number of intervals the subscription can be short on funds for before being cancelled
+        grace_intervals: u32,
+    }
+
+    /// Event emitted when the owner changes the price per block
+    #[ink(event)]
+    pub struct PriceUpdated {
+        /// Price per block before the change
+        old: Balance,
+        /// Price per block after the change
+        new: Balance,
+    }
+
+    /// Event emitted when the owner initiates transfer of the ownership
+    #[ink(event)]
+    pub struct OwnershipTransferInitiated {
+        /// Current owner
+        #[ink(topic)]
+        from: AccountId,
+        /// New owner, who needs to accept the ownership
+        #[ink(topic)]
+        to: AccountId,
+    }
+
+    /// Event emitted when the new owner accepts the ownership
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        /// Previous owner
+        #[ink(topic)]
+        from: AccountId,
+        /// New owner
+        #[ink(topic)]
+        to: AccountId,
+    }
+
+    /// Event emitted when the code of the smart contract is upgraded
+    #[ink(event)]
+    pub struct CodeUpgraded {
+        /// Hash of the new code
+        #[ink(topic)]
+        new_code_hash: Hash,
+        /// Who upgraded the code
+        by: AccountId,
+    }
+
+    /// Event emitted when the owner pauses registration of new subscriptions
+    #[ink(event)]
+    pub struct Paused {
+        /// Who paused the registration
+        by: AccountId,
+    }
+
+    /// Event emitted when the owner resumes registration of new subscriptions
+    #[ink(event)]
+    pub struct Unpaused {
+        /// Who resumed the registration
+        by: AccountId,
+    }
+
+    /// Event emitted when the owner changes the minimum required age
+    #[ink(event)]
+    pub struct MinRequiredAgeUpdated {
+        /// Minimum required age before the change
+        old: u128,
+        /// Minimum required age after the change
+        new: u128,
+    }
+
+    /// Event emitted when the owner changes the grace period
+    #[ink(event)]
+    pub struct GraceIntervalsUpdated {
+        /// Number of grace intervals before the change
+        old: u32,
+        /// Number of grace intervals after the change
+        new: u32,
+    }
+
+    /// Event emitted when the owner overrides the price of a single subscription
+    #[ink(event)]
+    pub struct SubscriptionPriceAdjusted {
+        /// Whose subscription price has been overridden
+        #[ink(topic)]
+        for_account: AccountId,
+        /// Price per interval before the change
+        old: Balance,
+        /// Price per interval after the change
+        new: Balance,
+    }
+
+    /// Event emitted when a subscriber tops up the subscription with funds for additional
+    /// intervals
+    #[ink(event)]
+    pub struct SubscriptionToppedUp {
+        /// Who topped up the subscription
+        #[ink(topic)]
+        for_account: AccountId,
+        /// Number of intervals the subscription has been topped up with
+        additional_intervals: u32,
+        /// Total number of declared payment intervals after the top up
+        declared_payment_intervals: u32,
+    }
+
+    /// Event emitted when the channel handle of a subscription is changed
+    #[ink(event)]
+    pub struct UpdatedChannelHandle {
+        /// Whose subscription has been updated
+        #[ink(topic)]
+        for_account: AccountId,
+
+        /// New handle (e.g. chat_id) associated with the user's subscription
+        external_channel_handle: Vec<u8>,
     }
 
     /// Event emitted on payment settelment, when there is at least one subscription to be cancelled
     #[ink(event)]
     pub struct CancelledSubscriptions {
         for_accounts: Vec<ActiveSubscriptionAttr>,
+        /// Why the subscriptions have been cancelled
+        reason: CancellationReason,
     }
 
     impl Subscriptions {
@@ -153,57 +534,151 @@ mod subscriptions {
         /// Only the owner can start payment settlement and transfer an ownership.
         /// Parameters:
         /// * `price_per_block` - price the subscriber needs to pay for the number of blocks translated to the payment interval.
-        /// * `proof_vk` - verification key hash used for zero knowledge proof verification. Must
-        /// be registered in aleph chain's `VkStorage` pallete
+        /// * `proof_vk` - verification key hash used for zero knowledge proof verification of the
+        /// minimum required age. Must be registered in aleph chain's `VkStorage` pallete
         /// * `proof_min_required_age` - minimum required age to proof the rights to setup new
         /// subscription
+        /// * `min_intervals` - minimum number of intervals a subscriber must declare to pay, so
+        /// there are no subscriptions costing more to settle than they are worth
+        /// * `max_intervals` - maximum number of intervals a subscriber can declare to pay, which
+        /// limits the tokens held by the smart contract on behalf of a subscriber
+        /// * `grace_intervals` - number of consecutive intervals a subscription can be short on
+        /// funds for before the payment settlement cancels it, 0 cancels it immediately
+        /// * `max_duration_blocks` - maximum number of blocks the declared intervals of
+        /// a subscription can cover, e.g. when regulations cap prepayment to 12 months
+        #[ink(constructor)]
+        pub fn new(
+            price_per_block: Balance,
+            proof_vk: Hash,
+            proof_min_required_age: u128,
+            min_intervals: u32,
+            max_intervals: u32,
+            grace_intervals: u32,
+            max_duration_blocks: u32,
+        ) -> Self {
+            Self::new_with_payment_token(
+                price_per_block,
+                proof_vk,
+                proof_min_required_age,
+                min_intervals,
+                max_intervals,
+                grace_intervals,
+                max_duration_blocks,
+                None,
+            )
+        }
+
+        /// Creates new instance of this smart contract accepting payments in PSP22 tokens.
+        /// Parameters are the same as for `new` constructor, plus:
+        /// * `payment_token` - PSP22 token contract used for payments and refunds. Subscribers
+        /// need to approve the smart contract to spend their tokens. Native token is used when
+        /// not set
         #[ink(constructor)]
-        pub fn new(price_per_block: Balance, proof_vk: Hash, proof_min_required_age: u128) -> Self {
-            Self {
+        pub fn new_with_payment_token(
+            price_per_block: Balance,
+            proof_vk: Hash,
+            proof_min_required_age: u128,
+            min_intervals: u32,
+            max_intervals: u32,
+            grace_intervals: u32,
+            max_duration_blocks: u32,
+            payment_token: Option<AccountId>,
+        ) -> Self {
+            let mut contract = Self {
                 owner: Self::env().caller(),
                 price_per_block,
                 subscriptions: Mapping::default(),
                 active_subscriptions: Vec::default(),
                 proof_vk,
                 proof_min_required_age,
-            }
+                storage_version: Lazy::new(),
+                proof_vks: Mapping::default(),
+                total_refunded: Lazy::new(),
+                payment_token: Lazy::new(),
+                min_intervals: Lazy::new(),
+                max_intervals: Lazy::new(),
+                grace_intervals: Lazy::new(),
+                max_duration_blocks: Lazy::new(),
+                lifetime_intervals: Mapping::default(),
+                last_settlement_timestamp: Lazy::new(),
+                used_proofs: Mapping::default(),
+                recent_cancellations: Lazy::new(),
+                total_subscriptions_created: Lazy::new(),
+                charge_buckets: Mapping::default(),
+                charge_bucket_of: Mapping::default(),
+                first_unsettled_bucket: Lazy::new(),
+                pending_owner: Lazy::new(),
+                paused: Lazy::new(),
+            };
+            contract.storage_version.set(&STORAGE_VERSION);
+            contract.proof_vks.insert(proof_min_required_age, &proof_vk);
+            contract.payment_token.set(&payment_token);
+            contract.min_intervals.set(&min_intervals);
+            contract.max_intervals.set(&max_intervals);
+            contract.grace_intervals.set(&grace_intervals);
+            contract.max_duration_blocks.set(&max_duration_blocks);
+            contract
+                .first_unsettled_bucket
+                .set(&(Self::env().block_number() / CHARGE_BUCKET_BLOCKS));
+            contract
         }
 
         /// Registers new subscrption for a caller and a given time period.
         /// Parameters:
-        /// * payment_interval - one of week|month
+        /// * payment_interval - one of week|month or a custom number of blocks
         /// * intervals_to_pay - number of paid intervales declared by the caller
         /// * external_channel_handle_id - external identifier, specific for the external channel, used by the notification service
+        /// * required_age - age proven by the zero knowledge proof, selecting the verification key,
+        /// e.g. 21 for a 21+ tier. Must not be lower than the minimum required age
         /// * proof - zero knowledge proof to verify what is required to add a new subscription
+        /// In PSP22 token mode tokens covering all declared intervals are transferred from the
+        /// caller, who needs to approve the smart contract to spend them first.
         /// Events:
         /// * NewSubscription
         /// Fails:
         /// * when subscription is already registerred
         /// * when invalid payment interval
+        /// * when number of intervals to pay is lower than the minimum or higher than the maximum
+        /// * when declared intervals cover more blocks than the maximum duration
+        /// * when zero knowledge proof is invalid or has already been used
+        /// * when no verification key is registered for the required age
+        /// * when registration of new subscriptions is paused
         /// * when not enough token value transferred to the smart contract call
+        /// * when PSP22 token transfer from the caller fails
+        /// * when native tokens are transferred in PSP22 token mode
         #[ink(message, payable)]
         pub fn add_subscription(
             &mut self,
             payment_interval: PaymentInterval,
             intervals_to_pay: u32,
             external_channel_handle: String,
+            required_age: u128,
             proof: Vec<u8>,
         ) -> Result<(), Error> {
+            if self.is_paused() {
+                return Err(Error::ContractPaused);
+            }
             let caller = self.env().caller();
             // if caller is already subscribed
             if self.subscriptions.get(caller).is_some() {
                 return Err(Error::AlreadyRegisterred(caller));
             }
 
+            self.validate_payment_interval(&payment_interval)?;
             self.validate_intervals_to_pay(intervals_to_pay)?;
+            self.validate_duration(&payment_interval, intervals_to_pay)?;
             self.validate_channel_handle(&external_channel_handle)?;
 
-            // verify zero knowlege proof
-            self.verify_proof(proof)?;
+            // verify zero knowlege proof, which can be used only once
+            let proof_hash = self.proof_hash(&proof);
+            if self.used_proofs.contains(proof_hash) {
+                return Err(Error::ProofAlreadyUsed);
+            }
+            self.verify_proof(proof, required_age)?;
 
             // create new subscription record
             let curr_block = self.env().block_number();
-            let price_per_interval = self.price_per_interval(&payment_interval);
+            let price_per_interval = self.price_per_interval(&payment_interval)?;
             let subscription = Subscription {
                 payment_interval,
                 declared_payment_intervals: intervals_to_pay,
@@ -212,27 +687,34 @@ mod subscriptions {
                 registered_at: curr_block,
                 last_payment_at: curr_block,
                 external_channel_handle: external_channel_handle.clone(),
+                frozen: false,
+                frozen_at: 0,
+                missed_intervals: 0,
             };
 
+            // Total cost of the subscription, computed once and used for both the check and the reimbursement
+            let total_cost = price_per_interval
+                .checked_mul(intervals_to_pay as u128)
+                .ok_or(Error::ArithmeticOverflow)?;
+
             // Check how many tokens have been transferred as part of the transaction and if are enough to cover current and future payments
-            let transferred_value = self.env().transferred_value();
-            if transferred_value < price_per_interval * intervals_to_pay as u128 {
-                return Err(Error::SubscriptionCostTooHigh(
-                    price_per_interval * intervals_to_pay as u128,
-                ));
-            }
+            let overpaid = self.collect_payment(caller, total_cost)?;
 
             // Transfer one interval payment to the contract's owner. The tokens needed for the remaining paiments will stay in the contract
             self.transfer_to_owner(price_per_interval);
 
             // If user transferred more than expected
-            self.reimburse(
-                caller,
-                transferred_value - price_per_interval * intervals_to_pay as u128,
-            );
+            if overpaid > 0 {
+                self.reimburse(caller, overpaid);
+            }
 
             self.subscriptions.insert(caller, &subscription);
             self.active_subscriptions.push(caller);
+            self.schedule_charge(caller, self.next_charge_at(&subscription));
+            self.total_subscriptions_created
+                .set(&self.total_subscriptions_created().saturating_add(1));
+            self.record_charged_intervals(&caller, 1);
+            self.used_proofs.insert(proof_hash, &());
 
             self.env().emit_event(NewSubscription {
                 for_account: caller,
@@ -242,15 +724,45 @@ mod subscriptions {
             Ok(())
         }
 
+        /// Changes channel handle of the caller's subscription, e.g. when the subscriber's chat id
+        /// changes, keeping prepaid intervals
+        /// Parameters:
+        /// * `new_handle` - new external channel handle
+        /// Events:
+        /// * UpdatedChannelHandle
+        /// Fails:
+        /// * NotRegisterred - when there is no subscription associated with the caller's account
+        /// * MissingChannelHandle - when the new handle is empty
+        #[ink(message)]
+        pub fn update_channel_handle(&mut self, new_handle: String) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let mut subscription = self
+                .subscriptions
+                .get(caller)
+                .ok_or(Error::NotRegisterred(caller))?;
+            self.validate_channel_handle(&new_handle)?;
+
+            subscription.external_channel_handle = new_handle.clone();
+            self.subscriptions.insert(caller, &subscription);
+
+            self.env().emit_event(UpdatedChannelHandle {
+                for_account: caller,
+                external_channel_handle: new_handle.into_bytes(),
+            });
+            Ok(())
+        }
+
         /// Cancels subscription associated with a caller.
         /// All remaining tokens are transferred back to the caller.
         /// Events:
         /// * CancelledSubscription
         /// Fails:
         /// * SubscriptionNotFound - when there is no subscription associated with the caller's account
+        /// * when native tokens are transferred in PSP22 token mode
         #[ink(message, payable)]
         pub fn cancel_subscription(&mut self) -> Result<(), Error> {
             let caller = self.env().caller();
+            self.reject_native_transfer()?;
 
             let subscription = self
                 .subscriptions
@@ -258,597 +770,4638 @@ mod subscriptions {
                 .ok_or(Error::NotRegisterred(caller))?;
 
             // Transfer remaining token value
-            let mut to_return: Balance = 0;
-            if subscription.declared_payment_intervals > subscription.paid_intervals {
-                to_return = subscription.price_per_interval
-                    * (subscription.declared_payment_intervals - subscription.paid_intervals)
-                        as u128;
-            }
+            let to_return = Self::unpaid_value(&subscription)?;
 
             // Get all transferred tokens. We need to return them.
             let transferred_value = self.env().transferred_value();
-            to_return += transferred_value;
+            let to_return = to_return
+                .checked_add(transferred_value)
+                .ok_or(Error::ArithmeticOverflow)?;
 
             // If there is something to return
             if to_return > 0 {
                 self.reimburse(caller, to_return);
             }
 
-            self.subscriptions.remove(caller);
-            self.active_subscriptions.retain(|acct| acct != &caller);
+            self.remove_subscription(&caller);
+            self.record_cancellation(caller, CancellationReason::UserRequested);
 
             self.env().emit_event(CancelledSubscription {
                 for_account: caller,
+                reason: CancellationReason::UserRequested,
             });
 
             Ok(())
         }
 
-        /// Retrieves a list of active subscriptions.
-        /// Returns:
-        /// * list of active subscriptions
-        /// Fails
-        /// * when there is an inconsistent subscription data
-        #[ink(message)]
-        pub fn get_active_subscriptions(&self) -> Result<Vec<ActiveSubscriptionAttr>, Error> {
-            let mut subs = vec![];
-            for acct_id in &*self.active_subscriptions {
-                let sub = self
-                    .subscriptions
-                    .get(acct_id)
-                    .ok_or(Error::InconsistentSubscriptionData(*acct_id))?;
-                subs.push(ActiveSubscriptionAttr {
-                    for_account: *acct_id,
-                    external_channel_handle: sub.external_channel_handle.into_bytes(),
-                });
-            }
-            Ok(subs)
-        }
-
-        /// Run payment settlement for the next subscription round.
-        /// For each active subscription check:
-        /// * is it still active
-        /// * does it have enough funds for the next interval
-        /// If above rules are not fulfilled subscription is automatically cancelled
+        /// Extends subscription of the caller by additional intervals, without registering a new
+        /// subscription. Additional intervals are paid at the subscription's price.
+        /// Parameters:
+        /// * `additional_intervals` - number of intervals the subscription is extended by
+        /// Events:
+        /// * RenewedSubscription
+        /// Fails:
+        /// * NotRegisterred - when there is no subscription associated with the caller's account
+        /// * InvalidIntervalsToPay - when no additional intervals are requested
+        /// * AboveMaximumIntervals - when declared intervals would exceed the maximum
+        /// * DurationTooLong - when declared intervals would exceed the maximum duration
+        /// * when not enough token value transferred to the smart contract call
+        /// * when PSP22 token transfer from the caller fails
+        /// * when native tokens are transferred in PSP22 token mode
         #[ink(message, payable)]
-        pub fn payment_settlement(&mut self) -> Result<(), Error> {
-            self.authorized(self.env().caller())?;
+        pub fn renew_subscription(&mut self, additional_intervals: u32) -> Result<(), Error> {
+            let (caller, declared_payment_intervals) =
+                self.extend_caller_subscription(additional_intervals)?;
 
-            let mut subs_to_cancel: Vec<ActiveSubscriptionAttr> = vec![];
+            self.env().emit_event(RenewedSubscription {
+                for_account: caller,
+                declared_payment_intervals,
+            });
 
-            let curr_block = self.env().block_number();
+            Ok(())
+        }
 
-            for acct_id in &*self.active_subscriptions {
-                let mut s = self
-                    .subscriptions
-                    .get(acct_id)
-                    .ok_or(Error::InconsistentSubscriptionData(*acct_id))?;
-                // calculate number of intervals to pay
-                let mut to_pay_intervals =
-                    self.to_pay_intervals(s.payment_interval, curr_block, s.last_payment_at);
-                // check if there is something to pay
-                if to_pay_intervals == 0 {
-                    continue;
-                }
-                // if founds are not sufficient to pay all intervals to pay, transfer the remaining funds and cancel subscription
-                let mut cancel_subscription = false;
-                if s.declared_payment_intervals - s.paid_intervals < to_pay_intervals {
-                    to_pay_intervals = s.declared_payment_intervals - s.paid_intervals;
-                    cancel_subscription = true;
-                }
+        /// Tops up subscription of the caller with funds for additional intervals, e.g. when the
+        /// subscriber declared too few intervals. Works for a subscription with all declared
+        /// intervals already paid as well, as long as it hasn't been cancelled by the payment
+        /// settlement yet.
+        /// Parameters:
+        /// * `additional_intervals` - number of intervals the subscription is topped up with
+        /// Events:
+        /// * SubscriptionToppedUp
+        /// Fails:
+        /// * NotRegisterred - when there is no subscription associated with the caller's account
+        /// * InvalidIntervalsToPay - when no additional intervals are requested
+        /// * AboveMaximumIntervals - when declared intervals would exceed the maximum
+        /// * DurationTooLong - when declared intervals would exceed the maximum duration
+        /// * when not enough token value transferred to the smart contract call
+        /// * when PSP22 token transfer from the caller fails
+        /// * when native tokens are transferred in PSP22 token mode
+        #[ink(message, payable)]
+        pub fn top_up(&mut self, additional_intervals: u32) -> Result<(), Error> {
+            let (caller, declared_payment_intervals) =
+                self.extend_caller_subscription(additional_intervals)?;
 
-                // calculate tokens to pay for past intervals eventually current interval
-                let to_pay = s.price_per_interval * to_pay_intervals as u128;
-                if to_pay > 0 {
-                    self.transfer_to_owner(to_pay);
-                }
+            self.env().emit_event(SubscriptionToppedUp {
+                for_account: caller,
+                additional_intervals,
+                declared_payment_intervals,
+            });
 
-                s.paid_intervals += to_pay_intervals;
-                s.last_payment_at = curr_block;
+            Ok(())
+        }
 
-                if cancel_subscription {
-                    // add subscription to the list of to be cancelled subsccriptions
-                    subs_to_cancel.push(ActiveSubscriptionAttr {
-                        for_account: *acct_id,
-                        external_channel_handle: s.external_channel_handle.into_bytes(),
-                    });
-                } else {
-                    self.subscriptions.insert(acct_id, &s);
-                }
-            }
+        /// Extends declared intervals of the caller's subscription, collecting the payment for
+        /// additional intervals at the subscription's price and reimbursing overpaid tokens
+        /// Returns:
+        /// * caller and its total number of declared payment intervals
+        fn extend_caller_subscription(
+            &mut self,
+            additional_intervals: u32,
+        ) -> Result<(AccountId, u32), Error> {
+            let caller = self.env().caller();
+            let mut subscription = self
+                .subscriptions
+                .get(caller)
+                .ok_or(Error::NotRegisterred(caller))?;
 
-            // cancel subscriptions
-            for sub_to_cancel in &*subs_to_cancel {
-                self.subscriptions.remove(sub_to_cancel.for_account);
-                self.active_subscriptions
-                    .retain(|id| &sub_to_cancel.for_account != id);
+            if additional_intervals == 0 {
+                return Err(Error::InvalidIntervalsToPay(additional_intervals));
             }
-            if !subs_to_cancel.is_empty() {
-                // emit an event with a list of cancelled subscriptions
-                self.env().emit_event(CancelledSubscriptions {
-                    for_accounts: subs_to_cancel,
-                });
+            let max_intervals = self.max_intervals.get().unwrap_or_default();
+            let declared_payment_intervals = subscription
+                .declared_payment_intervals
+                .checked_add(additional_intervals)
+                .filter(|intervals| *intervals <= max_intervals)
+                .ok_or(Error::AboveMaximumIntervals(max_intervals))?;
+            self.validate_duration(&subscription.payment_interval, declared_payment_intervals)?;
+
+            let cost = subscription
+                .price_per_interval
+                .checked_mul(additional_intervals as u128)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let overpaid = self.collect_payment(caller, cost)?;
+            if overpaid > 0 {
+                self.reimburse(caller, overpaid);
             }
-            Ok(())
+
+            subscription.declared_payment_intervals = declared_payment_intervals;
+            self.subscriptions.insert(caller, &subscription);
+
+            Ok((caller, declared_payment_intervals))
         }
 
-        /// Transfers ownership to a new owner. Only current owner is allowed to call it.
+        /// Cancels subscriptions of many subscribers at once, e.g. when winding down a cohort.
+        /// Unpaid intervals are refunded to the subscribers.
         /// Parameters:
-        /// * `new_owner` - new smart contract owner account
-        ///
+        /// * `accounts` - accounts of the subscribers, at most `MAX_RETURNED`
+        /// Returns:
+        /// * list of actually cancelled subscriptions, accounts without subscriptions are skipped
+        /// Events:
+        /// * CancelledSubscriptions, if at least one subscription has been cancelled
         /// Fails:
         /// * caller is not an owner of the smart contract
-        /// * caller and new owner is the same account
+        /// * too many accounts passed
         #[ink(message)]
-        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), Error> {
-            let caller = self.env().caller();
-            self.authorized(caller)?;
+        pub fn owner_cancel_many(
+            &mut self,
+            accounts: Vec<AccountId>,
+        ) -> Result<Vec<AccountId>, Error> {
+            self.authorized(self.env().caller())?;
+            if accounts.len() > MAX_RETURNED {
+                return Err(Error::TooManyAccounts(MAX_RETURNED as u32));
+            }
 
-            if new_owner == self.owner {
-                return Err(Error::NewOwnerMustBeDifferent);
+            let mut cancelled = vec![];
+            for account in accounts {
+                if cancelled.contains(&account) {
+                    continue;
+                }
+                let Some(s) = self.subscriptions.get(account) else {
+                    continue;
+                };
+                let to_return = Self::unpaid_value(&s)?;
+                if to_return > 0 {
+                    self.reimburse(account, to_return);
+                }
+                cancelled.push(account);
             }
 
-            self.owner = new_owner;
-            Ok(())
+            self.cancel_settled_subscriptions(cancelled.clone(), CancellationReason::OwnerRevoked)?;
+            Ok(cancelled)
         }
 
-        /// Modifies the code which is used to execute calls to this contract address (`AccountId`).
-        ///
-        /// We use this to upgrade the contract logic. We don't do any authorization here, any caller
-        /// can execute this method. In a production contract you would do some authorization here.
+        /// Changes channel handles of many subscriptions at once, e.g. when the channel platform
+        /// migrates its ids
+        /// Parameters:
+        /// * `remaps` - accounts of the subscribers with their new channel handles, at most
+        /// `MAX_RETURNED`. Accounts without subscriptions are skipped
+        /// Events:
+        /// * UpdatedChannelHandle, for every updated subscription
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        /// * too many remaps passed
+        /// * any of the channel handles is invalid, no handle is updated then
         #[ink(message)]
-        pub fn set_code(&mut self, code_hash: Hash) -> Result<(), Error> {
+        pub fn remap_handles(&mut self, remaps: Vec<(AccountId, String)>) -> Result<(), Error> {
             self.authorized(self.env().caller())?;
-            self.env().set_code_hash(&code_hash).unwrap_or_else(|err| {
-                panic!(
-                    "Failed to `set_code_hash` to {:?} due to {:?}",
-                    code_hash, err
-                )
-            });
-            ink::env::debug_println!("Switched code hash to {:?}.", code_hash);
-            Ok(())
-        }
+            if remaps.len() > MAX_RETURNED {
+                return Err(Error::TooManyAccounts(MAX_RETURNED as u32));
+            }
+            for (_, handle) in remaps.iter() {
+                self.validate_channel_handle(handle)?;
+            }
 
-        /// Checks if caller is this smart contract owner
-        fn authorized(&self, caller: AccountId) -> Result<(), Error> {
-            if caller != self.owner {
-                return Err(Error::NotAuthorized);
+            for (account, handle) in remaps {
+                let Some(mut s) = self.subscriptions.get(account) else {
+                    continue;
+                };
+                s.external_channel_handle = handle.clone();
+                self.subscriptions.insert(account, &s);
+
+                self.env().emit_event(UpdatedChannelHandle {
+                    for_account: account,
+                    external_channel_handle: handle.into_bytes(),
+                });
             }
             Ok(())
         }
 
-        /// Validates channel handle
-        fn validate_channel_handle(&self, channel_handle: &str) -> Result<(), Error> {
-            if channel_handle.is_empty() {
-                return Err(Error::MissingChannelHandle);
+        /// Retrieves a list of active subscriptions.
+        /// At most `MAX_RETURNED` subscriptions are returned.
+        /// Subscriptions are ordered by registration, i.e. ascending by the registration block and
+        /// the order of transactions within the block. Cancellations don't change the order of
+        /// the remaining subscriptions.
+        /// Returns:
+        /// * list of active subscriptions
+        /// * flag set when there are more active subscriptions than returned
+        /// Fails
+        /// * when there is an inconsistent subscription data
+        #[ink(message)]
+        pub fn get_active_subscriptions(
+            &self,
+        ) -> Result<(Vec<ActiveSubscriptionAttr>, bool), Error> {
+            let subs = self.active_subscriptions_attrs(0, MAX_RETURNED)?;
+            Ok((subs, self.active_subscriptions.len() > MAX_RETURNED))
+        }
+
+        /// Retrieves a page of active subscriptions, ordered as in `get_active_subscriptions`.
+        /// Use `subscriptions_count` to drive the pagination.
+        /// Parameters:
+        /// * `start` - index of the first returned subscription
+        /// * `limit` - maximum number of returned subscriptions, capped at `MAX_RETURNED`
+        /// Returns:
+        /// * list of active subscriptions, empty when `start` is past the end
+        /// Fails
+        /// * when there is an inconsistent subscription data
+        #[ink(message)]
+        pub fn get_active_subscriptions_paged(
+            &self,
+            start: u32,
+            limit: u32,
+        ) -> Result<Vec<ActiveSubscriptionAttr>, Error> {
+            self.active_subscriptions_attrs(start as usize, (limit as usize).min(MAX_RETURNED))
+        }
+
+        /// Retrieves number of active subscriptions
+        #[ink(message)]
+        pub fn subscriptions_count(&self) -> u32 {
+            self.active_subscriptions.len() as u32
+        }
+
+        /// Retrieves number of subscriptions ever created, e.g. for growth metrics. Unlike
+        /// `subscriptions_count` it doesn't decrease when subscriptions are cancelled
+        #[ink(message)]
+        pub fn total_subscriptions_created(&self) -> u64 {
+            self.total_subscriptions_created.get().unwrap_or_default()
+        }
+
+        /// Run payment settlement for the next subscription round.
+        /// For each active subscription check:
+        /// * is it still active
+        /// * does it have enough funds for the next interval
+        /// If above rules are not fulfilled subscription is automatically cancelled
+        /// Events:
+        /// * PaymentSettled, for every charged subscription which stays active
+        /// * CancelledSubscriptions, if at least one subscription has been cancelled
+        #[ink(message, payable)]
+        pub fn payment_settlement(&mut self) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+            self.settle_range(0, self.active_subscriptions.len())
+        }
+
+        /// Run payment settlement of a range of active subscriptions, so the gas of a single call
+        /// is bounded when there are many active subscriptions. Settles the subscriptions like
+        /// `payment_settlement` does.
+        /// Cancelled subscriptions are removed, which shifts the subsequent subscriptions towards
+        /// the start. Settle the ranges from the last one to the first one, so every subscription
+        /// is visited exactly once.
+        /// Parameters:
+        /// * `start` - index of the first settled subscription, as in `get_active_subscriptions`
+        /// * `end` - index past the last settled subscription, capped at the number of active
+        /// subscriptions
+        /// Events:
+        /// * PaymentSettled, for every charged subscription which stays active
+        /// * CancelledSubscriptions, if at least one subscription has been cancelled
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message, payable)]
+        pub fn payment_settlement_range(&mut self, start: u32, end: u32) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+            self.settle_range(start as usize, end as usize)
+        }
+
+        /// Run payment settlement of the subscriptions which are due, i.e. have at least one
+        /// interval to pay. Subscriptions are settled like `payment_settlement` does, but the
+        /// other subscriptions are not read at all, so the gas cost grows with the number of due
+        /// subscriptions instead of all active subscriptions. Subscriptions charged later within
+        /// the current day are read as well, and every day since the previous run costs a
+        /// storage read.
+        /// Events:
+        /// * PaymentSettled, for every charged subscription which stays active
+        /// * CancelledSubscriptions, if at least one subscription has been cancelled
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message, payable)]
+        pub fn settle_due_subscriptions(&mut self) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+
+            // subscriptions of the past buckets are due, the ones of the current bucket are
+            // settled once their charge is due, so the current bucket stays unsettled
+            let curr_bucket = self.env().block_number() / CHARGE_BUCKET_BLOCKS;
+            let first_bucket = self.first_unsettled_bucket.get().unwrap_or_default();
+            let mut accts = vec![];
+            for bucket in first_bucket..=curr_bucket {
+                accts.extend(self.charge_buckets.get(bucket).unwrap_or_default());
+            }
+            self.first_unsettled_bucket.set(&curr_bucket);
+            self.settle_accounts(accts)
+        }
+
+        /// Settles active subscriptions in the `[start..end)` range, cancelling the ones which ran
+        /// out of funds once all of them are settled
+        fn settle_range(&mut self, start: usize, end: usize) -> Result<(), Error> {
+            let end = end.min(self.active_subscriptions.len());
+            let start = start.min(end);
+            self.settle_accounts(self.active_subscriptions[start..end].to_vec())
+        }
+
+        /// Settles subscriptions of given accounts, cancelling the ones which ran out of funds
+        /// once all of them are settled
+        fn settle_accounts(&mut self, accts: Vec<AccountId>) -> Result<(), Error> {
+            let curr_block = self.env().block_number();
+
+            let mut accts_to_cancel: Vec<AccountId> = vec![];
+            for acct_id in accts {
+                let report = self.settle_subscription(&acct_id, curr_block)?;
+                if report.cancelled {
+                    accts_to_cancel.push(acct_id);
+                } else if report.charged_amount > 0 {
+                    self.env().emit_event(PaymentSettled {
+                        for_account: acct_id,
+                        intervals_charged: report.charged_intervals,
+                        amount: report.charged_amount,
+                    });
+                }
+            }
+            self.last_settlement_timestamp
+                .set(&self.env().block_timestamp());
+
+            self.cancel_settled_subscriptions(accts_to_cancel, CancellationReason::NonPayment)
+        }
+
+        /// Retrieves block timestamp of the last payment settlement run, e.g. for dashboards
+        /// Returns:
+        /// * timestamp in milliseconds, 0 if payment settlement has never been run
+        #[ink(message)]
+        pub fn last_settlement_timestamp(&self) -> Timestamp {
+            self.last_settlement_timestamp.get().unwrap_or_default()
+        }
+
+        /// Run payment settlement for a single subscriber, e.g. when resolving a billing dispute.
+        /// Other subscriptions are not affected.
+        /// Parameters:
+        /// * `account` - account of the subscriber to be settled
+        /// Returns:
+        /// * settlement report of the subscriber
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        /// * there is no subscription associated with the account
+        #[ink(message)]
+        pub fn settle_account(&mut self, account: AccountId) -> Result<SettlementReport, Error> {
+            self.authorized(self.env().caller())?;
+
+            if !self.subscriptions.contains(account) {
+                return Err(Error::NotRegisterred(account));
+            }
+
+            let report = self.settle_subscription(&account, self.env().block_number())?;
+            if report.cancelled {
+                self.cancel_settled_subscriptions(vec![account], CancellationReason::NonPayment)?;
+            }
+            Ok(report)
+        }
+
+        /// Freezes a subscription, e.g. when a subscriber disputes a charge or goes on hold.
+        /// Frozen subscriptions are skipped by the payment settlement and the frozen time is
+        /// not charged.
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// Fails:
+        /// * caller is neither the subscriber nor an owner of the smart contract
+        /// * there is no subscription associated with the account
+        /// * subscription is already frozen
+        #[ink(message)]
+        pub fn freeze_subscription(&mut self, account: AccountId) -> Result<(), Error> {
+            let mut s = self.subscription_of(account)?;
+            if s.frozen {
+                return Err(Error::AlreadyFrozen(account));
             }
+
+            s.frozen = true;
+            s.frozen_at = self.env().block_number();
+            self.subscriptions.insert(account, &s);
+            // frozen subscriptions are not charged
+            self.unschedule_charge(&account);
             Ok(())
         }
 
-        /// Validates intervals to pay
-        fn validate_intervals_to_pay(&self, intervals_to_pay: u32) -> Result<(), Error> {
-            if intervals_to_pay == 0 {
-                return Err(Error::InvalidIntervalsToPay(intervals_to_pay));
+        /// Unfreezes a subscription. Billing is resumed as if the frozen time has never passed.
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// Fails:
+        /// * caller is neither the subscriber nor an owner of the smart contract
+        /// * there is no subscription associated with the account
+        /// * subscription is not frozen
+        #[ink(message)]
+        pub fn unfreeze_subscription(&mut self, account: AccountId) -> Result<(), Error> {
+            let mut s = self.subscription_of(account)?;
+            if !s.frozen {
+                return Err(Error::NotFrozen(account));
+            }
+
+            // shift billing by the frozen time, so it's not counted toward paid intervals
+            let frozen_blocks = self.env().block_number().saturating_sub(s.frozen_at);
+            s.registered_at = s.registered_at.saturating_add(frozen_blocks);
+            s.last_payment_at = s.last_payment_at.saturating_add(frozen_blocks);
+            s.frozen = false;
+            s.frozen_at = 0;
+            self.subscriptions.insert(account, &s);
+            self.schedule_charge(account, self.next_charge_at(&s));
+            Ok(())
+        }
+
+        /// Calculates number of blocks remaining until the next charge of a subscriber.
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// Returns:
+        /// * number of blocks until the next charge, 0 if the charge is overdue
+        /// Fails:
+        /// * there is no subscription associated with the account
+        #[ink(message)]
+        pub fn blocks_until_next_charge(&self, account: AccountId) -> Result<u32, Error> {
+            let s = self
+                .subscriptions
+                .get(account)
+                .ok_or(Error::NotRegisterred(account))?;
+            Ok(self
+                .next_charge_at(&s)
+                .saturating_sub(self.env().block_number()))
+        }
+
+        /// Retrieves tokens `cancel_subscription` would refund to a subscriber, i.e. the value of
+        /// declared intervals which haven't been paid yet, so the subscriber knows the refund
+        /// before cancelling.
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// Returns:
+        /// * tokens refunded on cancellation
+        /// Fails:
+        /// * there is no subscription associated with the account
+        #[ink(message)]
+        pub fn quote_cancellation(&self, account: AccountId) -> Result<Balance, Error> {
+            let s = self
+                .subscriptions
+                .get(account)
+                .ok_or(Error::NotRegisterred(account))?;
+            Self::unpaid_value(&s)
+        }
+
+        /// Retrieves coverage of a subscriber's subscription in a single query.
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// Returns:
+        /// * coverage summary of the subscription
+        /// Fails:
+        /// * there is no subscription associated with the account
+        #[ink(message)]
+        pub fn coverage_summary(&self, account: AccountId) -> Result<CoverageSummary, Error> {
+            let s = self
+                .subscriptions
+                .get(account)
+                .ok_or(Error::NotRegisterred(account))?;
+            let blocks_per_interval = self.blocks_per_interval(&s.payment_interval);
+            let covered_until = |intervals: u32| {
+                s.registered_at
+                    .saturating_add(intervals.saturating_mul(blocks_per_interval))
+            };
+            Ok(CoverageSummary {
+                payment_interval: s.payment_interval,
+                registered_at: s.registered_at,
+                paid_intervals: s.paid_intervals,
+                declared_payment_intervals: s.declared_payment_intervals,
+                paid_through_block: covered_until(s.paid_intervals),
+                expiry_block: covered_until(s.declared_payment_intervals),
+            })
+        }
+
+        /// Retrieves subscription of a single subscriber, e.g. to render remaining balance
+        /// without scanning all active subscriptions
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// Returns:
+        /// * subscription of the subscriber
+        /// Fails:
+        /// * there is no subscription associated with the account
+        #[ink(message)]
+        pub fn get_subscription(&self, account: AccountId) -> Result<Subscription, Error> {
+            self.subscriptions
+                .get(account)
+                .ok_or(Error::NotRegisterred(account))
+        }
+
+        /// Retrieves subscription of a subscriber exactly as stored, e.g. for off-chain auditing.
+        /// Same as `get_subscription`, kept for auditing tools.
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// Returns:
+        /// * stored subscription with all its fields
+        /// Fails:
+        /// * there is no subscription associated with the account
+        #[ink(message)]
+        pub fn raw_subscription(&self, account: AccountId) -> Result<Subscription, Error> {
+            self.get_subscription(account)
+        }
+
+        /// Retrieves number of intervals an account has been charged for across all its
+        /// subscriptions, including cancelled ones, e.g. for loyalty programs
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// Returns:
+        /// * number of charged intervals, 0 if the account has never subscribed
+        #[ink(message)]
+        pub fn lifetime_intervals(&self, account: AccountId) -> u64 {
+            self.lifetime_intervals.get(account).unwrap_or_default()
+        }
+
+        /// Retrieves a histogram of remaining (declared, but not paid yet) intervals of active
+        /// subscriptions, e.g. for capacity planning.
+        /// Iterates over all active subscriptions, so gas cost grows linearly with their number.
+        /// Returns:
+        /// * list of `(remaining_intervals, count)` buckets ordered by remaining intervals
+        /// Fails:
+        /// * when there is an inconsistent subscription data
+        #[ink(message)]
+        pub fn remaining_intervals_histogram(&self) -> Result<Vec<(u32, u32)>, Error> {
+            let mut histogram = BTreeMap::<u32, u32>::new();
+            for acct_id in self.active_subscriptions.iter() {
+                let s = self
+                    .subscriptions
+                    .get(acct_id)
+                    .ok_or(Error::InconsistentSubscriptionData(*acct_id))?;
+                let remaining = s
+                    .declared_payment_intervals
+                    .saturating_sub(s.paid_intervals);
+                *histogram.entry(remaining).or_default() += 1;
+            }
+            Ok(histogram.into_iter().collect())
+        }
+
+        /// Retrieves aggregate metrics of all active subscriptions, e.g. to check the reserved
+        /// tokens before withdrawing the surplus.
+        /// Iterates over all active subscriptions, so gas cost grows linearly with their number.
+        #[ink(message)]
+        pub fn get_contract_stats(&self) -> ContractStats {
+            let mut stats = ContractStats {
+                active_count: self.active_subscriptions.len() as u32,
+                total_reserved: self.reserved_balance(),
+                total_declared_intervals: 0,
+                total_paid_intervals: 0,
+            };
+            for s in self
+                .active_subscriptions
+                .iter()
+                .filter_map(|acct_id| self.subscriptions.get(acct_id))
+            {
+                stats.total_declared_intervals += s.declared_payment_intervals as u64;
+                stats.total_paid_intervals += s.paid_intervals as u64;
+            }
+            stats
+        }
+
+        /// Retrieves the ratio of the contract's balance to the tokens which would be refunded if
+        /// all active subscriptions were cancelled, in basis points.
+        /// A ratio below `FULL_SOLVENCY_BPS` means the contract can't honor all refunds.
+        /// Iterates over all active subscriptions, so gas cost grows linearly with their number.
+        /// Returns:
+        /// * solvency ratio in basis points, capped at `u32::MAX`
+        /// * `u32::MAX` when there are no liabilities
+        #[ink(message)]
+        pub fn solvency_ratio_bps(&self) -> u32 {
+            let liabilities = self.reserved_balance();
+            if liabilities == 0 {
+                return u32::MAX;
+            }
+
+            let ratio = self.contract_balance().saturating_mul(FULL_SOLVENCY_BPS) / liabilities;
+            u32::try_from(ratio).unwrap_or(u32::MAX)
+        }
+
+        /// Withdraws tokens not owed to any subscriber, e.g. dust left by reimbursements or tokens
+        /// sent directly to the smart contract's account, to the owner
+        /// Returns:
+        /// * withdrawn amount of tokens, 0 when there is no surplus
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        /// * transfer of the surplus fails
+        #[ink(message)]
+        pub fn withdraw_surplus(&mut self) -> Result<Balance, Error> {
+            self.authorized(self.env().caller())?;
+            let surplus = self
+                .contract_balance()
+                .saturating_sub(self.reserved_balance());
+            if surplus > 0 {
+                self.transfer(self.owner, surplus)?;
             }
+            Ok(surplus)
+        }
+
+        /// Retrieves most recent cancellations, e.g. to tell subscribers why their subscriptions
+        /// ended
+        /// Returns:
+        /// * at most `MAX_RECENT_CANCELLATIONS` cancelled accounts with cancellation reasons, the
+        /// oldest first
+        #[ink(message)]
+        pub fn recent_cancellations(&self) -> Vec<(AccountId, CancellationReason)> {
+            self.recent_cancellations.get().unwrap_or_default()
+        }
+
+        /// Retrieves version of the events' shape, see `EVENTS_VERSION`
+        #[ink(message)]
+        pub fn events_version(&self) -> u32 {
+            EVENTS_VERSION
+        }
+
+        /// Retrieves the age policy enforced by the zero knowledge proof verification
+        /// Returns:
+        /// * minimum required age (inclusive) and maximum age (exclusive)
+        #[ink(message)]
+        pub fn age_policy(&self) -> (u128, u128) {
+            (self.proof_min_required_age, PROOF_MAX_AGE)
+        }
+
+        /// Checks if a subscription would be accepted for a given age, e.g. to validate a UI
+        /// input before generating an expensive proof
+        /// Parameters:
+        /// * `age` - age of the subscriber
+        /// Returns:
+        /// * true if the age satisfies the age policy, false otherwise
+        #[ink(message)]
+        pub fn would_accept_age(&self, age: u128) -> bool {
+            age >= self.proof_min_required_age && age < PROOF_MAX_AGE
+        }
+
+        /// Retrieves prices of both payment intervals in a single query, e.g. to render a plan
+        /// selector
+        /// Returns:
+        /// * tuple of price per week and price per month, `Balance::MAX` if a price overflows
+        #[ink(message)]
+        pub fn prices(&self) -> (Balance, Balance) {
+            (
+                self.price_per_interval(&PaymentInterval::Week)
+                    .unwrap_or(Balance::MAX),
+                self.price_per_interval(&PaymentInterval::Month)
+                    .unwrap_or(Balance::MAX),
+            )
+        }
+
+        /// Retrieves payment intervals supported by the smart contract, e.g. to render options in
+        /// a UI without hardcoding them
+        /// Returns:
+        /// * list of predefined payment intervals, custom intervals of any positive number of
+        /// blocks are supported too
+        #[ink(message)]
+        pub fn supported_intervals(&self) -> Vec<PaymentInterval> {
+            vec![PaymentInterval::Week, PaymentInterval::Month]
+        }
+
+        /// Retrieves PSP22 token contract used for payments
+        /// Returns:
+        /// * PSP22 token contract, or `None` when payments are in the native token
+        #[ink(message)]
+        pub fn payment_token(&self) -> Option<AccountId> {
+            self.payment_token.get().flatten()
+        }
+
+        /// Retrieves number of consecutive intervals a subscription can be short on funds for
+        /// before the payment settlement cancels it
+        #[ink(message)]
+        pub fn grace_intervals(&self) -> u32 {
+            self.grace_intervals.get().unwrap_or_default()
+        }
+
+        /// Retrieves maximum number of blocks the declared intervals of a subscription can cover
+        #[ink(message)]
+        pub fn max_duration_blocks(&self) -> u32 {
+            self.max_duration_blocks.get().unwrap_or_default()
+        }
+
+        /// Retrieves total amount of tokens ever refunded to subscribers, i.e. overpayments and
+        /// unpaid intervals of cancelled subscriptions
+        #[ink(message)]
+        pub fn total_refunded(&self) -> Balance {
+            self.total_refunded.get().unwrap_or_default()
+        }
+
+        /// Checks if an account is allowed to perform admin actions, e.g. payment settlement
+        /// Parameters:
+        /// * `account` - account to be checked
+        #[ink(message)]
+        pub fn is_admin(&self, account: AccountId) -> bool {
+            self.authorized(account).is_ok()
+        }
+
+        /// Changes price per block, e.g. to react to token price movements. Only current owner is
+        /// allowed to call it. Existing subscriptions keep their price locked at registration,
+        /// only new subscriptions are charged the new price.
+        /// Parameters:
+        /// * `new_price` - new price per block
+        /// Events:
+        /// * PriceUpdated
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message)]
+        pub fn set_price_per_block(&mut self, new_price: Balance) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+
+            let old = self.price_per_block;
+            self.price_per_block = new_price;
+            self.env().emit_event(PriceUpdated {
+                old,
+                new: new_price,
+            });
+            Ok(())
+        }
+
+        /// Changes minimum required age, e.g. when legal requirements of the service change. Only
+        /// current owner is allowed to call it. The minimum age is a public input of the zero
+        /// knowledge proof, so proofs of new subscriptions must be generated against the new age
+        /// and verified with a key registered for it by `register_vk_for_age`. Existing
+        /// subscriptions are not affected.
+        /// Parameters:
+        /// * `new_age` - new minimum required age
+        /// Events:
+        /// * MinRequiredAgeUpdated
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message)]
+        pub fn set_min_required_age(&mut self, new_age: u128) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+
+            let old = self.proof_min_required_age;
+            self.proof_min_required_age = new_age;
+            self.env()
+                .emit_event(MinRequiredAgeUpdated { old, new: new_age });
+            Ok(())
+        }
+
+        /// Registers verification key for the given required age, e.g. for a 21+ tier next to
+        /// the 18+ one. Replaces the key already registered for the age. Only current owner is
+        /// allowed to call it.
+        /// Parameters:
+        /// * `age` - required age proven by the verification key's circuit
+        /// * `vk_hash` - verification key hash. Must be registered in aleph chain's `VkStorage`
+        /// pallete
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message)]
+        pub fn register_vk_for_age(&mut self, age: u128, vk_hash: Hash) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+
+            self.proof_vks.insert(age, &vk_hash);
+            Ok(())
+        }
+
+        /// Changes number of consecutive intervals a subscription can be short on funds for before
+        /// the payment settlement cancels it, e.g. to lengthen the grace period during an outage.
+        /// Only current owner is allowed to call it. Applies to the subsequent settlements,
+        /// including subscriptions which are already short on funds.
+        /// Parameters:
+        /// * `grace` - new number of grace intervals
+        /// Events:
+        /// * GraceIntervalsUpdated
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message)]
+        pub fn set_grace_intervals(&mut self, grace: u32) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+
+            let old = self.grace_intervals();
+            self.grace_intervals.set(&grace);
+            self.env()
+                .emit_event(GraceIntervalsUpdated { old, new: grace });
+            Ok(())
+        }
+
+        /// Pauses registration of new subscriptions, e.g. before a code upgrade or a verification
+        /// key rotation. Only current owner is allowed to call it. Existing subscriptions are not
+        /// affected, i.e. they can still be cancelled and settled.
+        /// Events:
+        /// * Paused
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message)]
+        pub fn pause(&mut self) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.authorized(caller)?;
+
+            self.paused.set(&true);
+            self.env().emit_event(Paused { by: caller });
             Ok(())
         }
 
-        /// Calculates price of interval
-        fn price_per_interval(&self, payment_interval: &PaymentInterval) -> Balance {
-            self.price_per_block
-                * match payment_interval {
-                    PaymentInterval::Week => BLOCKS_PER_WEEK as u128,
-                    PaymentInterval::Month => BLOCKS_PER_MONTH as u128,
-                }
-        }
+        /// Resumes registration of new subscriptions. Only current owner is allowed to call it.
+        /// Events:
+        /// * Unpaused
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message)]
+        pub fn unpause(&mut self) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.authorized(caller)?;
+
+            self.paused.set(&false);
+            self.env().emit_event(Unpaused { by: caller });
+            Ok(())
+        }
+
+        /// Checks if registration of new subscriptions is paused, either by the owner or until
+        /// the storage is migrated after a code upgrade
+        #[ink(message)]
+        pub fn is_paused(&self) -> bool {
+            self.paused.get().unwrap_or_default() || self.storage_version() < STORAGE_VERSION
+        }
+
+        /// Overrides price per interval of a single subscription going forward, e.g. to grant
+        /// a negotiated rate. Only current owner is allowed to call it. Unpaid intervals are
+        /// charged, or refunded on cancellation, at the overridden price. They have been prepaid
+        /// at the previous price, so the prepaid tokens are settled: a lower price refunds the
+        /// difference to the subscriber, a higher price reduces the declared intervals to those
+        /// covered by the prepaid tokens and refunds the remainder.
+        /// Parameters:
+        /// * `account` - account of the subscriber
+        /// * `price_per_interval` - new price per interval of the subscription
+        /// Events:
+        /// * SubscriptionPriceAdjusted
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        /// * there is no subscription associated with the account
+        #[ink(message)]
+        pub fn set_subscription_price(
+            &mut self,
+            account: AccountId,
+            price_per_interval: Balance,
+        ) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+
+            let mut s = self
+                .subscriptions
+                .get(account)
+                .ok_or(Error::NotRegisterred(account))?;
+            let old = s.price_per_interval;
+            let prepaid = Self::unpaid_value(&s)?;
+            let unpaid_intervals = if price_per_interval > old {
+                // less than unpaid intervals at the previous price, so it fits u32
+                (prepaid / price_per_interval) as u32
+            } else {
+                s.declared_payment_intervals
+                    .saturating_sub(s.paid_intervals)
+            };
+            // doesn't exceed prepaid tokens, so it doesn't overflow
+            let to_return = prepaid - price_per_interval * unpaid_intervals as u128;
+            s.declared_payment_intervals = s.paid_intervals + unpaid_intervals;
+            s.price_per_interval = price_per_interval;
+            self.subscriptions.insert(account, &s);
+            if to_return > 0 {
+                self.reimburse(account, to_return);
+            }
+
+            self.env().emit_event(SubscriptionPriceAdjusted {
+                for_account: account,
+                old,
+                new: price_per_interval,
+            });
+            Ok(())
+        }
+
+        /// Initiates transfer of the ownership to a new owner. Only current owner is allowed to
+        /// call it. The ownership is transferred once the new owner accepts it with
+        /// `accept_ownership`, so a mistyped account can't take over the smart contract.
+        /// Transfer initiated before is replaced.
+        /// Parameters:
+        /// * `new_owner` - new smart contract owner account
+        /// Events:
+        /// * OwnershipTransferInitiated
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        /// * caller and new owner is the same account
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.authorized(caller)?;
+
+            if new_owner == self.owner {
+                return Err(Error::NewOwnerMustBeDifferent);
+            }
+
+            self.pending_owner.set(&Some(new_owner));
+            self.env().emit_event(OwnershipTransferInitiated {
+                from: self.owner,
+                to: new_owner,
+            });
+            Ok(())
+        }
+
+        /// Accepts the ownership transferred to the caller, who becomes the owner of the smart
+        /// contract.
+        /// Events:
+        /// * OwnershipTransferred
+        /// Fails:
+        /// * caller is not the pending owner, i.e. the ownership has not been transferred to the
+        /// caller
+        #[ink(message)]
+        pub fn accept_ownership(&mut self) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.pending_owner() != Some(caller) {
+                return Err(Error::NotPendingOwner);
+            }
+
+            let previous_owner = self.owner;
+            self.owner = caller;
+            self.pending_owner.set(&None);
+            self.env().emit_event(OwnershipTransferred {
+                from: previous_owner,
+                to: caller,
+            });
+            Ok(())
+        }
+
+        /// Cancels the ownership transfer which has not been accepted yet. Only current owner is
+        /// allowed to call it.
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        #[ink(message)]
+        pub fn cancel_ownership_transfer(&mut self) -> Result<(), Error> {
+            self.authorized(self.env().caller())?;
+            self.pending_owner.set(&None);
+            Ok(())
+        }
+
+        /// Retrieves account the ownership is being transferred to
+        /// Returns:
+        /// * pending owner, or `None` when no transfer has been initiated
+        #[ink(message)]
+        pub fn pending_owner(&self) -> Option<AccountId> {
+            self.pending_owner.get().flatten()
+        }
+
+        /// Modifies the code which is used to execute calls to this contract address (`AccountId`).
+        ///
+        /// We use this to upgrade the contract logic. Only an owner of the smart contract is
+        /// allowed to call this function. When the new code changes the storage layout, pause the
+        /// registration before the upgrade and call `migrate` of the new code right after it.
+        /// Events:
+        /// * CodeUpgraded
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        /// * code can't be switched, e.g. there is no code uploaded for the code hash
+        #[ink(message)]
+        pub fn set_code(&mut self, code_hash: Hash) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.authorized(caller)?;
+            self.env().set_code_hash(&code_hash).map_err(|err| {
+                ink::env::debug_println!(
+                    "Failed to `set_code_hash` to {:?} due to {:?}",
+                    code_hash,
+                    err
+                );
+                Error::CodeUpgradeFailed(format!("{:?}", err))
+            })?;
+            ink::env::debug_println!("Switched code hash to {:?}.", code_hash);
+            self.env().emit_event(CodeUpgraded {
+                new_code_hash: code_hash,
+                by: caller,
+            });
+            Ok(())
+        }
+
+        /// Retrieves version of the storage layout, 1 for the storage of the code which didn't
+        /// store it
+        #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version.get().unwrap_or(1)
+        }
+
+        /// Upgrades subscriptions stored by the previous code to the current storage layout, see
+        /// `STORAGE_VERSION`. Only current owner is allowed to call it. Must be called right after
+        /// `set_code`, as subscriptions of the previous layout can't be read until migrated.
+        /// Registration of new subscriptions is paused until then, so no subscription is stored
+        /// in the new layout before the migration. Settings added since the previous layout get
+        /// values matching the behaviour of the previous code and charges of migrated
+        /// subscriptions are scheduled. Does nothing when the storage is already up to date.
+        /// Returns:
+        /// * number of migrated subscriptions
+        /// Fails:
+        /// * caller is not an owner of the smart contract
+        /// * a stored subscription can't be decoded in the previous layout
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<u32, Error> {
+            self.authorized(self.env().caller())?;
+            if self.storage_version() >= STORAGE_VERSION {
+                return Ok(0);
+            }
+
+            // storage version 1 had no limits of the subscription length
+            if self.min_intervals.get().is_none() {
+                self.min_intervals.set(&1);
+            }
+            if self.max_intervals.get().is_none() {
+                self.max_intervals.set(&u32::MAX);
+            }
+            if self.max_duration_blocks.get().is_none() {
+                self.max_duration_blocks.set(&u32::MAX);
+            }
+            self.first_unsettled_bucket
+                .set(&(self.env().block_number() / CHARGE_BUCKET_BLOCKS));
+            // storage version 1 couldn't change the minimum age, so its key is built for it
+            if !self.proof_vks.contains(self.proof_min_required_age) {
+                self.proof_vks
+                    .insert(self.proof_min_required_age, &self.proof_vk);
+            }
+
+            let root_key = Self::root_key(&self.subscriptions);
+            let mut migrated = 0u32;
+            for account in self.active_subscriptions.clone() {
+                let legacy =
+                    ink::env::get_contract_storage::<_, SubscriptionV1>(&(root_key, account))?;
+                if let Some(legacy) = legacy {
+                    let subscription = Subscription::from(legacy);
+                    self.subscriptions.insert(account, &subscription);
+                    // storage version 1 had no charge schedule
+                    self.schedule_charge(account, self.next_charge_at(&subscription));
+                    migrated = migrated.saturating_add(1);
+                }
+            }
+            self.total_subscriptions_created
+                .set(&(self.active_subscriptions.len() as u64));
+            self.storage_version.set(&STORAGE_VERSION);
+            Ok(migrated)
+        }
+
+        /// Storage key of a given storage field, e.g. to read its values in a previous layout
+        fn root_key<T: StorageKey>(_field: &T) -> ink::primitives::Key {
+            T::KEY
+        }
+
+        /// Settles payments of a single subscription up to the current block.
+        /// Intervals which funds are not sufficient to pay for are not charged, but counted as
+        /// missed. Subscription short on funds for more than `grace_intervals` consecutive
+        /// intervals is reported as cancelled, but stays in the storage until removed by the
+        /// caller.
+        fn settle_subscription(
+            &mut self,
+            acct_id: &AccountId,
+            curr_block: BlockNumber,
+        ) -> Result<SettlementReport, Error> {
+            let mut s = self
+                .subscriptions
+                .get(acct_id)
+                .ok_or(Error::InconsistentSubscriptionData(*acct_id))?;
+            // frozen subscriptions are not charged
+            if s.frozen {
+                return Ok(SettlementReport::default());
+            }
+            // calculate number of intervals to pay
+            let mut to_pay_intervals =
+                self.to_pay_intervals(s.payment_interval, curr_block, s.last_payment_at);
+            // check if there is something to pay
+            if to_pay_intervals == 0 {
+                return Ok(SettlementReport::default());
+            }
+            // if founds are not sufficient to pay all intervals to pay, transfer the remaining funds
+            // and cancel subscription once the grace period is over
+            let mut cancelled = false;
+            let remaining_intervals = s.declared_payment_intervals - s.paid_intervals;
+            if remaining_intervals < to_pay_intervals {
+                let missed_before = s.missed_intervals;
+                s.missed_intervals = s
+                    .missed_intervals
+                    .saturating_add(to_pay_intervals - remaining_intervals);
+                to_pay_intervals = remaining_intervals;
+                cancelled = s.missed_intervals > self.grace_intervals();
+                if !cancelled && missed_before == 0 {
+                    self.env().emit_event(GracePeriodStarted {
+                        for_account: *acct_id,
+                        missed_intervals: s.missed_intervals,
+                        grace_intervals: self.grace_intervals(),
+                    });
+                }
+            } else {
+                s.missed_intervals = 0;
+            }
+
+            // calculate tokens to pay for past intervals eventually current interval
+            let to_pay = s
+                .price_per_interval
+                .checked_mul(to_pay_intervals as u128)
+                .ok_or(Error::ArithmeticOverflow)?;
+            if to_pay > 0 {
+                self.transfer_to_owner(to_pay);
+            }
+
+            s.paid_intervals += to_pay_intervals;
+            s.last_payment_at = curr_block;
+            self.subscriptions.insert(acct_id, &s);
+            self.schedule_charge(*acct_id, self.next_charge_at(&s));
+            self.record_charged_intervals(acct_id, to_pay_intervals);
+
+            Ok(SettlementReport {
+                charged_intervals: to_pay_intervals,
+                charged_amount: to_pay,
+                cancelled,
+            })
+        }
+
+        /// Retrieves attributes of at most `limit` active subscriptions starting at `start`
+        fn active_subscriptions_attrs(
+            &self,
+            start: usize,
+            limit: usize,
+        ) -> Result<Vec<ActiveSubscriptionAttr>, Error> {
+            let mut subs = vec![];
+            for acct_id in self.active_subscriptions.iter().skip(start).take(limit) {
+                let sub = self
+                    .subscriptions
+                    .get(acct_id)
+                    .ok_or(Error::InconsistentSubscriptionData(*acct_id))?;
+                subs.push(ActiveSubscriptionAttr {
+                    for_account: *acct_id,
+                    external_channel_handle: sub.external_channel_handle.into_bytes(),
+                });
+            }
+            Ok(subs)
+        }
+
+        /// Calculates value of declared, but not paid yet intervals of all active subscriptions,
+        /// i.e. tokens the smart contract holds on behalf of subscribers
+        fn reserved_balance(&self) -> Balance {
+            self.active_subscriptions
+                .iter()
+                .filter_map(|acct_id| self.subscriptions.get(acct_id))
+                .map(|s| {
+                    s.price_per_interval.saturating_mul(
+                        s.declared_payment_intervals
+                            .saturating_sub(s.paid_intervals) as u128,
+                    )
+                })
+                .fold(0u128, |total, liability| total.saturating_add(liability))
+        }
+
+        /// Calculates value of declared, but not paid yet intervals of a subscription
+        fn unpaid_value(s: &Subscription) -> Result<Balance, Error> {
+            s.price_per_interval
+                .checked_mul(
+                    s.declared_payment_intervals
+                        .saturating_sub(s.paid_intervals) as u128,
+                )
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// Removes cancelled subscriptions, i.e. during the payment settlement, and emits an event
+        /// with a list of cancelled subscriptions
+        fn cancel_settled_subscriptions(
+            &mut self,
+            accts: Vec<AccountId>,
+            reason: CancellationReason,
+        ) -> Result<(), Error> {
+            if accts.is_empty() {
+                return Ok(());
+            }
+
+            let mut subs_to_cancel: Vec<ActiveSubscriptionAttr> = vec![];
+            for acct_id in accts {
+                let s = self
+                    .subscriptions
+                    .get(acct_id)
+                    .ok_or(Error::InconsistentSubscriptionData(acct_id))?;
+                self.remove_subscription(&acct_id);
+                self.record_cancellation(acct_id, reason);
+                // add subscription to the list of cancelled subsccriptions
+                subs_to_cancel.push(ActiveSubscriptionAttr {
+                    for_account: acct_id,
+                    external_channel_handle: s.external_channel_handle.into_bytes(),
+                });
+            }
+
+            // emit an event with a list of cancelled subscriptions
+            self.env().emit_event(CancelledSubscriptions {
+                for_accounts: subs_to_cancel,
+                reason,
+            });
+            Ok(())
+        }
+
+        /// Removes all the state associated with the subscriber's account.
+        /// Every path that ends a subscription must go through this function, so no orphaned
+        /// per-account state is left behind.
+        fn remove_subscription(&mut self, account: &AccountId) {
+            self.subscriptions.remove(account);
+            // `retain` keeps the order of the remaining subscriptions, don't swap-remove
+            self.active_subscriptions.retain(|acct| acct != account);
+            self.unschedule_charge(account);
+        }
+
+        /// Calculates block of the next charge of a subscription
+        fn next_charge_at(&self, s: &Subscription) -> BlockNumber {
+            s.last_payment_at
+                .saturating_add(self.blocks_per_interval(&s.payment_interval))
+        }
+
+        /// Schedules the next charge of a subscription, replacing the already scheduled one.
+        /// Charges already due are scheduled in the first unsettled bucket, so they are not
+        /// skipped by `settle_due_subscriptions`.
+        fn schedule_charge(&mut self, account: AccountId, charge_at: BlockNumber) {
+            self.unschedule_charge(&account);
+            let bucket = (charge_at / CHARGE_BUCKET_BLOCKS)
+                .max(self.first_unsettled_bucket.get().unwrap_or_default());
+            let mut accts = self.charge_buckets.get(bucket).unwrap_or_default();
+            accts.push(account);
+            self.charge_buckets.insert(bucket, &accts);
+            self.charge_bucket_of.insert(account, &bucket);
+        }
+
+        /// Removes the scheduled charge of a subscription, if any
+        fn unschedule_charge(&mut self, account: &AccountId) {
+            let Some(bucket) = self.charge_bucket_of.take(account) else {
+                return;
+            };
+            let mut accts = self.charge_buckets.get(bucket).unwrap_or_default();
+            accts.retain(|acct| acct != account);
+            if accts.is_empty() {
+                self.charge_buckets.remove(bucket);
+            } else {
+                self.charge_buckets.insert(bucket, &accts);
+            }
+        }
+
+        /// Records a cancellation among the recent ones, dropping the oldest one when there are
+        /// too many of them
+        fn record_cancellation(&mut self, account: AccountId, reason: CancellationReason) {
+            let mut recent = self.recent_cancellations();
+            if recent.len() == MAX_RECENT_CANCELLATIONS {
+                recent.remove(0);
+            }
+            recent.push((account, reason));
+            self.recent_cancellations.set(&recent);
+        }
+
+        /// Increments number of intervals an account has been charged for across its lifetime.
+        /// The counter outlives subscriptions, so it's not removed by `remove_subscription`
+        fn record_charged_intervals(&mut self, account: &AccountId, intervals: u32) {
+            let lifetime = self.lifetime_intervals.get(account).unwrap_or_default();
+            self.lifetime_intervals
+                .insert(account, &lifetime.saturating_add(intervals as u64));
+        }
+
+        /// Retrieves subscription of an account managed by the caller, i.e. the caller is either
+        /// the subscriber or an owner of the smart contract
+        fn subscription_of(&self, account: AccountId) -> Result<Subscription, Error> {
+            let caller = self.env().caller();
+            if caller != account {
+                self.authorized(caller)?;
+            }
+            self.subscriptions
+                .get(account)
+                .ok_or(Error::NotRegisterred(account))
+        }
+
+        /// Checks if caller is this smart contract owner
+        fn authorized(&self, caller: AccountId) -> Result<(), Error> {
+            if caller != self.owner {
+                return Err(Error::NotAuthorized);
+            }
+            Ok(())
+        }
+
+        /// Validates channel handle
+        fn validate_channel_handle(&self, channel_handle: &str) -> Result<(), Error> {
+            if channel_handle.is_empty() {
+                return Err(Error::MissingChannelHandle);
+            }
+            Ok(())
+        }
+
+        /// Validates payment interval
+        fn validate_payment_interval(
+            &self,
+            payment_interval: &PaymentInterval,
+        ) -> Result<(), Error> {
+            if self.blocks_per_interval(payment_interval) == 0 {
+                return Err(Error::InvalidPaymentInterval);
+            }
+            Ok(())
+        }
+
+        /// Validates intervals to pay
+        fn validate_intervals_to_pay(&self, intervals_to_pay: u32) -> Result<(), Error> {
+            if intervals_to_pay == 0 {
+                return Err(Error::InvalidIntervalsToPay(intervals_to_pay));
+            }
+            let min_intervals = self.min_intervals.get().unwrap_or_default();
+            if intervals_to_pay < min_intervals {
+                return Err(Error::BelowMinimumIntervals(min_intervals));
+            }
+            let max_intervals = self.max_intervals.get().unwrap_or_default();
+            if intervals_to_pay > max_intervals {
+                return Err(Error::AboveMaximumIntervals(max_intervals));
+            }
+            Ok(())
+        }
+
+        /// Validates number of blocks covered by the declared intervals
+        fn validate_duration(
+            &self,
+            payment_interval: &PaymentInterval,
+            declared_payment_intervals: u32,
+        ) -> Result<(), Error> {
+            let duration = self.blocks_per_interval(payment_interval) as u64
+                * declared_payment_intervals as u64;
+            let max_duration_blocks = self.max_duration_blocks();
+            if duration > max_duration_blocks as u64 {
+                return Err(Error::DurationTooLong(max_duration_blocks));
+            }
+            Ok(())
+        }
+
+        /// Calculates number of blocks in the interval
+        fn blocks_per_interval(&self, payment_interval: &PaymentInterval) -> u32 {
+            match payment_interval {
+                PaymentInterval::Week => BLOCKS_PER_WEEK,
+                PaymentInterval::Month => BLOCKS_PER_MONTH,
+                PaymentInterval::Custom(blocks) => *blocks,
+            }
+        }
+
+        /// Calculates price of interval
+        fn price_per_interval(&self, payment_interval: &PaymentInterval) -> Result<Balance, Error> {
+            self.price_per_block
+                .checked_mul(self.blocks_per_interval(payment_interval) as u128)
+                .ok_or(Error::ArithmeticOverflow)
+        }
+
+        /// Calculates number of intervals from the last paid block. Nothing is to pay when the
+        /// last payment is ahead of the current block, e.g. after a chain reorg
+        fn to_pay_intervals(
+            &self,
+            payment_interval: PaymentInterval,
+            curr_block: BlockNumber,
+            last_payment_at: BlockNumber,
+        ) -> u32 {
+            curr_block.saturating_sub(last_payment_at) / self.blocks_per_interval(&payment_interval)
+        }
+
+        /// Transfers amount of tokens from the contract's account to the owner account.
+        fn transfer_to_owner(&self, amount: Balance) {
+            if self.transfer(self.owner, amount).is_err() {
+                panic!("failed to transfer tokens to owner")
+            }
+        }
+
+        /// Reimburses the caller with overpaid tokens.
+        /// Panics if the transfer fails - this means this contract's balance is
+        /// too low which means something went wrong.
+        fn reimburse(&mut self, recipient: AccountId, amount: Balance) {
+            if self.transfer(recipient, amount).is_err() {
+                panic!("failed to reimburse the caller")
+            }
+            self.total_refunded
+                .set(&self.total_refunded().saturating_add(amount));
+        }
+
+        /// Collects payment of a given cost from the caller, either from the transferred native
+        /// tokens or from the caller's PSP22 allowance
+        /// Returns:
+        /// * overpaid native token value, to be reimbursed to the caller
+        fn collect_payment(&self, caller: AccountId, cost: Balance) -> Result<Balance, Error> {
+            let transferred_value = self.env().transferred_value();
+            match self.payment_token() {
+                Some(token) => {
+                    if transferred_value > 0 {
+                        return Err(Error::NativeTransferNotAccepted);
+                    }
+                    // Tokens are transferred from the caller's allowance, exactly as needed
+                    psp22::transfer_from(token, caller, self.env().account_id(), cost)?;
+                    Ok(0)
+                }
+                None => transferred_value
+                    .checked_sub(cost)
+                    .ok_or(Error::SubscriptionCostTooHigh(cost)),
+            }
+        }
+
+        /// Transfers tokens from the contract's account, either native or PSP22 ones
+        fn transfer(&self, recipient: AccountId, amount: Balance) -> Result<(), Error> {
+            match self.payment_token() {
+                Some(token) => psp22::transfer(token, recipient, amount),
+                None => Ok(Self::env().transfer(recipient, amount)?),
+            }
+        }
+
+        /// Balance of the contract's account, either in native or PSP22 tokens.
+        /// Panics if the PSP22 token contract can't be called.
+        fn contract_balance(&self) -> Balance {
+            match self.payment_token() {
+                Some(token) => psp22::balance_of(token, self.env().account_id()),
+                None => self.env().balance(),
+            }
+        }
+
+        /// Fails when native tokens are transferred in PSP22 token mode
+        fn reject_native_transfer(&self) -> Result<(), Error> {
+            if self.payment_token().is_some() && self.env().transferred_value() > 0 {
+                return Err(Error::NativeTransferNotAccepted);
+            }
+            Ok(())
+        }
+
+        /// Verifies zero knowledge proof as provided by user, against the verification key
+        /// registered for the required age
+        fn verify_proof(&self, proof: Vec<u8>, required_age: u128) -> Result<(), Error> {
+            if required_age < self.proof_min_required_age {
+                return Err(Error::InvalidProofForMinAgeRequired);
+            }
+            let proof_vk = self
+                .proof_vks
+                .get(required_age)
+                .ok_or(Error::NoVkForAge(required_age))?;
+            let vk_hash = Self::key_hash(proof_vk.as_ref())?;
+            self.env()
+                .extension()
+                .verify(vk_hash, proof, self.proof_public_inputs(required_age)?)
+                .map_err(|_| Error::InvalidProofForMinAgeRequired)
+        }
+
+        /// Hashes zero knowledge proof, identifying it among used proofs
+        fn proof_hash(&self, proof: &[u8]) -> Hash {
+            Hash::from(self.env().hash_bytes::<Blake2x256>(proof))
+        }
+
+        /// Converts verification key hash to the chain extension's key hash.
+        /// Fails when the lengths of the hashes differ, instead of trapping the contract.
+        fn key_hash(vk_hash: &[u8]) -> Result<baby_liminal_extension::KeyHash, Error> {
+            if vk_hash.len() != baby_liminal_extension::KeyHash::len_bytes() {
+                return Err(Error::InvalidVkHashLength);
+            }
+            Ok(baby_liminal_extension::KeyHash::from_slice(vk_hash))
+        }
+
+        /// Generates zero knowledge proof public inputs.
+        /// Caller's address is used as one of the inputs.
+        fn proof_public_inputs(&self, required_age: u128) -> Result<Vec<u8>, Error> {
+            let mut inputs = Vec::<u8>::new();
+            // first input is a required age
+            inputs.extend(required_age.to_le_bytes());
+            // Finite field (Fr) elements are 256-bit so we need to pad with zero
+            inputs.extend([0u8; 16]);
+            // second input is caller's address in two 128-bit chunks
+            let caller = self.env().caller();
+            let bs: &[u8; 32] = caller.as_ref();
+            inputs.extend(
+                u128::from_le_bytes(
+                    bs[..16]
+                        .try_into()
+                        .map_err(|_| Error::ProofCallerAddressNotSerializable)?,
+                )
+                .to_le_bytes(),
+            );
+            inputs.extend([0u8; 16]);
+            inputs.extend(
+                u128::from_le_bytes(
+                    bs[16..]
+                        .try_into()
+                        .map_err(|_| Error::ProofCallerAddressNotSerializable)?,
+                )
+                .to_le_bytes(),
+            );
+            inputs.extend([0u8; 16]);
+
+            Ok(inputs)
+        }
+    }
+
+    /// Calls of the PSP22 token contract used for payments and refunds
+    #[cfg(not(test))]
+    mod psp22 {
+        use ink::env::call::{build_call, ExecutionInput, Selector};
+
+        use super::*;
+
+        /// Selector of the PSP22 token's `transfer` message
+        const PSP22_TRANSFER_SELECTOR: [u8; 4] = ink::selector_bytes!("PSP22::transfer");
+        /// Selector of the PSP22 token's `balance_of` message
+        const PSP22_BALANCE_OF_SELECTOR: [u8; 4] = ink::selector_bytes!("PSP22::balance_of");
+        /// Selector of the PSP22 token's `transfer_from` message
+        const PSP22_TRANSFER_FROM_SELECTOR: [u8; 4] = ink::selector_bytes!("PSP22::transfer_from");
+
+        /// Retrieves PSP22 token balance of an account.
+        /// Panics if the PSP22 token contract can't be called.
+        pub fn balance_of(token: AccountId, owner: AccountId) -> Balance {
+            build_call::<Environment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_BALANCE_OF_SELECTOR)).push_arg(owner),
+                )
+                .returns::<Balance>()
+                .invoke()
+        }
+
+        /// Transfers PSP22 tokens from the calling contract's account to the recipient
+        pub fn transfer(token: AccountId, to: AccountId, value: Balance) -> Result<(), Error> {
+            build_call::<Environment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_TRANSFER_SELECTOR))
+                        .push_arg(to)
+                        .push_arg(value)
+                        .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<Result<(), PSP22Error>>()
+                .try_invoke()
+                .map_err(|_| Error::TokenTransferFailed)?
+                .map_err(|_| Error::TokenTransferFailed)?
+                .map_err(|_| Error::TokenTransferFailed)
+        }
+
+        /// Transfers PSP22 tokens from an account to the recipient. The account must approve the
+        /// calling contract to spend the tokens.
+        pub fn transfer_from(
+            token: AccountId,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<(), Error> {
+            build_call::<Environment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_TRANSFER_FROM_SELECTOR))
+                        .push_arg(from)
+                        .push_arg(to)
+                        .push_arg(value)
+                        .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<Result<(), PSP22Error>>()
+                .try_invoke()
+                .map_err(|_| Error::TokenTransferFailed)?
+                .map_err(|_| Error::TokenTransferFailed)?
+                .map_err(|_| Error::TokenTransferFailed)
+        }
+    }
+
+    /// In-memory PSP22 token ledger replacing the PSP22 token calls in unit tests, as the
+    /// off-chain test environment can't call other contracts. Transfers are recorded, so tests
+    /// can check the transferred amounts. Allowances are not tracked.
+    #[cfg(test)]
+    mod psp22 {
+        use std::cell::RefCell;
+
+        use super::*;
+
+        thread_local! {
+            static BALANCES: RefCell<BTreeMap<AccountId, Balance>> = RefCell::new(BTreeMap::new());
+            static TRANSFERS: RefCell<Vec<(AccountId, AccountId, Balance)>> =
+                RefCell::new(Vec::new());
+        }
+
+        /// Retrieves PSP22 token balance of an account
+        pub fn balance_of(_token: AccountId, owner: AccountId) -> Balance {
+            BALANCES.with(|balances| balances.borrow().get(&owner).copied().unwrap_or_default())
+        }
+
+        /// Transfers PSP22 tokens from the calling contract's account to the recipient
+        pub fn transfer(token: AccountId, to: AccountId, value: Balance) -> Result<(), Error> {
+            let contract = ink::env::account_id::<Environment>();
+            transfer_from(token, contract, to, value)
+        }
+
+        /// Transfers PSP22 tokens from an account to the recipient
+        /// Fails:
+        /// * when the balance of the account is too low
+        pub fn transfer_from(
+            token: AccountId,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<(), Error> {
+            move_tokens(token, from, to, value).map_err(|_| Error::TokenTransferFailed)
+        }
+
+        /// Moves PSP22 tokens between accounts, failing like the PSP22 token contract does
+        fn move_tokens(
+            token: AccountId,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<(), PSP22Error> {
+            let from_balance = balance_of(token, from)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            set_balance(from, from_balance);
+            set_balance(to, balance_of(token, to) + value);
+            TRANSFERS.with(|transfers| transfers.borrow_mut().push((from, to, value)));
+            Ok(())
+        }
+
+        /// Sets PSP22 token balance of an account
+        pub fn set_balance(owner: AccountId, value: Balance) {
+            BALANCES.with(|balances| balances.borrow_mut().insert(owner, value));
+        }
+
+        /// Retrieves transfers made so far as `(from, to, value)`, the oldest first
+        pub fn transfers() -> Vec<(AccountId, AccountId, Balance)> {
+            TRANSFERS.with(|transfers| transfers.borrow().clone())
+        }
+    }
+
+    /// Off-chain simulation harness of this smart contract.
+    /// Allows to write scenario tests of the contract logic in the ink! off-chain test environment,
+    /// without running a node. Must be used from within `#[ink::test]`.
+    #[cfg(feature = "std")]
+    pub mod test_support {
+        use super::*;
+
+        type Env = ink::env::DefaultEnvironment;
+
+        /// Mocks baby_liminal_extension, so every zero knowledge proof is accepted
+        struct AcceptAllProofs;
+
+        impl ink::env::test::ChainExtension for AcceptAllProofs {
+            fn ext_id(&self) -> u16 {
+                baby_liminal_extension::extension_ids::EXTENSION_ID
+            }
+
+            fn call(&mut self, _func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS
+            }
+        }
+
+        /// Subscriptions smart contract deployed to the off-chain test environment
+        pub struct Simulation {
+            /// Owner of the deployed smart contract
+            owner: AccountId,
+            /// Deployed smart contract
+            contract: Subscriptions,
+            /// Number of registrations, distinguishes proofs of the registrations
+            registrations: u32,
+        }
+
+        impl Simulation {
+            /// Deploys the smart contract with a given owner and price per block.
+            /// Zero knowledge proofs of all subscribers are accepted.
+            pub fn new(owner: AccountId, price_per_block: Balance) -> Self {
+                Self::with_grace_intervals(owner, price_per_block, 0)
+            }
+
+            /// Deploys the smart contract like `new`, with a given number of grace intervals
+            pub fn with_grace_intervals(
+                owner: AccountId,
+                price_per_block: Balance,
+                grace_intervals: u32,
+            ) -> Self {
+                ink::env::test::register_chain_extension(AcceptAllProofs);
+                ink::env::test::set_caller::<Env>(owner);
+                let contract = Subscriptions::new(
+                    price_per_block,
+                    Hash::default(),
+                    18,
+                    1,
+                    u32::MAX,
+                    grace_intervals,
+                    u32::MAX,
+                );
+                Self {
+                    owner,
+                    contract,
+                    registrations: 0,
+                }
+            }
+
+            /// Deployed smart contract
+            pub fn contract(&self) -> &Subscriptions {
+                &self.contract
+            }
+
+            /// Deployed smart contract, e.g. to call messages not covered by this harness
+            pub fn contract_mut(&mut self) -> &mut Subscriptions {
+                &mut self.contract
+            }
+
+            /// Registers new subscription for a subscriber. The subscriber is funded with exactly
+            /// the cost of the subscription.
+            pub fn add_subscription(
+                &mut self,
+                subscriber: AccountId,
+                payment_interval: PaymentInterval,
+                intervals_to_pay: u32,
+                external_channel_handle: &str,
+            ) -> Result<(), Error> {
+                let cost =
+                    self.contract.price_per_interval(&payment_interval)? * intervals_to_pay as u128;
+                let balance =
+                    ink::env::test::get_account_balance::<Env>(subscriber).unwrap_or_default();
+                ink::env::test::set_account_balance::<Env>(subscriber, balance + cost);
+                ink::env::test::set_caller::<Env>(subscriber);
+                ink::env::test::transfer_in::<Env>(cost);
+                // every proof can be used only once
+                self.registrations += 1;
+                self.contract.add_subscription(
+                    payment_interval,
+                    intervals_to_pay,
+                    external_channel_handle.to_string(),
+                    self.contract.proof_min_required_age,
+                    self.registrations.to_le_bytes().to_vec(),
+                )
+            }
+
+            /// Cancels subscription of a subscriber
+            pub fn cancel_subscription(&mut self, subscriber: AccountId) -> Result<(), Error> {
+                ink::env::test::set_caller::<Env>(subscriber);
+                ink::env::test::transfer_in::<Env>(0);
+                self.contract.cancel_subscription()
+            }
+
+            /// Runs payment settlement on behalf of the owner
+            pub fn payment_settlement(&mut self) -> Result<(), Error> {
+                ink::env::test::set_caller::<Env>(self.owner);
+                ink::env::test::transfer_in::<Env>(0);
+                self.contract.payment_settlement()
+            }
+
+            /// Advances the chain by a given number of blocks
+            pub fn advance_blocks(&self, blocks: u32) {
+                for _ in 0..blocks {
+                    ink::env::test::advance_block::<Env>();
+                }
+            }
+
+            /// Number of already paid intervals of a subscriber, if subscribed
+            pub fn paid_intervals(&self, subscriber: AccountId) -> Option<u32> {
+                self.contract
+                    .subscriptions
+                    .get(subscriber)
+                    .map(|s| s.paid_intervals)
+            }
+
+            /// Checks if a subscriber has an active subscription
+            pub fn is_subscribed(&self, subscriber: AccountId) -> bool {
+                self.contract.active_subscriptions.contains(&subscriber)
+            }
+
+            /// Token balance of an account
+            pub fn balance(&self, account: AccountId) -> Balance {
+                ink::env::test::get_account_balance::<Env>(account).unwrap_or_default()
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use ink::{
+            env::test::{recorded_events, EmittedEvent},
+            primitives::Hash,
+            scale::Decode as _,
+        };
+
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        pub const ONE_TOKEN: Balance = 1_000_000_000_000;
+        pub const ONE_WEEK_TOKENS: Balance = 604_800;
+        pub const PROOF_VK_HASH: [u8; 32] = [0u8; 32];
+        pub const MIN_REQUIRED_AGE: u128 = 18;
+        pub const MIN_INTERVALS: u32 = 1;
+        pub const MAX_INTERVALS: u32 = u32::MAX;
+        pub const GRACE_INTERVALS: u32 = 0;
+        pub const MAX_DURATION_BLOCKS: u32 = u32::MAX;
+
+        /// Mocks baby_liminal_extension
+        struct MockZKPVerifier {
+            /// Should be one of baby_liminal_extension::status_codes
+            expected_result: u32,
+        }
+
+        impl MockZKPVerifier {
+            pub fn new(expected_result: u32) -> Self {
+                Self { expected_result }
+            }
+        }
+
+        impl ink::env::test::ChainExtension for MockZKPVerifier {
+            fn ext_id(&self) -> u16 {
+                baby_liminal_extension::extension_ids::EXTENSION_ID
+            }
+
+            fn call(&mut self, func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+                assert_eq!(
+                    func_id,
+                    baby_liminal_extension::extension_ids::VERIFY_FUNC_ID
+                );
+                self.expected_result
+            }
+        }
+
+        /// Mocks baby_liminal_extension checking public inputs: a proof is accepted only when it
+        /// starts with the minimum required age passed as the first public input
+        struct MinAgeCheckingVerifier;
+
+        impl MinAgeCheckingVerifier {
+            /// Mocked proof generated for a given minimum age, distinguished by a nonce
+            pub fn proof(min_age: u128, nonce: u8) -> Vec<u8> {
+                let mut proof = min_age.to_le_bytes().to_vec();
+                proof.push(nonce);
+                proof
+            }
+        }
+
+        impl ink::env::test::ChainExtension for MinAgeCheckingVerifier {
+            fn ext_id(&self) -> u16 {
+                baby_liminal_extension::extension_ids::EXTENSION_ID
+            }
+
+            fn call(&mut self, func_id: u16, input: &[u8], _output: &mut Vec<u8>) -> u32 {
+                assert_eq!(
+                    func_id,
+                    baby_liminal_extension::extension_ids::VERIFY_FUNC_ID
+                );
+                // input is the encoded verification key hash, proof and public inputs
+                let (proof, public_inputs) =
+                    <(Vec<u8>, Vec<u8>)>::decode(&mut &input[32..]).expect("invalid input");
+                if proof[..16] == public_inputs[..16] {
+                    baby_liminal_extension::status_codes::VERIFY_SUCCESS
+                } else {
+                    baby_liminal_extension::status_codes::VERIFY_VERIFICATION_FAIL
+                }
+            }
+        }
+
+        /// We test a simple use case of our contract.
+        #[ink::test]
+        fn it_works() {
+            // register baby liminal extension, used for zero knowlege proof verification
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let proof = vec![0u8; 60];
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 0);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            assert_eq!(&subscriptions.owner, &accounts.bob);
+            assert_eq!(subscriptions.price_per_block, 1u128);
+
+            // prepare balance for the caller
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                2 * ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+            // add subscription
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof,
+                )
+                .unwrap();
+            assert!(subscriptions.subscriptions.contains(accounts.charlie));
+            assert!(subscriptions
+                .active_subscriptions
+                .contains(&accounts.charlie));
+
+            // bob, an owner of the contract should get payment
+            assert_eq!(
+                ONE_WEEK_TOKENS,
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap()
+            );
+            // overpaid tokens should be returned to charlie
+            assert_eq!(
+                2 * ONE_TOKEN - ONE_WEEK_TOKENS,
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                )
+                .unwrap()
+            );
+
+            // test recorded events
+            let events = recorded_events().collect::<Vec<_>>();
+            assert_new_subscription(&events[0], accounts.charlie, "1111".to_string());
+        }
+
+        #[ink::test]
+        fn proof_verification_fails() {
+            // register baby liminal extension, used for zero knowlege proof verification
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_VERIFICATION_FAIL,
+            ));
+            let proof = vec![0u8; 60];
+
+            let mut subscriptions = Subscriptions::new(
+                0u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            // add subscription failes becase of failed verification
+            assert!(subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof
+                )
+                .is_err());
+        }
+
+        #[ink::test]
+        fn set_min_required_age_works() {
+            ink::env::test::register_chain_extension(MinAgeCheckingVerifier);
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                0u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    MinAgeCheckingVerifier::proof(18, 1),
+                )
+                .unwrap();
+
+            // only the owner can change the minimum age
+            assert_eq!(
+                subscriptions.set_min_required_age(21),
+                Err(Error::NotAuthorized)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let events_before = recorded_events().count();
+            subscriptions.set_min_required_age(21).unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            let decoded_event = <MinRequiredAgeUpdated>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!((decoded_event.old, decoded_event.new), (18, 21));
+            assert_eq!(subscriptions.age_policy().0, 21);
+
+            // the key of the initial age doesn't verify proofs of the new one
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "2222".to_string(),
+                    21,
+                    MinAgeCheckingVerifier::proof(21, 5),
+                ),
+                Err(Error::NoVkForAge(21))
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            subscriptions
+                .register_vk_for_age(21, Hash::from(PROOF_VK_HASH))
+                .unwrap();
+
+            // ages below the new minimum are rejected
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "2222".to_string(),
+                    MIN_REQUIRED_AGE,
+                    MinAgeCheckingVerifier::proof(18, 2),
+                ),
+                Err(Error::InvalidProofForMinAgeRequired)
+            );
+            // proofs generated for the previous age are rejected
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "2222".to_string(),
+                    21,
+                    MinAgeCheckingVerifier::proof(18, 3),
+                ),
+                Err(Error::InvalidProofForMinAgeRequired)
+            );
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "2222".to_string(),
+                    21,
+                    MinAgeCheckingVerifier::proof(21, 4),
+                )
+                .unwrap();
+            assert_eq!(subscriptions.subscriptions_count(), 2);
+        }
+
+        #[ink::test]
+        fn register_vk_for_age_works() {
+            ink::env::test::register_chain_extension(MinAgeCheckingVerifier);
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                0u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            // no verification key for the 21+ tier yet
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    21,
+                    MinAgeCheckingVerifier::proof(21, 1),
+                ),
+                Err(Error::NoVkForAge(21))
+            );
+
+            // only the owner can register verification keys
+            assert_eq!(
+                subscriptions.register_vk_for_age(21, Hash::from(PROOF_VK_HASH)),
+                Err(Error::NotAuthorized)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            subscriptions
+                .register_vk_for_age(21, Hash::from(PROOF_VK_HASH))
+                .unwrap();
+
+            // both tiers are accepted, each with a proof of its own age
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    21,
+                    MinAgeCheckingVerifier::proof(21, 2),
+                )
+                .unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "2222".to_string(),
+                    21,
+                    MinAgeCheckingVerifier::proof(18, 3),
+                ),
+                Err(Error::InvalidProofForMinAgeRequired)
+            );
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "2222".to_string(),
+                    MIN_REQUIRED_AGE,
+                    MinAgeCheckingVerifier::proof(18, 4),
+                )
+                .unwrap();
+            assert_eq!(subscriptions.subscriptions_count(), 2);
+        }
+
+        #[ink::test]
+        fn malformed_vk_hash_is_rejected() {
+            assert!(Subscriptions::key_hash(&PROOF_VK_HASH).is_ok());
+            assert_eq!(
+                Subscriptions::key_hash(&PROOF_VK_HASH[1..]),
+                Err(Error::InvalidVkHashLength)
+            );
+            assert_eq!(
+                Subscriptions::key_hash(&[0u8; 33]),
+                Err(Error::InvalidVkHashLength)
+            );
+        }
+
+        #[ink::test]
+        fn add_subscription_with_used_proof_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            let proof = vec![7u8; 60];
+
+            for subscriber in [accounts.bob, accounts.charlie] {
+                ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                    subscriber, ONE_TOKEN,
+                );
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_WEEK_TOKENS);
+            simulation
+                .contract_mut()
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof.clone(),
+                )
+                .unwrap();
+
+            // the same proof is rejected, even after the subscription is cancelled
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            for subscriber in [accounts.bob, accounts.charlie] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(subscriber);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_WEEK_TOKENS);
+                assert_eq!(
+                    simulation.contract_mut().add_subscription(
+                        PaymentInterval::Week,
+                        1,
+                        "1111".to_string(),
+                        MIN_REQUIRED_AGE,
+                        proof.clone()
+                    ),
+                    Err(Error::ProofAlreadyUsed)
+                );
+                assert!(!simulation.is_subscribed(subscriber));
+            }
+        }
+
+        #[ink::test]
+        fn cancel_subscription_works() {
+            // register baby liminal extension, used for zero knowlege proof verification
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let proof = vec![0u8; 60];
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // setup Bob as a contract owner
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 0);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            // prepare balance for the Charlie as the contract caller
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+            // add subscription
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof,
+                )
+                .unwrap();
+            assert!(subscriptions.subscriptions.contains(accounts.charlie));
+            assert!(subscriptions
+                .active_subscriptions
+                .contains(&accounts.charlie));
+
+            // Charlie cancels subscription
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
+            subscriptions.cancel_subscription().unwrap();
+            assert!(!subscriptions.subscriptions.contains(accounts.charlie));
+            assert!(!subscriptions
+                .active_subscriptions
+                .contains(&accounts.charlie));
+
+            // test if remaining tokens are returned to the Charlie
+            assert_eq!(
+                ONE_TOKEN - ONE_WEEK_TOKENS,
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
+                    accounts.charlie
+                )
+                .unwrap()
+            );
+            // test recorded events
+            let events = recorded_events().collect::<Vec<_>>();
+            assert_new_subscription(&events[0], accounts.charlie, "1111".to_string());
+            assert_cancelled_subscription(&events[1], accounts.charlie);
+        }
+
+        #[ink::test]
+        fn renew_subscription_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 2, "1111")
+                .unwrap();
+            let events_before = recorded_events().count();
+
+            // Django has no subscription to renew
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                simulation.contract_mut().renew_subscription(1),
+                Err(Error::NotRegisterred(accounts.django))
+            );
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                simulation.contract_mut().renew_subscription(0),
+                Err(Error::InvalidIntervalsToPay(0))
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_WEEK_TOKENS);
+            assert_eq!(
+                simulation.contract_mut().renew_subscription(2),
+                Err(Error::SubscriptionCostTooHigh(2 * ONE_WEEK_TOKENS))
+            );
+
+            // overpaid tokens are reimbursed
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(3 * ONE_WEEK_TOKENS + 5);
+            simulation.contract_mut().renew_subscription(3).unwrap();
+            assert_eq!(
+                simulation.balance(accounts.charlie),
+                ONE_TOKEN - 3 * ONE_WEEK_TOKENS
+            );
+
+            let s = simulation
+                .contract()
+                .subscriptions
+                .get(accounts.charlie)
+                .unwrap();
+            assert_eq!(s.declared_payment_intervals, 5);
+            assert_eq!(s.paid_intervals, 1);
+
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            assert_eq!(events.len(), 1);
+            let decoded_event = <RenewedSubscription>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!(decoded_event.for_account, accounts.charlie);
+            assert_eq!(decoded_event.declared_payment_intervals, 5);
+        }
+
+        #[ink::test]
+        fn recent_cancellations_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 1, "2222")
+                .unwrap();
+            simulation
+                .add_subscription(accounts.django, PaymentInterval::Week, 3, "3333")
+                .unwrap();
+            assert_eq!(simulation.contract().recent_cancellations(), vec![]);
+
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            let events = recorded_events().collect::<Vec<_>>();
+            let decoded_event =
+                <CancelledSubscription>::decode(&mut &events.last().unwrap().data[..])
+                    .expect("invalid event buffer");
+            assert_eq!(decoded_event.reason, CancellationReason::UserRequested);
+
+            // Charlie runs out of funds
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            let events = recorded_events().collect::<Vec<_>>();
+            let decoded_event =
+                <CancelledSubscriptions>::decode(&mut &events.last().unwrap().data[..])
+                    .expect("invalid event buffer");
+            assert_eq!(decoded_event.reason, CancellationReason::NonPayment);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            simulation
+                .contract_mut()
+                .owner_cancel_many(vec![accounts.django])
+                .unwrap();
+
+            assert_eq!(
+                simulation.contract().recent_cancellations(),
+                vec![
+                    (accounts.bob, CancellationReason::UserRequested),
+                    (accounts.charlie, CancellationReason::NonPayment),
+                    (accounts.django, CancellationReason::OwnerRevoked),
+                ]
+            );
+        }
+
+        #[ink::test]
+        fn recent_cancellations_are_bounded() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            for i in 0..=MAX_RECENT_CANCELLATIONS {
+                let account = AccountId::from([i as u8; 32]);
+                simulation
+                    .add_subscription(account, PaymentInterval::Week, 1, "1111")
+                    .unwrap();
+                simulation.cancel_subscription(account).unwrap();
+            }
+
+            // the oldest cancellation is dropped
+            let cancellations = simulation.contract().recent_cancellations();
+            assert_eq!(cancellations.len(), MAX_RECENT_CANCELLATIONS);
+            assert_eq!(cancellations[0].0, AccountId::from([1u8; 32]));
+        }
+
+        #[ink::test]
+        fn top_up_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            let events_before = recorded_events().count();
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(
+                simulation.contract_mut().top_up(0),
+                Err(Error::InvalidIntervalsToPay(0))
+            );
+
+            // overpaid tokens are reimbursed
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_WEEK_TOKENS + 5);
+            simulation.contract_mut().top_up(1).unwrap();
+            assert_eq!(
+                simulation.balance(accounts.charlie),
+                ONE_TOKEN - ONE_WEEK_TOKENS
+            );
+            let s = simulation
+                .contract()
+                .subscriptions
+                .get(accounts.charlie)
+                .unwrap();
+            assert_eq!(s.declared_payment_intervals, 4);
+            assert_eq!(s.paid_intervals, 1);
+
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            assert_eq!(events.len(), 1);
+            let decoded_event = <SubscriptionToppedUp>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!(decoded_event.for_account, accounts.charlie);
+            assert_eq!(decoded_event.additional_intervals, 1);
+            assert_eq!(decoded_event.declared_payment_intervals, 4);
+        }
+
+        #[ink::test]
+        fn top_up_about_to_expire_subscription_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            // the only declared interval is paid at the registration
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 1, "1111")
+                .unwrap();
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+
+            // the subscription lapsed, but hasn't been settled yet
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(2 * ONE_WEEK_TOKENS);
+            simulation.contract_mut().top_up(2).unwrap();
+
+            // so the settlement charges the next interval instead of cancelling it
+            simulation.payment_settlement().unwrap();
+            assert!(simulation.is_subscribed(accounts.charlie));
+            assert_eq!(simulation.paid_intervals(accounts.charlie), Some(2));
+        }
+
+        #[ink::test]
+        fn cancel_subscription_removes_all_subscriber_state() {
+            // register baby liminal extension, used for zero knowlege proof verification
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let proof = vec![0u8; 60];
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            // Charlie registers and cancels subscription
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                2 * ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    2,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof.clone(),
+                )
+                .unwrap();
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
+            subscriptions.cancel_subscription().unwrap();
+
+            // no state is left for Charlie
+            assert!(!subscriptions.subscriptions.contains(accounts.charlie));
+            assert!(!subscriptions.charge_bucket_of.contains(accounts.charlie));
+            assert_eq!(
+                subscriptions.get_active_subscriptions().unwrap(),
+                (vec![], false)
+            );
+            assert_eq!(
+                subscriptions.cancel_subscription(),
+                Err(Error::NotRegisterred(accounts.charlie))
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                subscriptions.settle_account(accounts.charlie),
+                Err(Error::NotRegisterred(accounts.charlie))
+            );
+
+            // Charlie can register again, with a new proof
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+            assert!(subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "2222".to_string(),
+                    MIN_REQUIRED_AGE,
+                    vec![1u8; 60]
+                )
+                .is_ok());
+        }
+
+        #[ink::test]
+        fn get_active_subscriptions_works() {
+            // register baby liminal extension, used for zero knowlege proof verification
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let proof = vec![0u8; 60];
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                0u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            // prepare balance for the Charlie as the contract caller
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+            // add subscription
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof,
+                )
+                .unwrap();
+            assert!(subscriptions.subscriptions.contains(accounts.charlie));
+            assert!(subscriptions
+                .active_subscriptions
+                .contains(&accounts.charlie));
+
+            // test list of active subscriptions
+            assert_eq!(
+                subscriptions.get_active_subscriptions().unwrap(),
+                (
+                    vec![ActiveSubscriptionAttr {
+                        for_account: accounts.charlie,
+                        external_channel_handle: "1111".as_bytes().to_vec()
+                    }],
+                    false
+                )
+            );
+        }
+
+        #[ink::test]
+        fn total_subscriptions_created_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(simulation.contract().total_subscriptions_created(), 0);
+
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            assert_eq!(simulation.contract().total_subscriptions_created(), 1);
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Month, 2, "2222")
+                .unwrap();
+            assert_eq!(simulation.contract().total_subscriptions_created(), 2);
+
+            // cancellation doesn't decrease the total, registering again increases it
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            assert_eq!(simulation.contract().subscriptions_count(), 1);
+            assert_eq!(simulation.contract().total_subscriptions_created(), 2);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 1, "1111")
+                .unwrap();
+            assert_eq!(simulation.contract().total_subscriptions_created(), 3);
+        }
+
+        #[ink::test]
+        fn lifetime_intervals_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(simulation.contract().lifetime_intervals(accounts.bob), 0);
+
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.contract().lifetime_intervals(accounts.bob), 2);
+
+            // counter persists across cancel and resubscribe
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            assert_eq!(simulation.contract().lifetime_intervals(accounts.bob), 2);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 2, "1111")
+                .unwrap();
+            assert_eq!(simulation.contract().lifetime_intervals(accounts.bob), 3);
+
+            // and keeps accumulating on settlement
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.contract().lifetime_intervals(accounts.bob), 4);
+        }
+
+        #[ink::test]
+        fn get_subscription_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(
+                simulation.contract().get_subscription(accounts.bob).err(),
+                Some(Error::NotRegisterred(accounts.bob))
+            );
+
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Month, 2, "1111")
+                .unwrap();
+            let s = simulation
+                .contract()
+                .get_subscription(accounts.bob)
+                .unwrap();
+            assert_eq!(s.payment_interval, PaymentInterval::Month);
+            assert_eq!(s.declared_payment_intervals, 2);
+            assert_eq!(s.paid_intervals, 1);
+            assert_eq!(s.price_per_interval, BLOCKS_PER_MONTH as u128);
+            assert_eq!(s.external_channel_handle, "1111");
+
+            // subscription is gone once cancelled
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            assert_eq!(
+                simulation.contract().get_subscription(accounts.bob).err(),
+                Some(Error::NotRegisterred(accounts.bob))
+            );
+        }
+
+        #[ink::test]
+        fn raw_subscription_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(
+                simulation.contract().raw_subscription(accounts.bob).err(),
+                Some(Error::NotRegisterred(accounts.bob))
+            );
+
+            simulation.advance_blocks(5);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            let s = simulation
+                .contract()
+                .raw_subscription(accounts.bob)
+                .unwrap();
+            assert_eq!(s.payment_interval, PaymentInterval::Week);
+            assert_eq!(s.declared_payment_intervals, 3);
+            assert_eq!(s.paid_intervals, 1);
+            assert_eq!(s.price_per_interval, ONE_WEEK_TOKENS);
+            assert_eq!(s.registered_at, 5);
+            assert_eq!(s.last_payment_at, 5);
+            assert_eq!(s.external_channel_handle, "1111");
+            assert!(!s.frozen);
+            assert_eq!(s.frozen_at, 0);
+
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            let s = simulation
+                .contract()
+                .raw_subscription(accounts.bob)
+                .unwrap();
+            assert_eq!(s.declared_payment_intervals, 3);
+            assert_eq!(s.paid_intervals, 2);
+            assert_eq!(s.price_per_interval, ONE_WEEK_TOKENS);
+            assert_eq!(s.registered_at, 5);
+            assert_eq!(s.last_payment_at, 5 + BLOCKS_PER_WEEK);
+            assert_eq!(s.external_channel_handle, "1111");
+        }
+
+        #[ink::test]
+        fn get_active_subscriptions_order_is_stable() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            let order = |simulation: &test_support::Simulation| {
+                let (subs, _) = simulation.contract().get_active_subscriptions().unwrap();
+                subs.into_iter()
+                    .map(|sub| sub.for_account)
+                    .collect::<Vec<_>>()
+            };
+
+            for subscriber in [accounts.bob, accounts.charlie, accounts.django] {
+                simulation
+                    .add_subscription(subscriber, PaymentInterval::Week, 3, "1111")
+                    .unwrap();
+                simulation.advance_blocks(1);
+            }
+            assert_eq!(
+                order(&simulation),
+                vec![accounts.bob, accounts.charlie, accounts.django]
+            );
+
+            simulation.cancel_subscription(accounts.charlie).unwrap();
+            simulation
+                .add_subscription(accounts.eve, PaymentInterval::Week, 3, "2222")
+                .unwrap();
+            assert_eq!(
+                order(&simulation),
+                vec![accounts.bob, accounts.django, accounts.eve]
+            );
+
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 3, "3333")
+                .unwrap();
+            assert_eq!(
+                order(&simulation),
+                vec![accounts.django, accounts.eve, accounts.charlie]
+            );
+        }
+
+        #[ink::test]
+        fn get_active_subscriptions_paged_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(simulation.contract().subscriptions_count(), 0);
+            let subscribers = [
+                accounts.bob,
+                accounts.charlie,
+                accounts.django,
+                accounts.eve,
+            ];
+            for subscriber in subscribers {
+                simulation
+                    .add_subscription(subscriber, PaymentInterval::Week, 3, "1111")
+                    .unwrap();
+            }
+            assert_eq!(simulation.contract().subscriptions_count(), 4);
+
+            // drive the pagination loop with pages of 3 subscriptions
+            let mut paged = vec![];
+            let mut start = 0;
+            while start < simulation.contract().subscriptions_count() {
+                let page = simulation
+                    .contract()
+                    .get_active_subscriptions_paged(start, 3)
+                    .unwrap();
+                assert!(page.len() <= 3);
+                paged.extend(page.into_iter().map(|sub| sub.for_account));
+                start += 3;
+            }
+            assert_eq!(paged, subscribers.to_vec());
+
+            // start past the end returns no subscriptions
+            assert!(simulation
+                .contract()
+                .get_active_subscriptions_paged(4, 3)
+                .unwrap()
+                .is_empty());
+            assert!(simulation
+                .contract()
+                .get_active_subscriptions_paged(u32::MAX, u32::MAX)
+                .unwrap()
+                .is_empty());
+        }
+
+        #[ink::test]
+        fn get_active_subscriptions_signals_truncation() {
+            // register baby liminal extension, used for zero knowlege proof verification
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+
+            let mut subscriptions = Subscriptions::new(
+                0u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            // register one subscription more than returned by a single query
+            for i in 0..=MAX_RETURNED {
+                let account = AccountId::from([i as u8; 32]);
+                ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(account, 0);
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(account);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
+                subscriptions
+                    .add_subscription(
+                        PaymentInterval::Week,
+                        1,
+                        "1111".to_string(),
+                        MIN_REQUIRED_AGE,
+                        vec![i as u8; 60],
+                    )
+                    .unwrap();
+            }
+
+            let (subs, more) = subscriptions.get_active_subscriptions().unwrap();
+            assert_eq!(subs.len(), MAX_RETURNED);
+            assert!(more);
+        }
+
+        #[ink::test]
+        fn payment_settlement_works() {
+            // register baby liminal extension, used for zero knowlege proof verification
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let proof = vec![0u8; 60];
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            // register subscription for Bob
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.bob,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    2,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    vec![1u8; 60],
+                )
+                .unwrap();
+            // register subscription for Charlie
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                3 * ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+            // add subscription
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    3,
+                    "2222".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof,
+                )
+                .unwrap();
+
+            assert!(subscriptions.subscriptions.contains(accounts.bob));
+            assert!(subscriptions.active_subscriptions.contains(&accounts.bob));
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.bob)
+                    .unwrap()
+                    .paid_intervals,
+                1
+            );
+            assert!(subscriptions.subscriptions.contains(accounts.charlie));
+            assert!(subscriptions
+                .active_subscriptions
+                .contains(&accounts.charlie));
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.charlie)
+                    .unwrap()
+                    .paid_intervals,
+                1
+            );
+
+            // advance one block and execute payment settlement
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert!(subscriptions.payment_settlement().is_ok());
+
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.bob)
+                    .unwrap()
+                    .paid_intervals,
+                1
+            );
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.charlie)
+                    .unwrap()
+                    .paid_intervals,
+                1
+            );
+
+            // advance one week of blocks, both bob and charlie should still have active subscriptions
+            for _ in 0..BLOCKS_PER_WEEK {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert!(subscriptions.payment_settlement().is_ok());
+            assert!(subscriptions.subscriptions.get(accounts.bob).is_some());
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.bob)
+                    .unwrap()
+                    .paid_intervals,
+                2
+            );
+            assert!(subscriptions.subscriptions.get(accounts.charlie).is_some());
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.charlie)
+                    .unwrap()
+                    .paid_intervals,
+                2
+            );
+
+            // advance one more week of blocks, bob's subscription should be cancelled.  Charlie should still have active subscription
+            for _ in 0..BLOCKS_PER_WEEK {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert!(subscriptions.payment_settlement().is_ok());
+            assert!(subscriptions.subscriptions.get(accounts.bob).is_none());
+            assert!(subscriptions.subscriptions.get(accounts.charlie).is_some());
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.charlie)
+                    .unwrap()
+                    .paid_intervals,
+                3
+            );
+
+            // test emitted events
+            let events = recorded_events().collect::<Vec<_>>();
+            assert_new_subscription(&events[0], accounts.bob, "1111".to_string());
+            assert_new_subscription(&events[1], accounts.charlie, "2222".to_string());
+            assert_payment_settled(&events[2], accounts.bob, 1, ONE_WEEK_TOKENS);
+            assert_payment_settled(&events[3], accounts.charlie, 1, ONE_WEEK_TOKENS);
+            // bob's subscription is cancelled, not charged
+            assert_payment_settled(&events[4], accounts.charlie, 1, ONE_WEEK_TOKENS);
+            assert_cancelled_subscriptions(
+                &events[5],
+                vec![ActiveSubscriptionAttr {
+                    for_account: accounts.bob,
+                    external_channel_handle: "1111".as_bytes().to_vec(),
+                }],
+            );
+        }
+
+        #[ink::test]
+        fn owner_cancel_many_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            for (subscriber, handle) in [(accounts.bob, "1111"), (accounts.charlie, "2222")] {
+                simulation
+                    .add_subscription(subscriber, PaymentInterval::Week, 3, handle)
+                    .unwrap();
+            }
+            let bob_balance = simulation.balance(accounts.bob);
+            let charlie_balance = simulation.balance(accounts.charlie);
+
+            // only the owner can cancel subscriptions
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation
+                    .contract_mut()
+                    .owner_cancel_many(vec![accounts.charlie]),
+                Err(Error::NotAuthorized)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                simulation
+                    .contract_mut()
+                    .owner_cancel_many(vec![accounts.bob; MAX_RETURNED + 1]),
+                Err(Error::TooManyAccounts(MAX_RETURNED as u32))
+            );
+
+            // Django has no subscription, so the account is skipped
+            let cancelled = simulation
+                .contract_mut()
+                .owner_cancel_many(vec![accounts.bob, accounts.django, accounts.charlie])
+                .unwrap();
+            assert_eq!(cancelled, vec![accounts.bob, accounts.charlie]);
+            assert!(!simulation.is_subscribed(accounts.bob));
+            assert!(!simulation.is_subscribed(accounts.charlie));
+
+            // two unpaid intervals are refunded
+            assert_eq!(
+                simulation.balance(accounts.bob),
+                bob_balance + 2 * ONE_WEEK_TOKENS
+            );
+            assert_eq!(
+                simulation.balance(accounts.charlie),
+                charlie_balance + 2 * ONE_WEEK_TOKENS
+            );
+
+            let events = recorded_events().collect::<Vec<_>>();
+            assert_cancelled_subscriptions(
+                events.last().unwrap(),
+                vec![
+                    ActiveSubscriptionAttr {
+                        for_account: accounts.bob,
+                        external_channel_handle: "1111".as_bytes().to_vec(),
+                    },
+                    ActiveSubscriptionAttr {
+                        for_account: accounts.charlie,
+                        external_channel_handle: "2222".as_bytes().to_vec(),
+                    },
+                ],
+            );
+        }
+
+        #[ink::test]
+        fn settle_account_works() {
+            // register baby liminal extension, used for zero knowlege proof verification
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+
+            // register subscriptions for Bob and Charlie
+            for (account, handle) in [(accounts.bob, "1111"), (accounts.charlie, "2222")] {
+                ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                    account, ONE_TOKEN,
+                );
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(account);
+                ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+                subscriptions
+                    .add_subscription(
+                        PaymentInterval::Week,
+                        3,
+                        handle.to_string(),
+                        MIN_REQUIRED_AGE,
+                        account.as_ref().to_vec(),
+                    )
+                    .unwrap();
+            }
+
+            // advance one week of blocks, both subscriptions are overdue
+            for _ in 0..BLOCKS_PER_WEEK {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+
+            // only owner is allowed to settle an account
+            assert_eq!(
+                subscriptions.settle_account(accounts.bob),
+                Err(Error::NotAuthorized)
+            );
+
+            // settle Bob only
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                subscriptions.settle_account(accounts.bob),
+                Ok(SettlementReport {
+                    charged_intervals: 1,
+                    charged_amount: ONE_WEEK_TOKENS,
+                    cancelled: false,
+                })
+            );
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.bob)
+                    .unwrap()
+                    .paid_intervals,
+                2
+            );
+            assert_eq!(
+                subscriptions
+                    .subscriptions
+                    .get(accounts.charlie)
+                    .unwrap()
+                    .paid_intervals,
+                1
+            );
+
+            // account without subscription can't be settled
+            assert_eq!(
+                subscriptions.settle_account(accounts.django),
+                Err(Error::NotRegisterred(accounts.django))
+            );
+        }
+
+        #[ink::test]
+        fn to_pay_intervals_with_last_payment_ahead_of_current_block() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            simulation.advance_blocks(10);
+
+            // last payment is recorded ahead of the current block
+            let curr_block = ink::env::block_number::<ink::env::DefaultEnvironment>();
+            let mut s = simulation
+                .contract()
+                .subscriptions
+                .get(accounts.charlie)
+                .unwrap();
+            s.last_payment_at = curr_block + 100;
+            simulation
+                .contract_mut()
+                .subscriptions
+                .insert(accounts.charlie, &s);
+
+            assert_eq!(
+                simulation.contract().to_pay_intervals(
+                    PaymentInterval::Week,
+                    curr_block,
+                    s.last_payment_at
+                ),
+                0
+            );
+            // nothing is charged and the subscription stays active
+            simulation.payment_settlement().unwrap();
+            assert!(simulation.is_subscribed(accounts.charlie));
+            assert_eq!(simulation.paid_intervals(accounts.charlie), Some(1));
+        }
+
+        #[ink::test]
+        fn blocks_until_next_charge_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 2, "1111")
+                .unwrap();
+
+            let subscriptions = simulation.contract();
+            assert_eq!(
+                subscriptions.blocks_until_next_charge(accounts.bob),
+                Ok(BLOCKS_PER_WEEK)
+            );
+            assert_eq!(
+                subscriptions.blocks_until_next_charge(accounts.charlie),
+                Err(Error::NotRegisterred(accounts.charlie))
+            );
+
+            // countdown decreases as blocks advance
+            simulation.advance_blocks(10);
+            assert_eq!(
+                simulation.contract().blocks_until_next_charge(accounts.bob),
+                Ok(BLOCKS_PER_WEEK - 10)
+            );
+
+            // overdue charge
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            assert_eq!(
+                simulation.contract().blocks_until_next_charge(accounts.bob),
+                Ok(0)
+            );
+        }
+
+        #[ink::test]
+        fn quote_cancellation_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 4, "1111")
+                .unwrap();
+            assert_eq!(
+                simulation.contract().quote_cancellation(accounts.charlie),
+                Err(Error::NotRegisterred(accounts.charlie))
+            );
+            assert_eq!(
+                simulation.contract().quote_cancellation(accounts.bob),
+                Ok(3 * ONE_WEEK_TOKENS)
+            );
+
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            let quote = simulation
+                .contract()
+                .quote_cancellation(accounts.bob)
+                .unwrap();
+            assert_eq!(quote, 2 * ONE_WEEK_TOKENS);
+
+            let balance_before = simulation.balance(accounts.bob);
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            assert_eq!(simulation.balance(accounts.bob) - balance_before, quote);
+        }
+
+        #[ink::test]
+        fn coverage_summary_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            let registered_at = ink::env::block_number::<ink::env::DefaultEnvironment>();
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+
+            assert_eq!(
+                simulation.contract().coverage_summary(accounts.bob),
+                Ok(CoverageSummary {
+                    payment_interval: PaymentInterval::Week,
+                    registered_at,
+                    paid_intervals: 1,
+                    declared_payment_intervals: 3,
+                    paid_through_block: registered_at + BLOCKS_PER_WEEK,
+                    expiry_block: registered_at + 3 * BLOCKS_PER_WEEK,
+                })
+            );
+            assert_eq!(
+                simulation.contract().coverage_summary(accounts.charlie),
+                Err(Error::NotRegisterred(accounts.charlie))
+            );
+
+            // settlement extends paid coverage, but not the expiry
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            let summary = simulation
+                .contract()
+                .coverage_summary(accounts.bob)
+                .unwrap();
+            assert_eq!(summary.paid_intervals, 2);
+            assert_eq!(
+                summary.paid_through_block,
+                registered_at + 2 * BLOCKS_PER_WEEK
+            );
+            assert_eq!(summary.expiry_block, registered_at + 3 * BLOCKS_PER_WEEK);
+        }
+
+        #[ink::test]
+        fn age_policy_works() {
+            let subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+            assert_eq!(subscriptions.age_policy(), (18, 120));
+        }
+
+        #[ink::test]
+        fn min_intervals_policy_works() {
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let proof = vec![0u8; 60];
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions =
+                Subscriptions::new(1u128, Hash::from(PROOF_VK_HASH), MIN_REQUIRED_AGE, 4, 12, 0);
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(4 * ONE_WEEK_TOKENS);
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    2,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof.clone()
+                ),
+                Err(Error::BelowMinimumIntervals(4))
+            );
+            assert!(!subscriptions.subscriptions.contains(accounts.charlie));
+
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    4,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof,
+                )
+                .unwrap();
+            assert!(subscriptions.subscriptions.contains(accounts.charlie));
+        }
+
+        #[ink::test]
+        fn max_intervals_policy_works() {
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let proof = vec![0u8; 60];
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                12,
+                0,
+            );
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(13 * ONE_WEEK_TOKENS);
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    13,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof.clone()
+                ),
+                Err(Error::AboveMaximumIntervals(12))
+            );
+            assert!(!subscriptions.subscriptions.contains(accounts.charlie));
+
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(12 * ONE_WEEK_TOKENS);
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    12,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof,
+                )
+                .unwrap();
+            assert!(subscriptions.subscriptions.contains(accounts.charlie));
+        }
+
+        #[ink::test]
+        fn prices_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let simulation = test_support::Simulation::new(accounts.alice, 3u128);
+
+            let (week, month) = simulation.contract().prices();
+            assert_eq!(week, 3 * BLOCKS_PER_WEEK as u128);
+            assert_eq!(month, 3 * BLOCKS_PER_MONTH as u128);
+            assert_eq!(
+                month * BLOCKS_PER_WEEK as u128,
+                week * BLOCKS_PER_MONTH as u128
+            );
+        }
+
+        #[ink::test]
+        fn custom_payment_interval_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation.contract_mut().add_subscription(
+                    PaymentInterval::Custom(0),
+                    3,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    vec![]
+                ),
+                Err(Error::InvalidPaymentInterval)
+            );
+
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Custom(10), 3, "2222")
+                .unwrap();
+            let s = simulation
+                .contract()
+                .subscriptions
+                .get(accounts.charlie)
+                .unwrap();
+            assert_eq!(s.price_per_interval, 10);
+
+            // one interval is charged every 10 blocks
+            simulation.advance_blocks(10);
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.paid_intervals(accounts.charlie), Some(2));
+            simulation.advance_blocks(15);
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.paid_intervals(accounts.charlie), Some(3));
+        }
+
+        #[ink::test]
+        fn set_price_per_block_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+
+            // only the owner can change the price
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation.contract_mut().set_price_per_block(2),
+                Err(Error::NotAuthorized)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let events_before = recorded_events().count();
+            simulation.contract_mut().set_price_per_block(2).unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            let decoded_event =
+                <PriceUpdated>::decode(&mut &events[0].data[..]).expect("invalid event buffer");
+            assert_eq!((decoded_event.old, decoded_event.new), (1, 2));
+            assert_eq!(
+                simulation.contract().prices(),
+                (2 * ONE_WEEK_TOKENS, 2 * BLOCKS_PER_MONTH as u128)
+            );
+
+            // new subscriptions are charged the new price
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 3, "2222")
+                .unwrap();
+            assert_eq!(
+                simulation
+                    .contract()
+                    .subscriptions
+                    .get(accounts.charlie)
+                    .unwrap()
+                    .price_per_interval,
+                2 * ONE_WEEK_TOKENS
+            );
+
+            // while the existing subscription still settles at its original price
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            let owner_balance = simulation.balance(accounts.alice);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let report = simulation
+                .contract_mut()
+                .settle_account(accounts.bob)
+                .unwrap();
+            assert_eq!(report.charged_amount, ONE_WEEK_TOKENS);
+            assert_eq!(
+                simulation.balance(accounts.alice),
+                owner_balance + ONE_WEEK_TOKENS
+            );
+        }
+
+        #[ink::test]
+        fn would_accept_age_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let simulation = test_support::Simulation::new(accounts.alice, 1u128);
+
+            assert!(!simulation.contract().would_accept_age(17));
+            assert!(simulation.contract().would_accept_age(18));
+            assert!(simulation.contract().would_accept_age(PROOF_MAX_AGE - 1));
+            assert!(!simulation.contract().would_accept_age(PROOF_MAX_AGE));
+        }
+
+        #[ink::test]
+        fn set_subscription_price_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+
+            // only the owner can override the price
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation
+                    .contract_mut()
+                    .set_subscription_price(accounts.bob, ONE_WEEK_TOKENS / 2),
+                Err(Error::NotAuthorized)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                simulation
+                    .contract_mut()
+                    .set_subscription_price(accounts.charlie, ONE_WEEK_TOKENS / 2),
+                Err(Error::NotRegisterred(accounts.charlie))
+            );
+            let bob_balance = simulation.balance(accounts.bob);
+            let events_before = recorded_events().count();
+            simulation
+                .contract_mut()
+                .set_subscription_price(accounts.bob, ONE_WEEK_TOKENS / 2)
+                .unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            let decoded_event = <SubscriptionPriceAdjusted>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!(decoded_event.for_account, accounts.bob);
+            assert_eq!(
+                (decoded_event.old, decoded_event.new),
+                (ONE_WEEK_TOKENS, ONE_WEEK_TOKENS / 2)
+            );
+
+            // prepaid intervals are refunded the difference
+            assert_eq!(
+                simulation.balance(accounts.bob),
+                bob_balance + ONE_WEEK_TOKENS
+            );
+            assert_eq!(
+                simulation.contract().quote_cancellation(accounts.bob),
+                Ok(ONE_WEEK_TOKENS)
+            );
+
+            // the next interval is charged at the discounted price
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            let owner_balance = simulation.balance(accounts.alice);
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.paid_intervals(accounts.bob), Some(2));
+            assert_eq!(
+                simulation.balance(accounts.alice),
+                owner_balance + ONE_WEEK_TOKENS / 2
+            );
+        }
+
+        #[ink::test]
+        fn raised_subscription_price_keeps_prepaid_tokens() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 4, "1111")
+                .unwrap();
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 4, "2222")
+                .unwrap();
+
+            // 3 prepaid intervals cover a single interval at the raised price and a remainder
+            let bob_balance = simulation.balance(accounts.bob);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            simulation
+                .contract_mut()
+                .set_subscription_price(accounts.bob, 2 * ONE_WEEK_TOKENS)
+                .unwrap();
+            assert_eq!(
+                simulation.balance(accounts.bob),
+                bob_balance + ONE_WEEK_TOKENS
+            );
+            let s = simulation
+                .contract()
+                .get_subscription(accounts.bob)
+                .unwrap();
+            assert_eq!((s.paid_intervals, s.declared_payment_intervals), (1, 2));
+
+            // cancellation refunds the remaining prepaid tokens and the contract stays solvent
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            assert_eq!(
+                simulation.balance(accounts.bob),
+                bob_balance + 3 * ONE_WEEK_TOKENS
+            );
+            assert!(simulation.contract().solvency_ratio_bps() >= FULL_SOLVENCY_BPS as u32);
+            let charlie_balance = simulation.balance(accounts.charlie);
+            simulation.cancel_subscription(accounts.charlie).unwrap();
+            assert_eq!(
+                simulation.balance(accounts.charlie),
+                charlie_balance + 3 * ONE_WEEK_TOKENS
+            );
+        }
+
+        #[ink::test]
+        fn supported_intervals_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let simulation = test_support::Simulation::new(accounts.alice, 1u128);
+
+            let intervals = simulation.contract().supported_intervals();
+            assert!(intervals.contains(&PaymentInterval::Week));
+            assert!(intervals.contains(&PaymentInterval::Month));
+            // every supported interval has its price
+            for interval in intervals {
+                assert!(simulation.contract().price_per_interval(&interval).unwrap() > 0);
+            }
+        }
+
+        #[ink::test]
+        fn payment_token_mode_rejects_native_transfer() {
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let proof = vec![0u8; 60];
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                Subscriptions::new(
+                    1u128,
+                    Hash::from(PROOF_VK_HASH),
+                    MIN_REQUIRED_AGE,
+                    MIN_INTERVALS,
+                    MAX_INTERVALS,
+                    GRACE_INTERVALS,
+                    MAX_DURATION_BLOCKS
+                )
+                .payment_token(),
+                None
+            );
+            let mut subscriptions = Subscriptions::new_with_payment_token(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+                Some(accounts.django),
+            );
+            assert_eq!(subscriptions.payment_token(), Some(accounts.django));
+
+            // native tokens transferred together with the registration are rejected
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                ONE_TOKEN,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    proof
+                ),
+                Err(Error::NativeTransferNotAccepted)
+            );
+            assert!(!subscriptions.subscriptions.contains(accounts.charlie));
+        }
+
+        #[ink::test]
+        fn payment_token_transfers_works() {
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let contract = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let mut subscriptions = Subscriptions::new_with_payment_token(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+                Some(accounts.django),
+            );
+            psp22::set_balance(accounts.charlie, 10 * ONE_WEEK_TOKENS);
+
+            // all declared intervals are transferred from the subscriber, the first one is
+            // transferred to the owner
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    3,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    vec![0u8; 60],
+                )
+                .unwrap();
+            assert_eq!(
+                psp22::transfers(),
+                vec![
+                    (accounts.charlie, contract, 3 * ONE_WEEK_TOKENS),
+                    (contract, accounts.alice, ONE_WEEK_TOKENS),
+                ]
+            );
+
+            // payment settlement transfers the due interval to the owner
+            for _ in 0..BLOCKS_PER_WEEK {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            subscriptions.payment_settlement().unwrap();
+            assert_eq!(
+                psp22::transfers()[2..],
+                [(contract, accounts.alice, ONE_WEEK_TOKENS)]
+            );
+
+            // cancellation refunds the remaining interval to the subscriber
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            subscriptions.cancel_subscription().unwrap();
+            assert_eq!(
+                psp22::transfers()[3..],
+                [(contract, accounts.charlie, ONE_WEEK_TOKENS)]
+            );
+            assert_eq!(
+                psp22::balance_of(accounts.django, accounts.charlie),
+                8 * ONE_WEEK_TOKENS
+            );
+            assert_eq!(
+                psp22::balance_of(accounts.django, accounts.alice),
+                2 * ONE_WEEK_TOKENS
+            );
+            assert_eq!(psp22::balance_of(accounts.django, contract), 0);
+        }
+
+        #[ink::test]
+        fn remaining_intervals_histogram_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(
+                simulation.contract().remaining_intervals_histogram(),
+                Ok(vec![])
+            );
+
+            // the first interval is paid at the registration
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 2, "1111")
+                .unwrap();
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 2, "2222")
+                .unwrap();
+            simulation
+                .add_subscription(accounts.django, PaymentInterval::Month, 4, "3333")
+                .unwrap();
+            assert_eq!(
+                simulation.contract().remaining_intervals_histogram(),
+                Ok(vec![(1, 2), (3, 1)])
+            );
+        }
+
+        #[ink::test]
+        fn solvency_ratio_bps_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            let contract = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(contract, 0);
+            assert_eq!(simulation.contract().solvency_ratio_bps(), u32::MAX);
+
+            // contract holds exactly two remaining intervals
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            assert!(simulation.contract().solvency_ratio_bps() >= FULL_SOLVENCY_BPS as u32);
+
+            // half of the held tokens is missing
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract,
+                ONE_WEEK_TOKENS,
+            );
+            assert_eq!(simulation.contract().solvency_ratio_bps(), 5_000);
+        }
+
+        #[ink::test]
+        fn get_contract_stats_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(
+                simulation.contract().get_contract_stats(),
+                ContractStats {
+                    active_count: 0,
+                    total_reserved: 0,
+                    total_declared_intervals: 0,
+                    total_paid_intervals: 0,
+                }
+            );
+
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Month, 2, "2222")
+                .unwrap();
+            let month_price = simulation
+                .contract()
+                .price_per_interval(&PaymentInterval::Month)
+                .unwrap();
+            assert_eq!(
+                simulation.contract().get_contract_stats(),
+                ContractStats {
+                    active_count: 2,
+                    total_reserved: 2 * ONE_WEEK_TOKENS + month_price,
+                    total_declared_intervals: 5,
+                    total_paid_intervals: 2,
+                }
+            );
+
+            // cancelled subscriptions are not counted
+            simulation.cancel_subscription(accounts.bob).unwrap();
+            assert_eq!(
+                simulation.contract().get_contract_stats(),
+                ContractStats {
+                    active_count: 1,
+                    total_reserved: month_price,
+                    total_declared_intervals: 2,
+                    total_paid_intervals: 1,
+                }
+            );
+        }
+
+        #[ink::test]
+        fn withdraw_surplus_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            let contract = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+
+            // the contract holds two remaining intervals of Bob and tokens sent directly to it
+            let surplus = ONE_TOKEN;
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract,
+                2 * ONE_WEEK_TOKENS + surplus,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation.contract_mut().withdraw_surplus(),
+                Err(Error::NotAuthorized)
+            );
+
+            let owner_balance = simulation.balance(accounts.alice);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(simulation.contract_mut().withdraw_surplus(), Ok(surplus));
+            assert_eq!(simulation.balance(accounts.alice), owner_balance + surplus);
+            assert_eq!(simulation.balance(contract), 2 * ONE_WEEK_TOKENS);
+
+            // nothing more to withdraw
+            assert_eq!(simulation.contract_mut().withdraw_surplus(), Ok(0));
+        }
+
+        #[ink::test]
+        fn events_version_works() {
+            let subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+            assert_eq!(subscriptions.events_version(), EVENTS_VERSION);
+            assert_eq!(subscriptions.events_version(), 2);
+        }
+
+        #[ink::test]
+        fn add_subscription_with_exact_payment_is_not_reimbursed() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                accounts.charlie,
+                0,
+            );
+
+            // subscriber is funded with exactly the cost of the subscription
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            assert_eq!(simulation.contract().total_refunded(), 0);
+            assert_eq!(simulation.balance(accounts.charlie), 0);
+        }
+
+        #[ink::test]
+        fn price_per_interval_overflow_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let simulation = test_support::Simulation::new(accounts.alice, Balance::MAX / 2);
+
+            assert_eq!(
+                simulation
+                    .contract()
+                    .price_per_interval(&PaymentInterval::Week),
+                Err(Error::ArithmeticOverflow)
+            );
+            assert_eq!(simulation.contract().prices(), (Balance::MAX, Balance::MAX));
+        }
+
+        #[ink::test]
+        fn settlement_amount_overflow_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+
+            // a price which fits a single interval, but not the two intervals to settle
+            let mut s = simulation
+                .contract()
+                .get_subscription(accounts.bob)
+                .unwrap();
+            s.price_per_interval = Balance::MAX / 2 + 1;
+            simulation
+                .contract_mut()
+                .subscriptions
+                .insert(accounts.bob, &s);
+
+            simulation.advance_blocks(2 * BLOCKS_PER_WEEK);
+            assert_eq!(
+                simulation.payment_settlement(),
+                Err(Error::ArithmeticOverflow)
+            );
+            assert_eq!(simulation.paid_intervals(accounts.bob), Some(1));
+        }
+
+        #[ink::test]
+        fn add_subscription_cost_overflow_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // price per interval itself overflows
+            let mut simulation = test_support::Simulation::new(accounts.alice, Balance::MAX / 2);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                simulation.contract_mut().add_subscription(
+                    PaymentInterval::Week,
+                    1,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    vec![]
+                ),
+                Err(Error::ArithmeticOverflow)
+            );
+
+            // price per interval fits, but the total cost of many intervals overflows
+            let price_per_block = Balance::MAX / BLOCKS_PER_MONTH as u128;
+            let mut simulation = test_support::Simulation::new(accounts.alice, price_per_block);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                simulation.contract_mut().add_subscription(
+                    PaymentInterval::Month,
+                    2,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    vec![]
+                ),
+                Err(Error::ArithmeticOverflow)
+            );
+            assert!(!simulation.is_subscribed(accounts.charlie));
+        }
+
+        #[ink::test]
+        fn grace_period_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation =
+                test_support::Simulation::with_grace_intervals(accounts.alice, 1u128, 1);
+            assert_eq!(simulation.contract().grace_intervals(), 1);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 1, "1111")
+                .unwrap();
+
+            // Bob is short on funds for the first time, but stays subscribed
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            let events_before = recorded_events().count();
+            simulation.payment_settlement().unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            assert_eq!(events.len(), 1);
+            let decoded_event = <GracePeriodStarted>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!(decoded_event.for_account, accounts.bob);
+            assert_eq!(decoded_event.missed_intervals, 1);
+            assert_eq!(decoded_event.grace_intervals, 1);
+            assert!(simulation.is_subscribed(accounts.bob));
+            assert_eq!(simulation.paid_intervals(accounts.bob), Some(1));
+
+            // the grace period is over
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            let events_before = recorded_events().count();
+            simulation.payment_settlement().unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            assert_eq!(events.len(), 1);
+            assert_cancelled_subscriptions(
+                &events[0],
+                vec![ActiveSubscriptionAttr {
+                    for_account: accounts.bob,
+                    external_channel_handle: "1111".to_string().into_bytes(),
+                }],
+            );
+            assert!(!simulation.is_subscribed(accounts.bob));
+        }
+
+        #[ink::test]
+        fn set_grace_intervals_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(simulation.contract().grace_intervals(), 0);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 1, "1111")
+                .unwrap();
 
-        /// Calculates number of intervals from the last paid block
-        fn to_pay_intervals(
-            &self,
-            payment_interval: PaymentInterval,
-            curr_block: BlockNumber,
-            last_payment_at: BlockNumber,
-        ) -> u32 {
-            (curr_block - last_payment_at)
-                / match payment_interval {
-                    PaymentInterval::Week => BLOCKS_PER_WEEK,
-                    PaymentInterval::Month => BLOCKS_PER_MONTH,
-                }
-        }
+            // only the owner can change the grace period
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation.contract_mut().set_grace_intervals(2),
+                Err(Error::NotAuthorized)
+            );
 
-        /// Transfers amount of tokens from the contract's account to the owner account.
-        fn transfer_to_owner(&self, amount: Balance) {
-            if Self::env().transfer(self.owner, amount).is_err() {
-                panic!("failed to transfer tokens to owner")
-            }
-        }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let events_before = recorded_events().count();
+            simulation.contract_mut().set_grace_intervals(2).unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            let decoded_event = <GraceIntervalsUpdated>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!((decoded_event.old, decoded_event.new), (0, 2));
+            assert_eq!(simulation.contract().grace_intervals(), 2);
 
-        /// Reimburses the caller with overpaid tokens.
-        /// Panics if the transfer fails - this means this contract's balance is
-        /// too low which means something went wrong.
-        fn reimburse(&self, recipient: AccountId, amount: Balance) {
-            if Self::env().transfer(recipient, amount).is_err() {
-                panic!("failed to reimburse the caller")
-            }
+            // Bob would be cancelled without the grace period
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            let events_before = recorded_events().count();
+            simulation.payment_settlement().unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            assert_eq!(events.len(), 1);
+            let decoded_event = <GracePeriodStarted>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!(decoded_event.for_account, accounts.bob);
+            assert_eq!(decoded_event.grace_intervals, 2);
+            assert!(simulation.is_subscribed(accounts.bob));
         }
 
-        /// Verifies zero knowledge proof as provided by user
-        fn verify_proof(&self, proof: Vec<u8>) -> Result<(), Error> {
-            let vk_hash = baby_liminal_extension::KeyHash::from_slice(self.proof_vk.as_ref());
-            self.env()
-                .extension()
-                .verify(vk_hash, proof, self.proof_public_inputs()?)
-                .map_err(|_| Error::InvalidProofForMinAgeRequired)
-        }
+        #[ink::test]
+        fn max_duration_works() {
+            ink::env::test::register_chain_extension(MockZKPVerifier::new(
+                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
+            ));
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let max_duration_blocks = 52 * BLOCKS_PER_WEEK;
+            let mut subscriptions = Subscriptions::new(
+                0u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                max_duration_blocks,
+            );
+            assert_eq!(subscriptions.max_duration_blocks(), max_duration_blocks);
 
-        /// Generates zero knowledge proof public inputs.
-        /// Caller's address is used as one of the inputs.
-        fn proof_public_inputs(&self) -> Result<Vec<u8>, Error> {
-            let mut inputs = Vec::<u8>::new();
-            // first input is a minimum required age
-            inputs.extend(self.proof_min_required_age.to_le_bytes());
-            // Finite field (Fr) elements are 256-bit so we need to pad with zero
-            inputs.extend([0u8; 16]);
-            // second input is caller's address in two 128-bit chunks
-            let caller = self.env().caller();
-            let bs: &[u8; 32] = caller.as_ref();
-            inputs.extend(
-                u128::from_le_bytes(
-                    bs[..16]
-                        .try_into()
-                        .map_err(|_| Error::ProofCallerAddressNotSerializable)?,
-                )
-                .to_le_bytes(),
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                subscriptions.add_subscription(
+                    PaymentInterval::Week,
+                    60,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    vec![1u8; 60],
+                ),
+                Err(Error::DurationTooLong(max_duration_blocks))
             );
-            inputs.extend([0u8; 16]);
-            inputs.extend(
-                u128::from_le_bytes(
-                    bs[16..]
-                        .try_into()
-                        .map_err(|_| Error::ProofCallerAddressNotSerializable)?,
+            subscriptions
+                .add_subscription(
+                    PaymentInterval::Week,
+                    52,
+                    "1111".to_string(),
+                    MIN_REQUIRED_AGE,
+                    vec![2u8; 60],
                 )
-                .to_le_bytes(),
-            );
-            inputs.extend([0u8; 16]);
+                .unwrap();
 
-            Ok(inputs)
+            // renewal can't exceed the cap either
+            assert_eq!(
+                subscriptions.renew_subscription(1),
+                Err(Error::DurationTooLong(max_duration_blocks))
+            );
         }
-    }
-
-    #[cfg(test)]
-    mod tests {
-        use ink::{
-            env::test::{recorded_events, EmittedEvent},
-            primitives::Hash,
-            scale::Decode as _,
-        };
 
-        /// Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
+        #[ink::test]
+        fn migrate_works() {
+            /// Root cell of the smart contract as stored by storage version 1
+            #[derive(scale::Encode)]
+            struct RootV1 {
+                owner: AccountId,
+                price_per_block: Balance,
+                active_subscriptions: Vec<AccountId>,
+                proof_vk: Hash,
+                proof_min_required_age: u128,
+            }
 
-        pub const ONE_TOKEN: Balance = 1_000_000_000_000;
-        pub const ONE_WEEK_TOKENS: Balance = 604_800;
-        pub const PROOF_VK_HASH: [u8; 32] = [0u8; 32];
-        pub const MIN_REQUIRED_AGE: u128 = 18;
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let contract_account = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_account,
+                1_000_000_000,
+            );
 
-        /// Mocks baby_liminal_extension
-        struct MockZKPVerifier {
-            /// Should be one of baby_liminal_extension::status_codes
-            expected_result: u32,
-        }
+            // simulate the storage written by the code of storage version 1
+            let root = scale::Encode::encode(&RootV1 {
+                owner: accounts.alice,
+                price_per_block: 1,
+                active_subscriptions: vec![accounts.bob, accounts.charlie],
+                proof_vk: Hash::from([7u8; 32]),
+                proof_min_required_age: 18,
+            });
+            let mut input = &root[..];
+            let mut contract =
+                <Subscriptions as ink::storage::traits::Storable>::decode(&mut input).unwrap();
+            assert!(input.is_empty());
+            assert_eq!(contract.owner, accounts.alice);
+            assert_eq!(contract.proof_vk, Hash::from([7u8; 32]));
+            assert_eq!(contract.storage_version(), 1);
 
-        impl MockZKPVerifier {
-            pub fn new(expected_result: u32) -> Self {
-                Self { expected_result }
+            let root_key = Subscriptions::root_key(&contract.subscriptions);
+            for (subscriber, handle) in [(accounts.bob, "1111"), (accounts.charlie, "2222")] {
+                let legacy = SubscriptionV1 {
+                    payment_interval: PaymentInterval::Week,
+                    declared_payment_intervals: 3,
+                    paid_intervals: 1,
+                    price_per_interval: BLOCKS_PER_WEEK as u128,
+                    registered_at: 0,
+                    last_payment_at: 0,
+                    external_channel_handle: handle.to_string(),
+                };
+                ink::env::set_contract_storage(&(root_key, subscriber), &legacy);
             }
-        }
+            // registration is paused until the storage is migrated
+            assert!(contract.is_paused());
 
-        impl ink::env::test::ChainExtension for MockZKPVerifier {
-            fn ext_id(&self) -> u16 {
-                baby_liminal_extension::extension_ids::EXTENSION_ID
-            }
+            // only the owner can migrate the storage
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.migrate(), Err(Error::NotAuthorized));
 
-            fn call(&mut self, func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.migrate(), Ok(2));
+            assert_eq!(contract.storage_version(), STORAGE_VERSION);
+            assert!(!contract.is_paused());
+            assert_eq!(contract.total_subscriptions_created(), 2);
+            assert_eq!(contract.proof_vks.get(18), Some(Hash::from([7u8; 32])));
+            let s = contract.raw_subscription(accounts.charlie).unwrap();
+            assert_eq!(s.external_channel_handle, "2222");
+            assert_eq!((s.paid_intervals, s.declared_payment_intervals), (1, 3));
+            assert_eq!((s.frozen, s.missed_intervals), (false, 0));
+            for subscriber in [accounts.bob, accounts.charlie] {
                 assert_eq!(
-                    func_id,
-                    baby_liminal_extension::extension_ids::VERIFY_FUNC_ID
+                    contract.charge_bucket_of.get(subscriber),
+                    Some(BLOCKS_PER_WEEK / CHARGE_BUCKET_BLOCKS)
                 );
-                self.expected_result
             }
+
+            // migrated subscriptions are charged by the rebuilt schedule and the migration is
+            // not repeated
+            for _ in 0..BLOCKS_PER_WEEK {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            contract.settle_due_subscriptions().unwrap();
+            assert_eq!(
+                contract
+                    .raw_subscription(accounts.bob)
+                    .map(|s| s.paid_intervals),
+                Ok(2)
+            );
+            assert_eq!(contract.migrate(), Ok(0));
         }
 
-        /// We test a simple use case of our contract.
         #[ink::test]
-        fn it_works() {
-            // register baby liminal extension, used for zero knowlege proof verification
-            ink::env::test::register_chain_extension(MockZKPVerifier::new(
-                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
-            ));
-            let proof = vec![0u8; 60];
-
+        fn pause_works() {
             let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 0);
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            let mut subscriptions =
-                Subscriptions::new(1u128, Hash::from(PROOF_VK_HASH), MIN_REQUIRED_AGE);
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 2, "1111")
+                .unwrap();
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 2, "2222")
+                .unwrap();
 
-            assert_eq!(&subscriptions.owner, &accounts.bob);
-            assert_eq!(subscriptions.price_per_block, 1u128);
+            // only the owner can pause the registration
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(simulation.contract_mut().pause(), Err(Error::NotAuthorized));
 
-            // prepare balance for the caller
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.charlie,
-                2 * ONE_TOKEN,
-            );
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
-            // add subscription
-            subscriptions
-                .add_subscription(PaymentInterval::Week, 1, "1111".to_string(), proof)
-                .unwrap();
-            assert!(subscriptions.subscriptions.contains(accounts.charlie));
-            assert!(subscriptions
-                .active_subscriptions
-                .contains(&accounts.charlie));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let events_before = recorded_events().count();
+            simulation.contract_mut().pause().unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            let decoded_event =
+                <Paused>::decode(&mut &events[0].data[..]).expect("invalid event buffer");
+            assert_eq!(decoded_event.by, accounts.alice);
+            assert!(simulation.contract().is_paused());
 
-            // bob, an owner of the contract should get payment
+            // new subscriptions are blocked
             assert_eq!(
-                ONE_WEEK_TOKENS,
-                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
-                    .unwrap()
+                simulation.add_subscription(accounts.django, PaymentInterval::Week, 1, "3333"),
+                Err(Error::ContractPaused)
             );
-            // overpaid tokens should be returned to charlie
+
+            // existing subscriptions can still be settled and cancelled
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.paid_intervals(accounts.bob), Some(2));
+            simulation.cancel_subscription(accounts.charlie).unwrap();
+            assert!(!simulation.is_subscribed(accounts.charlie));
+
+            // only the owner can resume the registration
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
             assert_eq!(
-                2 * ONE_TOKEN - ONE_WEEK_TOKENS,
-                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
-                    accounts.charlie
-                )
-                .unwrap()
+                simulation.contract_mut().unpause(),
+                Err(Error::NotAuthorized)
             );
 
-            // test recorded events
-            let events = recorded_events().collect::<Vec<_>>();
-            assert_new_subscription(&events[0], accounts.charlie, "1111".to_string());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let events_before = recorded_events().count();
+            simulation.contract_mut().unpause().unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            let decoded_event =
+                <Unpaused>::decode(&mut &events[0].data[..]).expect("invalid event buffer");
+            assert_eq!(decoded_event.by, accounts.alice);
+            assert!(!simulation.contract().is_paused());
+            simulation
+                .add_subscription(accounts.django, PaymentInterval::Week, 1, "3333")
+                .unwrap();
+            assert!(simulation.is_subscribed(accounts.django));
         }
 
         #[ink::test]
-        fn proof_verification_fails() {
-            // register baby liminal extension, used for zero knowlege proof verification
-            ink::env::test::register_chain_extension(MockZKPVerifier::new(
-                baby_liminal_extension::status_codes::VERIFY_VERIFICATION_FAIL,
-            ));
-            let proof = vec![0u8; 60];
+        fn settle_due_subscriptions_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            let contract = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            // two weekly subscribers are due after a week, monthly subscribers are not
+            let subscribers = (0..100u8)
+                .map(|i| AccountId::from([i + 100; 32]))
+                .collect::<Vec<_>>();
+            for (i, subscriber) in subscribers.iter().enumerate() {
+                let payment_interval = if i == 17 || i == 71 {
+                    PaymentInterval::Week
+                } else {
+                    PaymentInterval::Month
+                };
+                simulation
+                    .add_subscription(*subscriber, payment_interval, 3, "1111")
+                    .unwrap();
+            }
 
-            let mut subscriptions =
-                Subscriptions::new(0u128, Hash::from(PROOF_VK_HASH), MIN_REQUIRED_AGE);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation.contract_mut().settle_due_subscriptions(),
+                Err(Error::NotAuthorized)
+            );
 
-            // add subscription failes becase of failed verification
-            assert!(subscriptions
-                .add_subscription(PaymentInterval::Week, 1, "1111".to_string(), proof)
-                .is_err());
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let (reads_before, writes_before) =
+                ink::env::test::get_contract_storage_rw::<ink::env::DefaultEnvironment>(contract);
+            let events_before = recorded_events().count();
+            simulation
+                .contract_mut()
+                .settle_due_subscriptions()
+                .unwrap();
+            let (reads, writes) =
+                ink::env::test::get_contract_storage_rw::<ink::env::DefaultEnvironment>(contract);
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+
+            // only due subscriptions are read and written: the daily buckets since the previous
+            // run, and the subscription, lifetime intervals and scheduled charge of each of them
+            assert!(reads - reads_before < 30);
+            assert!(writes - writes_before < 20);
+            assert_eq!(events.len(), 2);
+            assert_payment_settled(&events[0], subscribers[17], 1, ONE_WEEK_TOKENS);
+            assert_payment_settled(&events[1], subscribers[71], 1, ONE_WEEK_TOKENS);
+            for (i, subscriber) in subscribers.iter().enumerate() {
+                let expected = if i == 17 || i == 71 { 2 } else { 1 };
+                assert_eq!(simulation.paid_intervals(*subscriber), Some(expected));
+            }
+
+            // nothing is due until the next week
+            let events_before = recorded_events().count();
+            simulation
+                .contract_mut()
+                .settle_due_subscriptions()
+                .unwrap();
+            assert_eq!(recorded_events().count(), events_before);
         }
 
         #[ink::test]
-        fn cancel_subscription_works() {
-            // register baby liminal extension, used for zero knowlege proof verification
-            ink::env::test::register_chain_extension(MockZKPVerifier::new(
-                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
-            ));
-            let proof = vec![0u8; 60];
+        fn charge_schedule_is_not_stored_in_root() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            let root_size = |contract: &Subscriptions| {
+                let mut root = Vec::new();
+                ink::storage::traits::Storable::encode(contract, &mut root);
+                root.len() - scale::Encode::encode(&contract.active_subscriptions).len()
+            };
+            let empty_root_size = root_size(simulation.contract());
+
+            // beside the list of active subscriptions, the root doesn't grow with subscriptions
+            for i in 0..10u8 {
+                simulation
+                    .add_subscription(
+                        AccountId::from([i + 100; 32]),
+                        PaymentInterval::Week,
+                        3,
+                        "1111",
+                    )
+                    .unwrap();
+            }
+            assert_eq!(root_size(simulation.contract()), empty_root_size);
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            simulation
+                .contract_mut()
+                .settle_due_subscriptions()
+                .unwrap();
+            assert_eq!(root_size(simulation.contract()), empty_root_size);
+            // settled subscriptions are moved to the bucket of their next charge
+            let curr_block = ink::env::block_number::<ink::env::DefaultEnvironment>();
+            for i in 0..10u8 {
+                assert_eq!(
+                    simulation
+                        .contract()
+                        .charge_bucket_of
+                        .get(AccountId::from([i + 100; 32])),
+                    Some((curr_block + BLOCKS_PER_WEEK) / CHARGE_BUCKET_BLOCKS)
+                );
+            }
+        }
 
+        #[ink::test]
+        fn payment_settlement_range_works() {
             let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            // setup Bob as a contract owner
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 0);
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            let mut subscriptions =
-                Subscriptions::new(1u128, Hash::from(PROOF_VK_HASH), MIN_REQUIRED_AGE);
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            // every other subscriber runs out of funds after the first interval
+            let subscribers = (0..10u8)
+                .map(|i| AccountId::from([i + 100; 32]))
+                .collect::<Vec<_>>();
+            for (i, subscriber) in subscribers.iter().enumerate() {
+                let intervals = if i % 2 == 0 { 3 } else { 1 };
+                simulation
+                    .add_subscription(*subscriber, PaymentInterval::Week, intervals, "1111")
+                    .unwrap();
+            }
 
-            // prepare balance for the Charlie as the contract caller
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.charlie,
-                ONE_TOKEN,
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation.contract_mut().payment_settlement_range(0, 5),
+                Err(Error::NotAuthorized)
             );
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
-            // add subscription
-            subscriptions
-                .add_subscription(PaymentInterval::Week, 1, "1111".to_string(), proof)
+
+            // the last batch is settled first, the first batch isn't affected
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            simulation
+                .contract_mut()
+                .payment_settlement_range(5, 10)
                 .unwrap();
-            assert!(subscriptions.subscriptions.contains(accounts.charlie));
-            assert!(subscriptions
-                .active_subscriptions
-                .contains(&accounts.charlie));
+            assert_eq!(simulation.contract().subscriptions_count(), 8);
+            assert_eq!(simulation.paid_intervals(subscribers[0]), Some(1));
+            assert_eq!(simulation.paid_intervals(subscribers[6]), Some(2));
 
-            // Charlie cancels subscription
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(0);
-            subscriptions.cancel_subscription().unwrap();
-            assert!(!subscriptions.subscriptions.contains(accounts.charlie));
-            assert!(!subscriptions
-                .active_subscriptions
-                .contains(&accounts.charlie));
+            simulation
+                .contract_mut()
+                .payment_settlement_range(0, 5)
+                .unwrap();
+            assert_eq!(simulation.contract().subscriptions_count(), 5);
+            for (i, subscriber) in subscribers.iter().enumerate() {
+                if i % 2 == 0 {
+                    assert_eq!(simulation.paid_intervals(*subscriber), Some(2));
+                } else {
+                    assert!(!simulation.is_subscribed(*subscriber));
+                }
+            }
 
-            // test if remaining tokens are returned to the Charlie
+            // ranges past the end settle nothing
+            simulation
+                .contract_mut()
+                .payment_settlement_range(7, 100)
+                .unwrap();
+        }
+
+        #[ink::test]
+        fn last_settlement_timestamp_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(simulation.contract().last_settlement_timestamp(), 0);
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_700_000_000_000);
+            simulation.payment_settlement().unwrap();
             assert_eq!(
-                ONE_TOKEN - ONE_WEEK_TOKENS,
-                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(
-                    accounts.charlie
-                )
-                .unwrap()
+                simulation.contract().last_settlement_timestamp(),
+                1_700_000_000_000
+            );
+
+            // only settlement runs update the timestamp
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_700_000_600_000);
+            assert_eq!(
+                simulation.contract().last_settlement_timestamp(),
+                1_700_000_000_000
+            );
+            simulation.payment_settlement().unwrap();
+            assert_eq!(
+                simulation.contract().last_settlement_timestamp(),
+                1_700_000_600_000
             );
-            // test recorded events
-            let events = recorded_events().collect::<Vec<_>>();
-            assert_new_subscription(&events[0], accounts.charlie, "1111".to_string());
-            assert_cancelled_subscription(&events[1], accounts.charlie);
         }
 
         #[ink::test]
-        fn get_active_subscriptions_works() {
-            // register baby liminal extension, used for zero knowlege proof verification
-            ink::env::test::register_chain_extension(MockZKPVerifier::new(
-                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
-            ));
-            let proof = vec![0u8; 60];
+        fn total_refunded_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            assert_eq!(simulation.contract().total_refunded(), 0);
+
+            // exact payment, nothing to refund
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            assert_eq!(simulation.contract().total_refunded(), 0);
 
+            // two unpaid intervals are refunded on cancellation
+            simulation.cancel_subscription(accounts.charlie).unwrap();
+            assert_eq!(simulation.contract().total_refunded(), 2 * ONE_WEEK_TOKENS);
+        }
+
+        #[ink::test]
+        fn simulation_scenario_works() {
             let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            let mut subscriptions =
-                Subscriptions::new(0u128, Hash::from(PROOF_VK_HASH), MIN_REQUIRED_AGE);
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
 
-            // prepare balance for the Charlie as the contract caller
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.charlie,
-                ONE_TOKEN,
-            );
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
-            // add subscription
-            subscriptions
-                .add_subscription(PaymentInterval::Week, 1, "1111".to_string(), proof)
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 1, "1111")
                 .unwrap();
-            assert!(subscriptions.subscriptions.contains(accounts.charlie));
-            assert!(subscriptions
-                .active_subscriptions
-                .contains(&accounts.charlie));
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 3, "2222")
+                .unwrap();
+            assert_eq!(simulation.paid_intervals(accounts.bob), Some(1));
+            assert_eq!(simulation.paid_intervals(accounts.charlie), Some(1));
 
-            // test list of active subscriptions
+            // after one week Bob runs out of funds, Charlie keeps paying
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            assert!(!simulation.is_subscribed(accounts.bob));
+            assert!(simulation.is_subscribed(accounts.charlie));
+            assert_eq!(simulation.paid_intervals(accounts.charlie), Some(2));
+
+            // Charlie cancels and gets the last interval back
+            let charlie_balance = simulation.balance(accounts.charlie);
+            simulation.cancel_subscription(accounts.charlie).unwrap();
+            assert!(!simulation.is_subscribed(accounts.charlie));
             assert_eq!(
-                subscriptions.get_active_subscriptions().unwrap(),
-                vec![ActiveSubscriptionAttr {
-                    for_account: accounts.charlie,
-                    external_channel_handle: "1111".as_bytes().to_vec()
-                }]
+                simulation.balance(accounts.charlie),
+                charlie_balance + ONE_WEEK_TOKENS
             );
         }
 
         #[ink::test]
-        fn payment_settlement_works() {
-            // register baby liminal extension, used for zero knowlege proof verification
-            ink::env::test::register_chain_extension(MockZKPVerifier::new(
-                baby_liminal_extension::status_codes::VERIFY_SUCCESS,
-            ));
-            let proof = vec![0u8; 60];
-
+        fn freeze_subscription_works() {
             let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            let mut subscriptions =
-                Subscriptions::new(1u128, Hash::from(PROOF_VK_HASH), MIN_REQUIRED_AGE);
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
 
-            // register subscription for Bob
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.bob,
-                ONE_TOKEN,
+            // only the subscriber or the owner can freeze the subscription
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                simulation.contract_mut().freeze_subscription(accounts.bob),
+                Err(Error::NotAuthorized)
             );
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
-            subscriptions
-                .add_subscription(PaymentInterval::Week, 2, "1111".to_string(), proof.clone())
+            simulation
+                .contract_mut()
+                .freeze_subscription(accounts.bob)
                 .unwrap();
-            // register subscription for Charlie
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                accounts.charlie,
-                3 * ONE_TOKEN,
+            assert_eq!(
+                simulation.contract_mut().freeze_subscription(accounts.bob),
+                Err(Error::AlreadyFrozen(accounts.bob))
             );
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
-            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(ONE_TOKEN);
-            // add subscription
-            subscriptions
-                .add_subscription(PaymentInterval::Week, 3, "2222".to_string(), proof)
+
+            // frozen week is not charged
+            let owner_balance = simulation.balance(accounts.alice);
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.paid_intervals(accounts.bob), Some(1));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            simulation
+                .contract_mut()
+                .unfreeze_subscription(accounts.bob)
                 .unwrap();
+            assert_eq!(
+                simulation
+                    .contract_mut()
+                    .unfreeze_subscription(accounts.bob),
+                Err(Error::NotFrozen(accounts.bob))
+            );
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.paid_intervals(accounts.bob), Some(1));
+            assert_eq!(simulation.balance(accounts.alice), owner_balance);
 
-            assert!(subscriptions.subscriptions.contains(accounts.bob));
-            assert!(subscriptions.active_subscriptions.contains(&accounts.bob));
+            // billing is resumed after unfreezing
+            simulation.advance_blocks(BLOCKS_PER_WEEK);
+            simulation.payment_settlement().unwrap();
+            assert_eq!(simulation.paid_intervals(accounts.bob), Some(2));
             assert_eq!(
-                subscriptions
-                    .subscriptions
-                    .get(accounts.bob)
-                    .unwrap()
-                    .paid_intervals,
-                1
+                simulation.balance(accounts.alice),
+                owner_balance + ONE_WEEK_TOKENS
             );
-            assert!(subscriptions.subscriptions.contains(accounts.charlie));
-            assert!(subscriptions
-                .active_subscriptions
-                .contains(&accounts.charlie));
+        }
+
+        #[ink::test]
+        fn frozen_subscriptions_stay_listed() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
+            simulation
+                .add_subscription(accounts.charlie, PaymentInterval::Week, 3, "2222")
+                .unwrap();
+
+            // frozen subscriber keeps the plan, so the notifications keep reaching them
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            simulation
+                .contract_mut()
+                .freeze_subscription(accounts.bob)
+                .unwrap();
+            let (subs, truncated) = simulation.contract().get_active_subscriptions().unwrap();
             assert_eq!(
-                subscriptions
-                    .subscriptions
-                    .get(accounts.charlie)
+                subs.into_iter()
+                    .map(|attr| attr.for_account)
+                    .collect::<Vec<_>>(),
+                vec![accounts.bob, accounts.charlie]
+            );
+            assert!(!truncated);
+            assert_eq!(
+                simulation
+                    .contract()
+                    .get_active_subscriptions_paged(0, 1)
                     .unwrap()
-                    .paid_intervals,
+                    .len(),
                 1
             );
+            assert_eq!(simulation.contract().subscriptions_count(), 2);
+        }
 
-            // advance one block and execute payment settlement
-            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
-            assert!(subscriptions.payment_settlement().is_ok());
+        #[ink::test]
+        fn update_channel_handle_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            simulation
+                .add_subscription(accounts.bob, PaymentInterval::Week, 3, "1111")
+                .unwrap();
 
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
             assert_eq!(
-                subscriptions
-                    .subscriptions
-                    .get(accounts.bob)
-                    .unwrap()
-                    .paid_intervals,
-                1
+                simulation
+                    .contract_mut()
+                    .update_channel_handle("2222".to_string()),
+                Err(Error::NotRegisterred(accounts.charlie))
             );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
             assert_eq!(
-                subscriptions
-                    .subscriptions
-                    .get(accounts.charlie)
-                    .unwrap()
-                    .paid_intervals,
-                1
+                simulation
+                    .contract_mut()
+                    .update_channel_handle("".to_string()),
+                Err(Error::MissingChannelHandle)
             );
+            let events_before = recorded_events().count();
+            simulation
+                .contract_mut()
+                .update_channel_handle("2222".to_string())
+                .unwrap();
 
-            // advance one week of blocks, both bob and charlie should still have active subscriptions
-            for _ in 0..BLOCKS_PER_WEEK {
-                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            // prepaid intervals are kept
+            let s = simulation
+                .contract()
+                .subscriptions
+                .get(accounts.bob)
+                .unwrap();
+            assert_eq!(s.external_channel_handle, "2222");
+            assert_eq!(s.declared_payment_intervals, 3);
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            assert_eq!(events.len(), 1);
+            assert_updated_channel_handle(&events[0], accounts.bob, "2222".to_string());
+        }
+
+        #[ink::test]
+        fn remap_handles_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+            for (subscriber, handle) in [(accounts.bob, "1111"), (accounts.charlie, "2222")] {
+                simulation
+                    .add_subscription(subscriber, PaymentInterval::Week, 3, handle)
+                    .unwrap();
             }
-            assert!(subscriptions.payment_settlement().is_ok());
-            assert!(subscriptions.subscriptions.get(accounts.bob).is_some());
+
+            // only the owner can remap handles
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
             assert_eq!(
-                subscriptions
-                    .subscriptions
-                    .get(accounts.bob)
-                    .unwrap()
-                    .paid_intervals,
-                2
+                simulation
+                    .contract_mut()
+                    .remap_handles(vec![(accounts.bob, "3333".to_string())]),
+                Err(Error::NotAuthorized)
             );
-            assert!(subscriptions.subscriptions.get(accounts.charlie).is_some());
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(
-                subscriptions
-                    .subscriptions
-                    .get(accounts.charlie)
-                    .unwrap()
-                    .paid_intervals,
-                2
+                simulation
+                    .contract_mut()
+                    .remap_handles(vec![(accounts.bob, "3333".to_string()); MAX_RETURNED + 1]),
+                Err(Error::TooManyAccounts(MAX_RETURNED as u32))
+            );
+            // an invalid handle rejects the whole remapping
+            assert_eq!(
+                simulation.contract_mut().remap_handles(vec![
+                    (accounts.bob, "3333".to_string()),
+                    (accounts.charlie, "".to_string())
+                ]),
+                Err(Error::MissingChannelHandle)
             );
 
-            // advance one more week of blocks, bob's subscription should be cancelled.  Charlie should still have active subscription
-            for _ in 0..BLOCKS_PER_WEEK {
-                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            }
-            assert!(subscriptions.payment_settlement().is_ok());
-            assert!(subscriptions.subscriptions.get(accounts.bob).is_none());
-            assert!(subscriptions.subscriptions.get(accounts.charlie).is_some());
+            // Django has no subscription, so the account is skipped
+            let events_before = recorded_events().count();
+            simulation
+                .contract_mut()
+                .remap_handles(vec![
+                    (accounts.bob, "3333".to_string()),
+                    (accounts.django, "4444".to_string()),
+                    (accounts.charlie, "5555".to_string()),
+                ])
+                .unwrap();
+            assert!(!simulation.is_subscribed(accounts.django));
             assert_eq!(
-                subscriptions
-                    .subscriptions
-                    .get(accounts.charlie)
-                    .unwrap()
-                    .paid_intervals,
-                3
+                simulation.contract().get_active_subscriptions().unwrap(),
+                (
+                    vec![
+                        ActiveSubscriptionAttr {
+                            for_account: accounts.bob,
+                            external_channel_handle: "3333".to_string().into_bytes(),
+                        },
+                        ActiveSubscriptionAttr {
+                            for_account: accounts.charlie,
+                            external_channel_handle: "5555".to_string().into_bytes(),
+                        },
+                    ],
+                    false
+                )
             );
 
-            // test emitted events
-            let events = recorded_events().collect::<Vec<_>>();
-            assert_new_subscription(&events[0], accounts.bob, "1111".to_string());
-            assert_new_subscription(&events[1], accounts.charlie, "2222".to_string());
-            assert_cancelled_subscriptions(
-                &events[2],
-                vec![ActiveSubscriptionAttr {
-                    for_account: accounts.bob,
-                    external_channel_handle: "1111".as_bytes().to_vec(),
-                }],
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            assert_eq!(events.len(), 2);
+            assert_updated_channel_handle(&events[0], accounts.bob, "3333".to_string());
+            assert_updated_channel_handle(&events[1], accounts.charlie, "5555".to_string());
+        }
+
+        #[ink::test]
+        fn set_code_with_unknown_code_hash_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut simulation = test_support::Simulation::new(accounts.alice, 1u128);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                simulation.contract_mut().set_code(Hash::from([0xff; 32])),
+                Err(Error::NotAuthorized)
             );
+
+            // no code has been uploaded for the code hash
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let events_before = recorded_events().count();
+            assert!(matches!(
+                simulation.contract_mut().set_code(Hash::from([0xff; 32])),
+                Err(Error::CodeUpgradeFailed(_))
+            ));
+            // failed upgrade is not reported
+            assert_eq!(recorded_events().count(), events_before);
         }
 
         #[ink::test]
@@ -860,13 +5413,134 @@ mod subscriptions {
             ));
 
             let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            let mut subscriptions =
-                Subscriptions::new(1u128, Hash::from(PROOF_VK_HASH), MIN_REQUIRED_AGE);
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
             assert_eq!(subscriptions.owner, accounts.alice);
 
             // transfer ownership to bob
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                subscriptions.transfer_ownership(accounts.bob),
+                Err(Error::NotAuthorized)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert!(subscriptions.transfer_ownership(accounts.bob).is_ok());
+            assert_eq!(subscriptions.owner, accounts.alice);
+            assert_eq!(subscriptions.pending_owner(), Some(accounts.bob));
+
+            // bob accepts the ownership
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let events_before = recorded_events().count();
+            assert!(subscriptions.accept_ownership().is_ok());
             assert_eq!(subscriptions.owner, accounts.bob);
+            assert_eq!(subscriptions.pending_owner(), None);
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            let decoded_event = <OwnershipTransferred>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!(
+                (decoded_event.from, decoded_event.to),
+                (accounts.alice, accounts.bob)
+            );
+        }
+
+        #[ink::test]
+        fn accept_ownership_by_wrong_account_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                subscriptions.accept_ownership(),
+                Err(Error::NotPendingOwner)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let events_before = recorded_events().count();
+            subscriptions.transfer_ownership(accounts.bob).unwrap();
+            let events = recorded_events().skip(events_before).collect::<Vec<_>>();
+            let decoded_event = <OwnershipTransferInitiated>::decode(&mut &events[0].data[..])
+                .expect("invalid event buffer");
+            assert_eq!(
+                (decoded_event.from, decoded_event.to),
+                (accounts.alice, accounts.bob)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                subscriptions.accept_ownership(),
+                Err(Error::NotPendingOwner)
+            );
+            assert_eq!(subscriptions.owner, accounts.alice);
+            assert_eq!(subscriptions.pending_owner(), Some(accounts.bob));
+        }
+
+        #[ink::test]
+        fn cancel_ownership_transfer_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+            subscriptions.transfer_ownership(accounts.bob).unwrap();
+
+            // only the owner can cancel the transfer
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                subscriptions.cancel_ownership_transfer(),
+                Err(Error::NotAuthorized)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            subscriptions.cancel_ownership_transfer().unwrap();
+            assert_eq!(subscriptions.pending_owner(), None);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                subscriptions.accept_ownership(),
+                Err(Error::NotPendingOwner)
+            );
+            assert_eq!(subscriptions.owner, accounts.alice);
+        }
+
+        #[ink::test]
+        fn is_admin_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut subscriptions = Subscriptions::new(
+                1u128,
+                Hash::from(PROOF_VK_HASH),
+                MIN_REQUIRED_AGE,
+                MIN_INTERVALS,
+                MAX_INTERVALS,
+                GRACE_INTERVALS,
+                MAX_DURATION_BLOCKS,
+            );
+            assert!(subscriptions.is_admin(accounts.alice));
+            assert!(!subscriptions.is_admin(accounts.bob));
+
+            subscriptions.transfer_ownership(accounts.bob).unwrap();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            subscriptions.accept_ownership().unwrap();
+            assert!(!subscriptions.is_admin(accounts.alice));
+            assert!(subscriptions.is_admin(accounts.bob));
         }
 
         fn assert_new_subscription(
@@ -889,6 +5563,33 @@ mod subscriptions {
             assert_eq!(decoded_event.for_account, expected_for_account);
         }
 
+        fn assert_payment_settled(
+            event: &EmittedEvent,
+            expected_for_account: AccountId,
+            expected_intervals_charged: u32,
+            expected_amount: Balance,
+        ) {
+            let decoded_event =
+                <PaymentSettled>::decode(&mut &event.data[..]).expect("invalid event buffer");
+            assert_eq!(decoded_event.for_account, expected_for_account);
+            assert_eq!(decoded_event.intervals_charged, expected_intervals_charged);
+            assert_eq!(decoded_event.amount, expected_amount);
+        }
+
+        fn assert_updated_channel_handle(
+            event: &EmittedEvent,
+            expected_for_account: AccountId,
+            expected_external_channel_handle: String,
+        ) {
+            let decoded_event =
+                <UpdatedChannelHandle>::decode(&mut &event.data[..]).expect("invalid event buffer");
+            assert_eq!(decoded_event.for_account, expected_for_account);
+            assert_eq!(
+                decoded_event.external_channel_handle,
+                expected_external_channel_handle.into_bytes()
+            );
+        }
+
         fn assert_cancelled_subscriptions(
             event: &EmittedEvent,
             expected_for_accounts: Vec<ActiveSubscriptionAttr>,
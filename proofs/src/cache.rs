@@ -0,0 +1,162 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use anyhow::Result;
+
+use crate::proofs::Account;
+
+/// Identifies a generated zero knowledge proof, so retried requests can reuse it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProofKey {
+    /// Account for which the proof is generated
+    pub account: Account,
+    /// Age that is a witness of the proof
+    pub age: u64,
+    /// Range from (inclusive) proven by the proof
+    pub range_from: u64,
+    /// Range to (exclusive) proven by the proof
+    pub range_to: u64,
+    /// Distinguishes proofs generated for the same attributes, e.g. a request id
+    pub nonce: u64,
+}
+
+/// Least recently used cache of generated zero knowledge proofs.
+/// Proof generation is randomized (blinded with `OsRng`), so proving the same attributes twice
+/// yields different blobs. The cache returns one consistent blob per key instead, until the key
+/// is evicted.
+#[derive(Debug)]
+pub struct ProofCache {
+    /// Maximum number of cached proofs
+    capacity: usize,
+    /// Cached proofs, the most recently used at the back
+    entries: Mutex<VecDeque<(ProofKey, Vec<u8>)>>,
+}
+
+impl ProofCache {
+    /// Creates an empty cache
+    /// params:
+    /// * capacity - maximum number of cached proofs, 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Retrieves a cached proof, or generates and caches it when missing. The least recently
+    /// used proof is evicted when the cache is full
+    /// params:
+    /// * key - attributes of the proof
+    /// * generate - generates the proof on a cache miss
+    /// returns:
+    /// * serialized zero knowledge proof
+    pub fn get_or_generate(
+        &self,
+        key: ProofKey,
+        generate: impl FnOnce() -> Result<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        if let Some(proof) = self.get(&key) {
+            return Ok(proof);
+        }
+
+        // the lock is not held while proving, concurrent misses of the same key are both proven
+        // and the latter wins
+        let proof = generate()?;
+        if self.capacity > 0 {
+            let mut entries = self.entries.lock().unwrap();
+            entries.retain(|(cached, _)| *cached != key);
+            if entries.len() == self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back((key, proof.clone()));
+        }
+        Ok(proof)
+    }
+
+    /// Number of cached proofs
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Checks if no proof is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, key: &ProofKey) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap();
+        let position = entries.iter().position(|(cached, _)| cached == key)?;
+        let entry = entries.remove(position)?;
+        let proof = entry.1.clone();
+        entries.push_back(entry);
+        Some(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn key(nonce: u64) -> ProofKey {
+        ProofKey {
+            account: [1u8; 32],
+            age: 23,
+            range_from: 18,
+            range_to: 120,
+            nonce,
+        }
+    }
+
+    #[test]
+    fn test_cached_proof_is_not_regenerated() {
+        let cache = ProofCache::new(2);
+        let generated = Cell::new(0u8);
+        let generate = || {
+            generated.set(generated.get() + 1);
+            Ok(vec![generated.get()])
+        };
+
+        assert_eq!(cache.get_or_generate(key(1), generate).unwrap(), vec![1]);
+        assert_eq!(cache.get_or_generate(key(1), generate).unwrap(), vec![1]);
+        assert_eq!(generated.get(), 1);
+
+        // different nonce is a different proof
+        assert_eq!(cache.get_or_generate(key(2), generate).unwrap(), vec![2]);
+        assert_eq!(generated.get(), 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_least_recently_used_proof_is_evicted() {
+        let cache = ProofCache::new(2);
+        cache.get_or_generate(key(1), || Ok(vec![1])).unwrap();
+        cache.get_or_generate(key(2), || Ok(vec![2])).unwrap();
+        // key 1 is used, so key 2 becomes the least recently used one
+        cache.get_or_generate(key(1), || Ok(vec![0])).unwrap();
+        cache.get_or_generate(key(3), || Ok(vec![3])).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache.get_or_generate(key(1), || Ok(vec![0])).unwrap(),
+            vec![1]
+        );
+        assert_eq!(
+            cache.get_or_generate(key(2), || Ok(vec![0])).unwrap(),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn test_failed_generation_is_not_cached() {
+        let cache = ProofCache::new(2);
+        assert!(cache
+            .get_or_generate(key(1), || anyhow::bail!("proving failed"))
+            .is_err());
+        assert!(cache.is_empty());
+
+        let disabled = ProofCache::new(0);
+        disabled.get_or_generate(key(1), || Ok(vec![1])).unwrap();
+        assert!(disabled.is_empty());
+    }
+}
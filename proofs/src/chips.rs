@@ -1 +1,2 @@
+pub mod dynamic_in_range;
 pub mod in_range;
@@ -0,0 +1,273 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Instance, Selector},
+    poly::Rotation,
+};
+
+/// A gadget that checks that the value witnessed is in a range given by public inputs, so one
+/// trusted setup serves many ranges
+/// given
+///     value v
+///     [from..to)
+/// to check
+///     v - from = lo_0 + 2 * lo_1 + .. + 2^(MAX_BITS-1) * lo_(MAX_BITS-1)
+///     to - 1 - v = hi_0 + 2 * hi_1 + .. + 2^(MAX_BITS-1) * hi_(MAX_BITS-1)
+///     lo_i, hi_i are bits
+/// which means both differences are non-negative and lower than 2^MAX_BITS
+/// We take 4 public attributes passed as vector of instances which represent:
+///     * range from
+///     * range to
+///     * first part of account address, a subject of the proof
+///     * second part of account address, a subject of the proof
+///     | selector | v | from       | to         | bits_0..bits_(MAX_BITS-1) | a
+///     |        1 | x | instance_0 | instance_1 | lo_0..lo_(MAX_BITS-1)     | instance_2
+///     |        0 |   |            |            | hi_0..hi_(MAX_BITS-1)     | instance_3
+
+/// Represents configuration file for `dynamic_in_range` chip.
+#[derive(Debug, Clone)]
+pub struct DynamicInRangeConfig<F: Field, const MAX_BITS: usize> {
+    selector: Selector,
+    value: Column<Advice>,
+    from: Column<Advice>,
+    to: Column<Advice>,
+    bits: [Column<Advice>; MAX_BITS],
+    account: Column<Advice>,
+    instance: Column<Instance>,
+    _marker: PhantomData<F>,
+}
+
+/// Configures zero knowledge proof gates and allows for assignment of all witnessed values
+/// (advices)
+/// This chip configurates all gates to check if witness in between range from (inclusive) and
+/// range to (exclusive), both being public inputs lower than 2^MAX_BITS.
+pub struct DynamicInRangeChip<F: Field + From<u64>, const MAX_BITS: usize> {
+    config: DynamicInRangeConfig<F, MAX_BITS>,
+}
+
+impl<F: Field + From<u64>, const MAX_BITS: usize> DynamicInRangeChip<F, MAX_BITS> {
+    /// Creates new instance of the dynamic in range chip
+    pub fn construct(config: DynamicInRangeConfig<F, MAX_BITS>) -> Self {
+        Self { config }
+    }
+
+    /// Configures gates that checks if a given witnessed value is in the [from..to) range
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        value: Column<Advice>,
+        instance: Column<Instance>,
+    ) -> DynamicInRangeConfig<F, MAX_BITS> {
+        assert!(MAX_BITS > 0 && MAX_BITS < 64);
+        let selector = meta.selector();
+
+        let from = meta.advice_column();
+        let to = meta.advice_column();
+        let bits = core::array::from_fn(|_| meta.advice_column());
+        let account = meta.advice_column();
+
+        meta.enable_equality(from);
+        meta.enable_equality(to);
+        meta.enable_equality(account);
+        meta.enable_equality(instance);
+
+        meta.create_gate("dynamic in range", |meta| {
+            let selector = meta.query_selector(selector);
+            let value = meta.query_advice(value, Rotation::cur());
+            let from = meta.query_advice(from, Rotation::cur());
+            let to = meta.query_advice(to, Rotation::cur());
+            let lo = bits.map(|bit| meta.query_advice(bit, Rotation::cur()));
+            let hi = bits.map(|bit| meta.query_advice(bit, Rotation::next()));
+
+            let compose = |bits: &[Expression<F>]| {
+                bits.iter()
+                    .enumerate()
+                    .fold(Expression::Constant(F::ZERO), |expr, (i, bit)| {
+                        expr + bit.clone() * Expression::Constant(F::from(1u64 << i))
+                    })
+            };
+            let is_bit =
+                |bit: &Expression<F>| bit.clone() * (Expression::Constant(F::ONE) - bit.clone());
+
+            let mut constraints = lo.iter().chain(hi.iter()).map(is_bit).collect::<Vec<_>>();
+            constraints.push(value.clone() - from - compose(&lo));
+            constraints.push(to - Expression::Constant(F::ONE) - value - compose(&hi));
+            Constraints::with_selector(selector, constraints)
+        });
+
+        DynamicInRangeConfig {
+            selector,
+            value,
+            from,
+            to,
+            bits,
+            account,
+            instance,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Assigns witnessed value using the layouter
+    /// params:
+    /// * value - witnessed value
+    /// * range_from - range from (inclusive), must be equal to the first public input
+    /// * range_to - range to (exclusive), must be equal to the second public input
+    pub fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<u64>,
+        range_from: Value<u64>,
+        range_to: Value<u64>,
+    ) -> Result<(), Error> {
+        // out of range values wrap around, so they can't be decomposed into MAX_BITS bits
+        let lo = value.zip(range_from).map(|(v, from)| v.wrapping_sub(from));
+        let hi = value
+            .zip(range_to)
+            .map(|(v, to)| to.wrapping_sub(1).wrapping_sub(v));
+
+        layouter.assign_region(
+            || "assign value",
+            |mut region| {
+                self.config.selector.enable(&mut region, 0)?;
+                region.assign_advice(
+                    || "assign value",
+                    self.config.value,
+                    0,
+                    || value.map(F::from),
+                )?;
+                region.assign_advice_from_instance(
+                    || "range from",
+                    self.config.instance,
+                    0,
+                    self.config.from,
+                    0,
+                )?;
+                region.assign_advice_from_instance(
+                    || "range to",
+                    self.config.instance,
+                    1,
+                    self.config.to,
+                    0,
+                )?;
+
+                for (i, bit) in self.config.bits.iter().enumerate() {
+                    region.assign_advice(
+                        || "lower difference bit",
+                        *bit,
+                        0,
+                        || lo.map(|lo| F::from((lo >> i) & 1)),
+                    )?;
+                    region.assign_advice(
+                        || "upper difference bit",
+                        *bit,
+                        1,
+                        || hi.map(|hi| F::from((hi >> i) & 1)),
+                    )?;
+                }
+
+                region.assign_advice_from_instance(
+                    || "account low",
+                    self.config.instance,
+                    2,
+                    self.config.account,
+                    0,
+                )?;
+                region.assign_advice_from_instance(
+                    || "account high",
+                    self.config.instance,
+                    3,
+                    self.config.account,
+                    1,
+                )?;
+
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        halo2curves::{bn256::Fr as Fp, ff::PrimeField},
+        plonk::Circuit,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestCircuit {
+        value: Value<u64>,
+        range_from: Value<u64>,
+        range_to: Value<u64>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = DynamicInRangeConfig<Fp, 8>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let value = meta.advice_column();
+            let instance = meta.instance_column();
+            DynamicInRangeChip::<Fp, 8>::configure(meta, value, instance)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = DynamicInRangeChip::<Fp, 8>::construct(config);
+            chip.assign(
+                layouter.namespace(|| "assign value"),
+                self.value,
+                self.range_from,
+                self.range_to,
+            )
+        }
+    }
+
+    fn verify(value: u64, range_from: u64, range_to: u64, instances: [u64; 2]) -> bool {
+        let circuit = TestCircuit {
+            value: Value::known(value),
+            range_from: Value::known(range_from),
+            range_to: Value::known(range_to),
+        };
+        let account = [1u8; 32];
+        let instances = vec![
+            Fp::from(instances[0]),
+            Fp::from(instances[1]),
+            Fp::from_u128(u128::from_le_bytes(account[..16].try_into().unwrap())),
+            Fp::from_u128(u128::from_le_bytes(account[16..].try_into().unwrap())),
+        ];
+        let prover = MockProver::run(5, &circuit, vec![instances]).unwrap();
+        prover.verify().is_ok()
+    }
+
+    #[test]
+    fn test_in_range() {
+        for i in 18..65 {
+            assert!(verify(i, 18, 65, [18, 65]));
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        for i in (0..18).chain(65..255) {
+            assert!(!verify(i, 18, 65, [18, 65]));
+        }
+    }
+
+    #[test]
+    fn test_range_not_matching_public_inputs() {
+        assert!(!verify(30, 18, 65, [40, 65]));
+    }
+}
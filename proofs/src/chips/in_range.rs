@@ -1,11 +1,9 @@
 use std::marker::PhantomData;
 
 use halo2_proofs::{
-    arithmetic::Field,
     circuit::{Layouter, Value},
-    plonk::{
-        Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Instance, Selector,
-    },
+    halo2curves::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Instance, Selector},
     poly::Rotation,
 };
 
@@ -14,25 +12,28 @@ use halo2_proofs::{
 ///     value v
 ///     [from..to)
 /// to check
-///     (from-v)(from+1-v)..(to-1-v)
+///     v - from = lo_0 + 2 * lo_1 + .. + 2^(BITS-1) * lo_(BITS-1)
+///     to - 1 - v = hi_0 + 2 * hi_1 + .. + 2^(BITS-1) * hi_(BITS-1)
+///     lo_i, hi_i are bits
+/// where BITS is the number of bits of (to - 1 - from). Both differences are non-negative and
+/// not greater than (to - 1 - from), so the degree of constraints doesn't depend on the range
+/// width.
 /// We take 3 public attributes passed as vector if instances which represent:
-///     * value 0 (placeholder)
+///     * range from
 ///     * first part of account address, a subject of the proof
 ///     * second part of account address, a subject of the proof
-/// Expression
-/// selector_v * (from-v)(from+1-v)..(to-1-v) + selector_i*q_i*i + instance = 0
-///     | selector_v | selector_a | v | a          | q_a | instance
-///     |          1 |          0 | x | RANGE_FROM | -1  | instance_0 (range from)
-///     |          0 |          1 | 0 | instance_1 | -1  | instance_1 (account lower bits)
-///     |          0 |          1 | 0 | instance_2 | -1  | instance_2 (account upper bits)
+///     | selector | v | bits_0..bits_(BITS-1) | a
+///     |        1 | x | lo_0..lo_(BITS-1)     | instance_0 (range from)
+///     |        0 |   | hi_0..hi_(BITS-1)     | instance_1 (account lower bits)
+///     |        0 |   |                       | instance_2 (account upper bits)
 
 /// Represents configuration file for `in_range` chip.
 #[derive(Debug, Clone)]
-pub struct InRangeConfig<F: Field> {
-    selector_v: Selector,
+pub struct InRangeConfig<F: PrimeField> {
+    selector: Selector,
     value: Column<Advice>,
+    bits: Vec<Column<Advice>>,
     a: Column<Advice>,
-    q_a: Column<Fixed>,
     instance: Column<Instance>,
     _marker: PhantomData<F>,
 }
@@ -40,13 +41,27 @@ pub struct InRangeConfig<F: Field> {
 /// Configures zero knowledge proof gates and allows for assignment of all witnessed values
 /// (advices)
 /// This chip configurates all gates to check if witness in between RANGE_FROM (inclusive) and RANGE_TO (exclusive).
-pub struct InRangeChip<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize> {
+pub struct InRangeChip<F: PrimeField, const RANGE_FROM: usize, const RANGE_TO: usize> {
     config: InRangeConfig<F>,
 }
 
-impl<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize>
+impl<F: PrimeField, const RANGE_FROM: usize, const RANGE_TO: usize>
     InRangeChip<F, RANGE_FROM, RANGE_TO>
 {
+    /// Number of bits required to decompose differences between the value and range bounds
+    const BITS: usize = {
+        assert!(RANGE_FROM < RANGE_TO);
+        let max_diff = RANGE_TO - RANGE_FROM - 1;
+        if max_diff == 0 {
+            1
+        } else {
+            (usize::BITS - max_diff.leading_zeros()) as usize
+        }
+    };
+
+    /// Number of rows assigned by the chip
+    pub const ROWS: usize = 3;
+
     /// Creates new instance of the in range chip
     pub fn construct(config: InRangeConfig<F>) -> Self {
         Self { config }
@@ -58,44 +73,53 @@ impl<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize>
         value: Column<Advice>,
         instance: Column<Instance>,
     ) -> InRangeConfig<F> {
-        let selector_v = meta.selector();
+        let selector = meta.selector();
 
+        let bits = (0..Self::BITS)
+            .map(|_| meta.advice_column())
+            .collect::<Vec<_>>();
         let a = meta.advice_column();
-        let q_a = meta.fixed_column();
 
         meta.enable_equality(value);
         meta.enable_equality(a);
-        meta.enable_equality(q_a);
         meta.enable_equality(instance);
 
         meta.create_gate("in range", |meta| {
-            let selector_v = meta.query_selector(selector_v);
+            let selector = meta.query_selector(selector);
             let value = meta.query_advice(value, Rotation::cur());
-
             let a = meta.query_advice(a, Rotation::cur());
-            let q_a = meta.query_fixed(q_a, Rotation::cur());
-            let instance = meta.query_instance(instance, Rotation::cur());
+            let lo = bits
+                .iter()
+                .map(|bit| meta.query_advice(*bit, Rotation::cur()))
+                .collect::<Vec<_>>();
+            let hi = bits
+                .iter()
+                .map(|bit| meta.query_advice(*bit, Rotation::next()))
+                .collect::<Vec<_>>();
 
-            let in_range_exp = |range_from: usize, range_to: usize, v: Expression<F>| {
-                (range_from..range_to).fold(Expression::Constant(F::ONE), |expr, i| {
-                    expr * (Expression::Constant(F::from(i as u64)) - v.clone())
-                })
+            let constant = |v: usize| Expression::Constant(F::from(v as u64));
+            let compose = |bits: &[Expression<F>]| {
+                bits.iter()
+                    .enumerate()
+                    .fold(Expression::Constant(F::ZERO), |expr, (i, bit)| {
+                        expr + bit.clone() * Expression::Constant(F::from(1u64 << i))
+                    })
             };
+            let is_bit =
+                |bit: &Expression<F>| bit.clone() * (Expression::Constant(F::ONE) - bit.clone());
 
-            Constraints::with_selector(
-                selector_v,
-                [(
-                    "in range",
-                    in_range_exp(RANGE_FROM, RANGE_TO, value) + q_a * a + instance,
-                )],
-            )
+            let mut constraints = lo.iter().chain(hi.iter()).map(is_bit).collect::<Vec<_>>();
+            constraints.push(value.clone() - constant(RANGE_FROM) - compose(&lo));
+            constraints.push(constant(RANGE_TO - 1) - value - compose(&hi));
+            constraints.push(a - constant(RANGE_FROM));
+            Constraints::with_selector(selector, constraints)
         });
 
         InRangeConfig {
-            selector_v,
+            selector,
             value,
+            bits,
             a,
-            q_a,
             instance,
             _marker: PhantomData,
         }
@@ -103,42 +127,40 @@ impl<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize>
 
     /// Assigns witnessed value using the layouter
     pub fn assign(&self, mut layouter: impl Layouter<F>, value: Value<F>) -> Result<(), Error> {
+        // out of range values wrap around the field modulus, so their low bits don't compose back
+        let lo = value.map(|v| v - F::from(RANGE_FROM as u64));
+        let hi = value.map(|v| F::from((RANGE_TO - 1) as u64) - v);
+
         layouter.assign_region(
             || "assign value",
             |mut region| {
                 // enable range check
-                self.config.selector_v.enable(&mut region, 0)?;
+                self.config.selector.enable(&mut region, 0)?;
                 // assign value
                 region.assign_advice(|| "assign value", self.config.value, 0, || value)?;
 
-                region.assign_advice(
+                for (i, bit) in self.config.bits.iter().enumerate() {
+                    region.assign_advice(
+                        || "lower difference bit",
+                        *bit,
+                        0,
+                        || lo.map(|lo| Self::bit(&lo, i)),
+                    )?;
+                    region.assign_advice(
+                        || "upper difference bit",
+                        *bit,
+                        1,
+                        || hi.map(|hi| Self::bit(&hi, i)),
+                    )?;
+                }
+
+                region.assign_advice_from_instance(
                     || "range from",
-                    self.config.a,
+                    self.config.instance,
                     0,
-                    || Value::known(F::from(RANGE_FROM as u64)),
-                )?;
-                // region.assign_advice_from_instance(
-                // || "account low",
-                // self.config.instance,
-                // 0,
-                // self.config.a,
-                // 0,
-                // )?;
-                region.assign_fixed(
-                    || "fake instance",
-                    self.config.q_a,
+                    self.config.a,
                     0,
-                    || Value::known(F::ONE.neg()),
-                )?;
-
-                self.config.selector_v.enable(&mut region, 1)?;
-                region.assign_advice(
-                    || "assign value fake",
-                    self.config.value,
-                    1,
-                    || Value::known(F::from(RANGE_FROM as u64)),
                 )?;
-                // self.config.selector_a.enable(&mut region, 1)?;
                 region.assign_advice_from_instance(
                     || "account low",
                     self.config.instance,
@@ -146,21 +168,6 @@ impl<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize>
                     self.config.a,
                     1,
                 )?;
-                region.assign_fixed(
-                    || "account low selector",
-                    self.config.q_a,
-                    1,
-                    || Value::known(F::ONE.neg()),
-                )?;
-
-                self.config.selector_v.enable(&mut region, 2)?;
-                region.assign_advice(
-                    || "assign value fake",
-                    self.config.value,
-                    2,
-                    || Value::known(F::from(RANGE_FROM as u64)),
-                )?;
-                // self.config.selector_a.enable(&mut region, 2)?;
                 region.assign_advice_from_instance(
                     || "account high",
                     self.config.instance,
@@ -168,37 +175,34 @@ impl<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize>
                     self.config.a,
                     2,
                 )?;
-                region.assign_fixed(
-                    || "account high selector",
-                    self.config.q_a,
-                    2,
-                    || Value::known(F::ONE.neg()),
-                )?;
 
                 Ok(())
             },
         )
     }
+
+    /// Retrieves i-th bit of a field element, based on its little-endian representation
+    fn bit(value: &F, i: usize) -> F {
+        let repr = value.to_repr();
+        F::from(((repr.as_ref()[i / 8] >> (i % 8)) & 1) as u64)
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use halo2_proofs::{
-        circuit::SimpleFloorPlanner,
-        dev::MockProver,
-        halo2curves::{bn256::Fr as Fp, ff::PrimeField},
-        plonk::Circuit,
+        circuit::SimpleFloorPlanner, dev::MockProver, halo2curves::bn256::Fr as Fp, plonk::Circuit,
     };
 
     use super::*;
 
     #[derive(Default)]
-    struct TestCircuit<F: Field, const RANGE_FROM: usize, const RANGE_TO: usize> {
+    struct TestCircuit<F: PrimeField, const RANGE_FROM: usize, const RANGE_TO: usize> {
         value: Value<F>,
     }
 
-    impl<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize> Circuit<F>
+    impl<F: PrimeField, const RANGE_FROM: usize, const RANGE_TO: usize> Circuit<F>
         for TestCircuit<F, RANGE_FROM, RANGE_TO>
     {
         type Config = InRangeConfig<F>;
@@ -265,4 +269,48 @@ mod tests {
             assert!(prover.verify().is_err());
         }
     }
+
+    #[test]
+    fn test_full_range() {
+        // the whole 0..120 range is checked with the same k as narrower ranges
+        let k = 4;
+        let account = [1u8; 32];
+        for i in 0..130 {
+            let circuit = TestCircuit::<Fp, 0, 120> {
+                value: Value::known(Fp::from(i as u64)),
+            };
+            let instances = init_public_input(0, account).to_vec();
+            let prover = MockProver::run(k, &circuit, vec![instances]).unwrap();
+            assert_eq!(prover.verify().is_ok(), i < 120);
+        }
+    }
+
+    #[test]
+    fn test_range_from_not_matching_public_input() {
+        let k = 4;
+        let account = [1u8; 32];
+        let circuit = TestCircuit::<Fp, 18, 120> {
+            value: Value::known(Fp::from(30)),
+        };
+        let instances = init_public_input(21, account).to_vec();
+        let prover = MockProver::run(k, &circuit, vec![instances]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_constraint_degree() {
+        let mut narrow = ConstraintSystem::<Fp>::default();
+        let value = narrow.advice_column();
+        let instance = narrow.instance_column();
+        InRangeChip::<Fp, 18, 21>::configure(&mut narrow, value, instance);
+
+        let mut wide = ConstraintSystem::<Fp>::default();
+        let value = wide.advice_column();
+        let instance = wide.instance_column();
+        InRangeChip::<Fp, 0, 120>::configure(&mut wide, value, instance);
+
+        // degree doesn't grow with the range width
+        assert_eq!(narrow.degree(), wide.degree());
+        assert!(wide.degree() <= 3);
+    }
 }
@@ -1 +1,13 @@
+pub mod dynamic_in_range;
 pub mod in_range;
+
+/// Size of a circuit, which helps to choose the maximum polynomial degree (k) of the setup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitStats {
+    /// Number of rows required by the circuit, including rows reserved for blinding factors
+    pub rows: usize,
+    /// Maximum degree of the circuit's constraints
+    pub max_degree: usize,
+    /// Smallest k, such that 2^k rows fit the circuit
+    pub recommended_k: u32,
+}
@@ -0,0 +1,61 @@
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{SimpleFloorPlanner, Value},
+    plonk::Circuit,
+};
+
+use crate::chips::dynamic_in_range::{DynamicInRangeChip, DynamicInRangeConfig};
+
+/// Circuit for proving if value is between range from (inclusive) and range to (exclusive),
+/// both passed as public inputs and lower than 2^MAX_BITS
+#[derive(Default, Clone)]
+pub struct DynamicInRangeCircuit<F: Field + From<u64>, const MAX_BITS: usize> {
+    value: Value<u64>,
+    range_from: Value<u64>,
+    range_to: Value<u64>,
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<F: Field + From<u64>, const MAX_BITS: usize> DynamicInRangeCircuit<F, MAX_BITS> {
+    /// Creates circuit with witnessed value and range, which must match the public inputs
+    pub fn new(value: u64, range_from: u64, range_to: u64) -> Self {
+        Self {
+            value: Value::known(value),
+            range_from: Value::known(range_from),
+            range_to: Value::known(range_to),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<F: Field + From<u64>, const MAX_BITS: usize> Circuit<F>
+    for DynamicInRangeCircuit<F, MAX_BITS>
+{
+    type Config = DynamicInRangeConfig<F, MAX_BITS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
+        let value = meta.advice_column();
+        let instance = meta.instance_column();
+        DynamicInRangeChip::<F, MAX_BITS>::configure(meta, value, instance)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl halo2_proofs::circuit::Layouter<F>,
+    ) -> Result<(), halo2_proofs::plonk::Error> {
+        let chip = DynamicInRangeChip::<F, MAX_BITS>::construct(config);
+        chip.assign(
+            layouter.namespace(|| "assign value"),
+            self.value,
+            self.range_from,
+            self.range_to,
+        )?;
+        Ok(())
+    }
+}
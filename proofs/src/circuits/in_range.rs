@@ -1,18 +1,37 @@
 use halo2_proofs::{
-    arithmetic::Field,
     circuit::{SimpleFloorPlanner, Value},
-    plonk::Circuit,
+    halo2curves::ff::PrimeField,
+    plonk::{Circuit, ConstraintSystem},
 };
 
+use super::CircuitStats;
 use crate::chips::in_range::{InRangeChip, InRangeConfig};
 
 /// Circuit for proving if value is between RANGE_FROM (inclusive) and RANGE_TO (exclusive)
 #[derive(Default, Clone)]
-pub struct InRangeCircuit<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize> {
+pub struct InRangeCircuit<F: PrimeField, const RANGE_FROM: usize, const RANGE_TO: usize> {
     pub value: Value<F>,
 }
 
-impl<F: Field + From<u64>, const RANGE_FROM: usize, const RANGE_TO: usize> Circuit<F>
+impl<F: PrimeField, const RANGE_FROM: usize, const RANGE_TO: usize>
+    InRangeCircuit<F, RANGE_FROM, RANGE_TO>
+{
+    /// Measures the circuit using its constraint system
+    /// returns:
+    /// * number of required rows, maximum constraint degree and recommended k
+    pub fn stats() -> CircuitStats {
+        let mut meta = ConstraintSystem::<F>::default();
+        Self::configure(&mut meta);
+        let rows = InRangeChip::<F, RANGE_FROM, RANGE_TO>::ROWS + meta.minimum_rows();
+        CircuitStats {
+            rows,
+            max_degree: meta.degree(),
+            recommended_k: rows.next_power_of_two().trailing_zeros(),
+        }
+    }
+}
+
+impl<F: PrimeField, const RANGE_FROM: usize, const RANGE_TO: usize> Circuit<F>
     for InRangeCircuit<F, RANGE_FROM, RANGE_TO>
 {
     type Config = InRangeConfig<F>;
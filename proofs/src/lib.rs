@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod chips;
 pub mod circuits;
 pub mod proofs;
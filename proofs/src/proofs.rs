@@ -5,16 +5,24 @@ use halo2_proofs::{
         bn256::{Bn256, Fr as Fp, G1Affine},
         ff::PrimeField,
     },
-    plonk::{create_proof, keygen_pk, keygen_vk, Circuit, ProvingKey, VerifyingKey},
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ProvingKey, VerifyingKey},
     poly::{
         commitment::Params,
-        kzg::{commitment::ParamsKZG, multiopen::ProverGWC},
+        kzg::{
+            commitment::ParamsKZG,
+            multiopen::{ProverGWC, VerifierGWC},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
     },
-    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
 };
 use rand::rngs::OsRng;
 
-use crate::circuits::in_range::InRangeCircuit;
+use crate::circuits::{
+    dynamic_in_range::DynamicInRangeCircuit, in_range::InRangeCircuit, CircuitStats,
+};
 
 /// Represents on-chain account for which proof is generated
 pub type Account = [u8; 32];
@@ -37,10 +45,30 @@ impl Setup {
     /// params:
     /// * k - maximum polynomial degree
     pub fn generate<C: Circuit<Fp> + Default + Clone>(k: u32) -> Result<Self> {
+        Self::generate_with_progress::<C>(k, None)
+    }
+
+    /// Generate initial setup reporting progress of the generation phases, i.e. `params`,
+    /// `vk keygen` and `pk keygen`
+    /// params:
+    /// * k - maximum polynomial degree
+    /// * progress - optional callback invoked with a name of the phase when it starts
+    pub fn generate_with_progress<C: Circuit<Fp> + Default + Clone>(
+        k: u32,
+        progress: Option<&dyn Fn(&str)>,
+    ) -> Result<Self> {
+        let report = |phase: &str| {
+            if let Some(progress) = progress {
+                progress(phase)
+            }
+        };
         let circuit = C::default();
+        report("params");
         let params = ParamsKZG::<Bn256>::setup(k, ParamsKZG::<Bn256>::mock_rng());
         //let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+        report("vk keygen");
         let vk = keygen_vk(&params, &circuit).context("vk generation failed")?;
+        report("pk keygen");
         let pk = keygen_pk(&params, vk.clone(), &circuit).context("pk generation failed")?;
         Ok(Self { k, pk, vk, params })
     }
@@ -58,6 +86,21 @@ impl Setup {
         Ok(buffer)
     }
 
+    /// Checks if proofs generated with this setup verify with the other setup and vice versa, i.e.
+    /// both setups share the polynomial degree, verifier params and verification key. The key is
+    /// compared by its transcript representation as well, as serialized key doesn't cover the
+    /// constraint system, e.g. the range bounds of the circuit
+    /// params:
+    /// * other - setup to compare with
+    pub fn proof_compatible_with(&self, other: &Setup) -> bool {
+        let format = halo2_proofs::SerdeFormat::RawBytesUnchecked;
+        self.k == other.k
+            && self.params.g2() == other.params.g2()
+            && self.params.s_g2() == other.params.s_g2()
+            && self.vk.to_bytes(format) == other.vk.to_bytes(format)
+            && self.vk.transcript_repr() == other.vk.transcript_repr()
+    }
+
     /// Serializes verification key together with polynomial degree
     /// returns:
     /// * array of bytes including serialized polynomial degree (k) and verification key (vk)
@@ -97,7 +140,191 @@ impl Setup {
     }
 }
 
-const RANGE_TO: usize = 120;
+/// Serializes public inputs of the proof, each field element as 32 little endian bytes
+pub fn instances_to_bytes(instances: &[Fp]) -> Vec<u8> {
+    instances
+        .iter()
+        .flat_map(|instance| instance.to_repr())
+        .collect()
+}
+
+/// Serializes public inputs of the minimum age proof exactly as the subscriptions smart contract
+/// does before passing them to the chain extension: minimum required age followed by two 128-bit
+/// chunks of the account, each in little endian and padded with zeros to 256 bits
+/// params:
+/// * min_age - minimum required age the proof is verified against
+/// * account - account address for which the proof is generated
+pub fn min_age_public_input_bytes(min_age: u128, account: &Account) -> Vec<u8> {
+    let mut inputs = Vec::<u8>::new();
+    inputs.extend(min_age.to_le_bytes());
+    inputs.extend([0u8; 16]);
+    inputs.extend(&account[..16]);
+    inputs.extend([0u8; 16]);
+    inputs.extend(&account[16..]);
+    inputs.extend([0u8; 16]);
+    inputs
+}
+
+/// Everything needed to verify a proof in one call, so the proof can't be verified with
+/// a mismatching verifying key or params
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationBundle {
+    /// Serialized zero knowledge proof
+    pub proof: Vec<u8>,
+    /// Serialized verifying key
+    pub vk_bytes: Vec<u8>,
+    /// Serialized params of the polynomial commitment schema
+    pub params_bytes: Vec<u8>,
+    /// Public inputs of the proof
+    pub instances: [Fp; 3],
+}
+
+impl VerificationBundle {
+    /// Serializes the bundle to a single blob, each variable length part prefixed with its
+    /// length
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        for part in [&self.proof, &self.vk_bytes, &self.params_bytes] {
+            buffer.extend((part.len() as u32).to_le_bytes());
+            buffer.extend(part);
+        }
+        buffer.extend(instances_to_bytes(&self.instances));
+        buffer
+    }
+
+    /// Restores the bundle from a blob created by `to_bytes()`
+    /// params:
+    /// * buffer - serialized bundle
+    /// returns:
+    /// * deserialized bundle or error
+    pub fn from_bytes(mut buffer: &[u8]) -> Result<Self> {
+        let mut read = |len: usize| -> Result<&[u8]> {
+            if buffer.len() < len {
+                anyhow::bail!("verification bundle too short");
+            }
+            let (part, rest) = buffer.split_at(len);
+            buffer = rest;
+            Ok(part)
+        };
+        let mut read_part = || -> Result<Vec<u8>> {
+            let len = u32::from_le_bytes(read(4)?.try_into()?) as usize;
+            Ok(read(len)?.to_vec())
+        };
+        let proof = read_part()?;
+        let vk_bytes = read_part()?;
+        let params_bytes = read_part()?;
+
+        let mut instances = [Fp::from(0); 3];
+        for instance in instances.iter_mut() {
+            let repr: [u8; 32] = read(32)?.try_into()?;
+            *instance = Option::from(Fp::from_repr(repr)).context("invalid public input")?;
+        }
+        if !buffer.is_empty() {
+            anyhow::bail!("unexpected bytes after verification bundle");
+        }
+
+        Ok(Self {
+            proof,
+            vk_bytes,
+            params_bytes,
+            instances,
+        })
+    }
+}
+
+/// Magic bytes starting a serialized `MinAgeProofArtifact`
+const ARTIFACT_MAGIC: [u8; 4] = *b"MAPA";
+/// Version of the `MinAgeProofArtifact` serialization format
+const ARTIFACT_VERSION: u8 = 1;
+
+/// Minimum age zero knowledge proof together with the public attributes it has been generated
+/// for, so the proof can't be mistaken for a proof of another account or age policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinAgeProofArtifact {
+    /// Serialized zero knowledge proof
+    pub bytes: Vec<u8>,
+    /// Account for which the proof has been generated
+    pub account: Account,
+    /// Minimum age (inclusive) proven
+    pub range_from: u64,
+    /// Maximum age (exclusive) proven
+    pub range_to: u64,
+}
+
+impl MinAgeProofArtifact {
+    /// Serializes the artifact to a single blob: a header (magic bytes and format version),
+    /// the account, the range and the proof prefixed with its length
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = ARTIFACT_MAGIC.to_vec();
+        buffer.push(ARTIFACT_VERSION);
+        buffer.extend(self.account);
+        buffer.extend(self.range_from.to_le_bytes());
+        buffer.extend(self.range_to.to_le_bytes());
+        buffer.extend((self.bytes.len() as u32).to_le_bytes());
+        buffer.extend(&self.bytes);
+        buffer
+    }
+
+    /// Restores the artifact from a blob created by `to_bytes()`
+    /// params:
+    /// * buffer - serialized artifact
+    /// returns:
+    /// * deserialized artifact or error, e.g. when the blob is not an artifact
+    pub fn from_bytes(mut buffer: &[u8]) -> Result<Self> {
+        let mut read = |len: usize| -> Result<&[u8]> {
+            if buffer.len() < len {
+                anyhow::bail!("proof artifact too short");
+            }
+            let (part, rest) = buffer.split_at(len);
+            buffer = rest;
+            Ok(part)
+        };
+        if read(ARTIFACT_MAGIC.len())? != ARTIFACT_MAGIC {
+            anyhow::bail!("not a proof artifact");
+        }
+        let version = read(1)?[0];
+        if version != ARTIFACT_VERSION {
+            anyhow::bail!("unsupported proof artifact version {}", version);
+        }
+        let account: Account = read(32)?.try_into()?;
+        let range_from = u64::from_le_bytes(read(8)?.try_into()?);
+        let range_to = u64::from_le_bytes(read(8)?.try_into()?);
+        let len = u32::from_le_bytes(read(4)?.try_into()?) as usize;
+        let bytes = read(len)?.to_vec();
+        if !buffer.is_empty() {
+            anyhow::bail!("unexpected bytes after proof artifact");
+        }
+
+        Ok(Self {
+            bytes,
+            account,
+            range_from,
+            range_to,
+        })
+    }
+
+    /// Verifies the proof against the account and the minimum age of the artifact
+    /// params:
+    /// * setup - trusted setup used to generate the proof
+    pub fn verify(&self, setup: &Setup) -> Result<()> {
+        let instances = [
+            Fp::from(self.range_from),
+            Fp::from_u128(u128::from_le_bytes(self.account[..16].try_into()?)),
+            Fp::from_u128(u128::from_le_bytes(self.account[16..].try_into()?)),
+        ];
+        verify_proof::<_, VerifierGWC<_>, _, _, _>(
+            &setup.params,
+            &setup.vk,
+            SingleStrategy::new(&setup.params),
+            &[&[&instances]],
+            &mut Blake2bRead::<_, _, Challenge255<_>>::init(self.bytes.as_slice()),
+        )
+        .map_err(anyhow::Error::msg)
+    }
+}
+
+/// Upper bound (exclusive) of the age proven by the minimum age zero knowledge proof
+pub const RANGE_TO: usize = 120;
 const CIRCUIT_MAX_K: u32 = 5;
 
 #[derive(Debug, Clone)]
@@ -108,9 +335,24 @@ impl<const RANGE_FROM: usize> MinAgeProof<RANGE_FROM> {
         Self {}
     }
 
+    /// Measures the minimum age circuit, e.g. to choose k of the trusted setup
+    pub fn circuit_stats() -> CircuitStats {
+        InRangeCircuit::<Fp, RANGE_FROM, RANGE_TO>::stats()
+    }
+
     /// Generates trusted setup for minimum age zero knowledge proof
     pub fn generate_setup() -> Result<Setup> {
-        Setup::generate::<InRangeCircuit<Fp, RANGE_FROM, RANGE_TO>>(CIRCUIT_MAX_K)
+        Self::generate_setup_with_progress(None)
+    }
+
+    /// Generates trusted setup for minimum age zero knowledge proof reporting progress
+    /// params:
+    /// * progress - optional callback invoked with a name of each generation phase
+    pub fn generate_setup_with_progress(progress: Option<&dyn Fn(&str)>) -> Result<Setup> {
+        Setup::generate_with_progress::<InRangeCircuit<Fp, RANGE_FROM, RANGE_TO>>(
+            CIRCUIT_MAX_K,
+            progress,
+        )
     }
 
     /// Deserializes vector of bytes to the zero knowledge proof setup
@@ -119,7 +361,16 @@ impl<const RANGE_FROM: usize> MinAgeProof<RANGE_FROM> {
     /// returns:
     /// * trusted setup for minimum age zero knowlege proof
     pub fn load_setup(buffer: Vec<u8>) -> Result<Setup> {
-        Setup::from_bytes::<InRangeCircuit<Fp, RANGE_FROM, RANGE_TO>>(&mut buffer.as_slice())
+        Self::load_setup_from_slice(&buffer)
+    }
+
+    /// Deserializes a slice of bytes (e.g. memory-mapped file) to the zero knowledge proof setup
+    /// params:
+    /// * buffer - serialized to byte array zero knowledge proof setup
+    /// returns:
+    /// * trusted setup for minimum age zero knowlege proof
+    pub fn load_setup_from_slice(mut buffer: &[u8]) -> Result<Setup> {
+        Setup::from_bytes::<InRangeCircuit<Fp, RANGE_FROM, RANGE_TO>>(&mut buffer)
     }
 
     /// Generates zero knowledge proof that proofs age to be greater than RANGE_FROM
@@ -151,6 +402,125 @@ impl<const RANGE_FROM: usize> MinAgeProof<RANGE_FROM> {
         Ok(transcript.finalize())
     }
 
+    /// Generates zero knowledge proof that proofs age to be greater than RANGE_FROM, together
+    /// with the attributes it is generated for. Age is validated before witnessing, so no time
+    /// is wasted on proofs that can't be verified.
+    /// params:
+    /// * setup - trusted setup which can be generated using `generate_setup()` function
+    /// * age - age that is a witness, must be in [RANGE_FROM..RANGE_TO)
+    /// * for_account - account address for which the proof is generated
+    pub fn generate_artifact(
+        &self,
+        setup: &Setup,
+        age: u64,
+        for_account: &Account,
+    ) -> Result<MinAgeProofArtifact> {
+        if !(RANGE_FROM as u64..RANGE_TO as u64).contains(&age) {
+            anyhow::bail!(
+                "age {} out of the proven range [{}..{})",
+                age,
+                RANGE_FROM,
+                RANGE_TO
+            );
+        }
+        Ok(MinAgeProofArtifact {
+            bytes: self.generate_proof(setup, age, for_account)?,
+            account: *for_account,
+            range_from: RANGE_FROM as u64,
+            range_to: RANGE_TO as u64,
+        })
+    }
+
+    /// Verifies zero knowledge proof that proofs age to be greater than RANGE_FROM
+    /// params:
+    /// * setup - trusted setup used to generate the proof
+    /// * proof - serialized zero knowledge proof
+    /// * for_account - account address for which the proof is expected to be generated
+    pub fn verify_proof(&self, setup: &Setup, proof: &[u8], for_account: Account) -> Result<()> {
+        self.verify_proof_with_instances(setup, proof, &self.public_input(for_account))
+    }
+
+    /// Verifies zero knowledge proof against already known public inputs
+    /// params:
+    /// * setup - trusted setup used to generate the proof
+    /// * proof - serialized zero knowledge proof
+    /// * instances - public inputs of the proof, see `public_input()`
+    pub fn verify_proof_with_instances(
+        &self,
+        setup: &Setup,
+        proof: &[u8],
+        instances: &[Fp; 3],
+    ) -> Result<()> {
+        verify_proof::<_, VerifierGWC<_>, _, _, _>(
+            &setup.params,
+            &setup.vk,
+            SingleStrategy::new(&setup.params),
+            &[&[instances]],
+            &mut Blake2bRead::<_, _, Challenge255<_>>::init(proof),
+        )
+        .map_err(anyhow::Error::msg)
+    }
+
+    /// Generates zero knowledge proof together with everything needed to verify it
+    /// params:
+    /// * setup - trusted setup which can be generated using `generate_setup()` function
+    /// * age - age that is a witness
+    /// * for_account - account address for which the proof is generated
+    pub fn generate_verification_bundle(
+        &self,
+        setup: &Setup,
+        age: u64,
+        for_account: &Account,
+    ) -> Result<VerificationBundle> {
+        let mut params_bytes = vec![];
+        setup
+            .params
+            .write_custom(
+                &mut params_bytes,
+                halo2_proofs::SerdeFormat::RawBytesUnchecked,
+            )
+            .context("failed to serialize ZKP params")?;
+        Ok(VerificationBundle {
+            proof: self.generate_proof(setup, age, for_account)?,
+            vk_bytes: setup
+                .vk
+                .to_bytes(halo2_proofs::SerdeFormat::RawBytesUnchecked),
+            params_bytes,
+            instances: self.public_input(*for_account),
+        })
+    }
+
+    /// Verifies zero knowledge proof using the verifying key, params and public inputs shipped
+    /// with it
+    /// params:
+    /// * bundle - proof together with everything needed to verify it
+    /// returns:
+    /// * true if the proof is valid, false otherwise, e.g. when the bundle can't be deserialized
+    pub fn verify_bundle(&self, bundle: &VerificationBundle) -> bool {
+        let verify = || -> Result<()> {
+            let params = ParamsKZG::<Bn256>::read_custom(
+                &mut bundle.params_bytes.as_slice(),
+                halo2_proofs::SerdeFormat::RawBytesUnchecked,
+            )
+            .context("failed to read ZKP params")?;
+            let vk =
+                VerifyingKey::<G1Affine>::from_bytes::<InRangeCircuit<Fp, RANGE_FROM, RANGE_TO>>(
+                    &bundle.vk_bytes,
+                    halo2_proofs::SerdeFormat::RawBytesUnchecked,
+                )
+                .context("failed to read verifying key")?;
+            verify_proof::<_, VerifierGWC<_>, _, _, _>(
+                &params,
+                &vk,
+                SingleStrategy::new(&params),
+                &[&[&bundle.instances]],
+                &mut Blake2bRead::<_, _, Challenge255<_>>::init(bundle.proof.as_slice()),
+            )
+            .map_err(anyhow::Error::msg)
+        };
+        verify().is_ok()
+    }
+
     pub fn public_input(&self, account: Account) -> [Fp; 3] {
         [
             Fp::from_u128(RANGE_FROM as u128),
@@ -158,6 +528,111 @@ impl<const RANGE_FROM: usize> MinAgeProof<RANGE_FROM> {
             Fp::from_u128(u128::from_le_bytes(account[16..].try_into().unwrap())),
         ]
     }
+
+    /// Serializes public inputs exactly as the subscriptions smart contract does before passing
+    /// them to the chain extension, see `min_age_public_input_bytes`
+    /// params:
+    /// * account - account address for which the proof is generated
+    pub fn public_input_bytes(&self, account: Account) -> Vec<u8> {
+        min_age_public_input_bytes(RANGE_FROM as u128, &account)
+    }
+}
+
+/// Maximum bit width of ranges of the `DynamicRangeProof`, i.e. range bounds must be lower than
+/// 2^DYNAMIC_RANGE_MAX_BITS
+pub const DYNAMIC_RANGE_MAX_BITS: usize = 8;
+
+/// Proof of age being in a range, which bounds are public inputs instead of const generics.
+/// One trusted setup serves many age policies.
+#[derive(Debug, Clone, Default)]
+pub struct DynamicRangeProof {}
+
+impl DynamicRangeProof {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Generates trusted setup for dynamic range zero knowledge proof, common for all ranges
+    pub fn generate_setup() -> Result<Setup> {
+        Setup::generate::<DynamicInRangeCircuit<Fp, DYNAMIC_RANGE_MAX_BITS>>(CIRCUIT_MAX_K)
+    }
+
+    /// Deserializes vector of bytes to the zero knowledge proof setup
+    /// params:
+    /// * buffer - serialized to byte array zero knowledge proof setup
+    /// returns:
+    /// * trusted setup for dynamic range zero knowlege proof
+    pub fn load_setup(buffer: Vec<u8>) -> Result<Setup> {
+        Setup::from_bytes::<DynamicInRangeCircuit<Fp, DYNAMIC_RANGE_MAX_BITS>>(
+            &mut buffer.as_slice(),
+        )
+    }
+
+    /// Generates zero knowledge proof that proofs age to be in [range_from..range_to)
+    /// params:
+    /// * setup - trusted setup which can be generated using `generate_setup()` function
+    /// * age - age that is a witness
+    /// * range_from - range from (inclusive)
+    /// * range_to - range to (exclusive)
+    /// * for_account - account address for which proof is generated
+    pub fn generate_proof(
+        &self,
+        setup: &Setup,
+        age: u64,
+        range_from: u64,
+        range_to: u64,
+        for_account: &Account,
+    ) -> Result<Vec<u8>> {
+        let circuit =
+            DynamicInRangeCircuit::<Fp, DYNAMIC_RANGE_MAX_BITS>::new(age, range_from, range_to);
+        let instances = self.public_input(range_from, range_to, *for_account);
+
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof::<_, ProverGWC<'_, Bn256>, _, _, _, _>(
+            &setup.params,
+            &setup.pk,
+            &[circuit],
+            &[&[&instances]],
+            OsRng,
+            &mut transcript,
+        )?;
+        Ok(transcript.finalize())
+    }
+
+    /// Verifies zero knowledge proof that proofs age to be in [range_from..range_to)
+    /// params:
+    /// * setup - trusted setup used to generate the proof
+    /// * proof - serialized zero knowledge proof
+    /// * range_from - range from (inclusive)
+    /// * range_to - range to (exclusive)
+    /// * for_account - account address for which the proof is expected to be generated
+    pub fn verify_proof(
+        &self,
+        setup: &Setup,
+        proof: &[u8],
+        range_from: u64,
+        range_to: u64,
+        for_account: Account,
+    ) -> Result<()> {
+        let instances = self.public_input(range_from, range_to, for_account);
+        verify_proof::<_, VerifierGWC<_>, _, _, _>(
+            &setup.params,
+            &setup.vk,
+            SingleStrategy::new(&setup.params),
+            &[&[&instances]],
+            &mut Blake2bRead::<_, _, Challenge255<_>>::init(proof),
+        )
+        .map_err(anyhow::Error::msg)
+    }
+
+    pub fn public_input(&self, range_from: u64, range_to: u64, account: Account) -> [Fp; 4] {
+        [
+            Fp::from(range_from),
+            Fp::from(range_to),
+            Fp::from_u128(u128::from_le_bytes(account[..16].try_into().unwrap())),
+            Fp::from_u128(u128::from_le_bytes(account[16..].try_into().unwrap())),
+        ]
+    }
 }
 
 #[cfg(test)]
@@ -210,6 +685,26 @@ mod tests {
         .map_err(anyhow::Error::msg)
     }
 
+    #[test]
+    fn test_public_input_bytes() {
+        let account: Account = core::array::from_fn(|i| i as u8 + 1);
+        let mut expected = vec![0u8; 96];
+        expected[0] = 18;
+        expected[32..48].copy_from_slice(&account[..16]);
+        expected[64..80].copy_from_slice(&account[16..]);
+
+        let min_age_proof = MinAgeProof::<REQUIRED_AGE_18>::new();
+        assert_eq!(min_age_proof.public_input_bytes(account), expected);
+        assert_eq!(
+            min_age_proof.public_input_bytes(account),
+            instances_to_bytes(&min_age_proof.public_input(account))
+        );
+
+        // a different minimum age changes only the first input
+        expected[0] = 21;
+        assert_eq!(min_age_public_input_bytes(21, &account), expected);
+    }
+
     #[test]
     fn test_valid_proof() {
         assert!(validate(generate_proof::<REQUIRED_AGE_18>(19, ACCOUNT).unwrap()).is_ok());
@@ -251,6 +746,162 @@ mod tests {
         assert!(validate(invalid_setup).is_err());
     }
 
+    #[test]
+    fn test_verify_proof_with_instances() {
+        let setup = MinAgeProof::<REQUIRED_AGE_18>::generate_setup().unwrap();
+        let min_age_proof = MinAgeProof::<REQUIRED_AGE_18>::new();
+        let proof = min_age_proof.generate_proof(&setup, 19, &ACCOUNT).unwrap();
+
+        let instances = min_age_proof.public_input(ACCOUNT);
+        assert!(min_age_proof
+            .verify_proof_with_instances(&setup, &proof, &instances)
+            .is_ok());
+
+        let mut tampered = instances;
+        tampered[0] = Fp::from_u128(17);
+        assert!(min_age_proof
+            .verify_proof_with_instances(&setup, &proof, &tampered)
+            .is_err());
+        let mut tampered = instances;
+        tampered[2] += Fp::from(1);
+        assert!(min_age_proof
+            .verify_proof_with_instances(&setup, &proof, &tampered)
+            .is_err());
+    }
+
+    #[test]
+    fn test_dynamic_range_proof() {
+        let setup = DynamicRangeProof::generate_setup().unwrap();
+        let dynamic_proof = DynamicRangeProof::new();
+
+        let proof = dynamic_proof
+            .generate_proof(&setup, 30, 18, 65, &ACCOUNT)
+            .unwrap();
+        assert!(dynamic_proof
+            .verify_proof(&setup, &proof, 18, 65, ACCOUNT)
+            .is_ok());
+        assert!(dynamic_proof
+            .verify_proof(&setup, &proof, 18, 65, INVALID_ACCOUNT)
+            .is_err());
+
+        // the same setup serves another range
+        let proof = dynamic_proof
+            .generate_proof(&setup, 30, 40, 65, &ACCOUNT)
+            .unwrap();
+        assert!(dynamic_proof
+            .verify_proof(&setup, &proof, 40, 65, ACCOUNT)
+            .is_err());
+        let proof = dynamic_proof
+            .generate_proof(&setup, 45, 40, 65, &ACCOUNT)
+            .unwrap();
+        assert!(dynamic_proof
+            .verify_proof(&setup, &proof, 40, 65, ACCOUNT)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_setup_generation_progress() {
+        let phases = std::cell::RefCell::new(vec![]);
+        let progress = |phase: &str| phases.borrow_mut().push(phase.to_string());
+        MinAgeProof::<REQUIRED_AGE_18>::generate_setup_with_progress(Some(&progress)).unwrap();
+        assert_eq!(
+            phases.into_inner(),
+            vec!["params", "vk keygen", "pk keygen"]
+        );
+    }
+
+    #[test]
+    fn test_circuit_stats() {
+        let stats = MinAgeProof::<REQUIRED_AGE_18>::circuit_stats();
+        assert!(stats.rows <= 1 << stats.recommended_k);
+        assert!(stats.recommended_k <= CIRCUIT_MAX_K);
+
+        // setup generated with the recommended k allows for proving and verification
+        let setup =
+            Setup::generate::<InRangeCircuit<Fp, REQUIRED_AGE_18, RANGE_TO>>(stats.recommended_k)
+                .unwrap();
+        let proof = MinAgeProof::<REQUIRED_AGE_18>::new()
+            .generate_proof(&setup, 23, &ACCOUNT)
+            .unwrap();
+        assert!(MinAgeProof::<REQUIRED_AGE_18>::new()
+            .verify_proof(&setup, &proof, ACCOUNT)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_verification_bundle() {
+        let setup = MinAgeProof::<REQUIRED_AGE_18>::generate_setup().unwrap();
+        let proof = MinAgeProof::<REQUIRED_AGE_18>::new();
+        let bundle = proof
+            .generate_verification_bundle(&setup, 23, &ACCOUNT)
+            .unwrap();
+        assert!(proof.verify_bundle(&bundle));
+
+        // bundle survives serialization to a single blob
+        let deserialized = VerificationBundle::from_bytes(&bundle.to_bytes()).unwrap();
+        assert_eq!(deserialized, bundle);
+        assert!(proof.verify_bundle(&deserialized));
+    }
+
+    #[test]
+    fn test_verification_bundle_with_replaced_proof() {
+        let setup = MinAgeProof::<REQUIRED_AGE_18>::generate_setup().unwrap();
+        let proof = MinAgeProof::<REQUIRED_AGE_18>::new();
+        let mut bundle = proof
+            .generate_verification_bundle(&setup, 23, &ACCOUNT)
+            .unwrap();
+        bundle.proof[0] ^= 0x01;
+        assert!(!proof.verify_bundle(&bundle));
+
+        // truncated blob can't be deserialized
+        let bs = bundle.to_bytes();
+        assert!(VerificationBundle::from_bytes(&bs[..bs.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_proof_artifact() {
+        let setup = MinAgeProof::<REQUIRED_AGE_18>::generate_setup().unwrap();
+        let proof = MinAgeProof::<REQUIRED_AGE_18>::new();
+        let artifact = proof.generate_artifact(&setup, 23, &ACCOUNT).unwrap();
+        assert_eq!(artifact.account, ACCOUNT);
+        assert_eq!(artifact.range_from, 18);
+        assert_eq!(artifact.range_to, RANGE_TO as u64);
+        assert!(artifact.verify(&setup).is_ok());
+
+        // artifact survives serialization to a single blob
+        let deserialized = MinAgeProofArtifact::from_bytes(&artifact.to_bytes()).unwrap();
+        assert_eq!(deserialized, artifact);
+        assert!(deserialized.verify(&setup).is_ok());
+
+        // age out of range is rejected before witnessing
+        assert!(proof.generate_artifact(&setup, 17, &ACCOUNT).is_err());
+        assert!(proof
+            .generate_artifact(&setup, RANGE_TO as u64, &ACCOUNT)
+            .is_err());
+    }
+
+    #[test]
+    fn test_tampered_proof_artifact() {
+        let setup = MinAgeProof::<REQUIRED_AGE_18>::generate_setup().unwrap();
+        let artifact = MinAgeProof::<REQUIRED_AGE_18>::new()
+            .generate_artifact(&setup, 23, &ACCOUNT)
+            .unwrap();
+
+        let mut tampered = artifact.clone();
+        tampered.bytes[0] ^= 0x01;
+        assert!(tampered.verify(&setup).is_err());
+        let tampered = MinAgeProofArtifact {
+            account: INVALID_ACCOUNT,
+            ..artifact.clone()
+        };
+        assert!(tampered.verify(&setup).is_err());
+
+        // raw proof or truncated blob can't be deserialized
+        assert!(MinAgeProofArtifact::from_bytes(&artifact.bytes).is_err());
+        let bs = artifact.to_bytes();
+        assert!(MinAgeProofArtifact::from_bytes(&bs[..bs.len() - 1]).is_err());
+    }
+
     #[test]
     fn test_serialization() {
         let setup = Setup::generate::<InRangeCircuit<Fp, 18, 120>>(CIRCUIT_MAX_K).unwrap();
@@ -258,15 +909,20 @@ mod tests {
         let setup_deserialized =
             Setup::from_bytes::<InRangeCircuit<Fp, 18, 120>>(&mut bs.as_slice()).unwrap();
 
-        assert_eq!(setup.k, setup_deserialized.k);
-        assert_eq!(setup.params.s_g2(), setup_deserialized.params.s_g2());
-        assert_eq!(
-            setup
-                .pk
-                .to_bytes(halo2_proofs::SerdeFormat::RawBytesUnchecked),
-            setup_deserialized
-                .pk
-                .to_bytes(halo2_proofs::SerdeFormat::RawBytesUnchecked)
-        );
+        assert!(setup.proof_compatible_with(&setup_deserialized));
+    }
+
+    #[test]
+    fn test_proof_compatible_with() {
+        let setup = Setup::generate::<InRangeCircuit<Fp, 18, 120>>(CIRCUIT_MAX_K).unwrap();
+        let bs = setup.to_bytes().unwrap();
+        let setup_deserialized =
+            Setup::from_bytes::<InRangeCircuit<Fp, 18, 120>>(&mut bs.as_slice()).unwrap();
+        let other_range_setup =
+            Setup::generate::<InRangeCircuit<Fp, 21, 120>>(CIRCUIT_MAX_K).unwrap();
+
+        assert!(setup.proof_compatible_with(&setup_deserialized));
+        assert!(setup_deserialized.proof_compatible_with(&setup));
+        assert!(!setup.proof_compatible_with(&other_range_setup));
     }
 }
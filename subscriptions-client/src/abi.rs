@@ -0,0 +1,107 @@
+use crate::subscription_contract_ops::json_string;
+
+/// Variants of the subscriptions smart contract's `PaymentInterval` enum, in the order of
+/// their SCALE indexes. Must mirror the smart contract's definition
+pub const PAYMENT_INTERVAL_VARIANTS: &[&str] = &["Week", "Month", "Custom"];
+
+/// Variants of the subscriptions smart contract's `Error` enum, in the order of their SCALE
+/// indexes. Must mirror the smart contract's definition
+pub const ERROR_VARIANTS: &[&str] = &[
+    "NotAuthorized",
+    "AlreadyRegisterred",
+    "InvalidIntervalsToPay",
+    "SubscriptionCostTooHigh",
+    "MissingChannelHandle",
+    "NotRegisterred",
+    "NewOwnerMustBeDifferent",
+    "InconsistentSubscriptionData",
+    "InkEnvFailure",
+    "ProofCallerAddressNotSerializable",
+    "InvalidProofForMinAgeRequired",
+    "InvalidVkHashLength",
+    "TokenTransferFailed",
+    "NativeTransferNotAccepted",
+    "BelowMinimumIntervals",
+    "AboveMaximumIntervals",
+    "AlreadyFrozen",
+    "NotFrozen",
+    "TooManyAccounts",
+    "CodeUpgradeFailed",
+    "ArithmeticOverflow",
+    "InvalidPaymentInterval",
+    "ProofAlreadyUsed",
+    "NotPendingOwner",
+    "NoVkForAge",
+    "ContractPaused",
+    "DurationTooLong",
+];
+
+/// Encodes SCALE indexes and names of the smart contract's enums as JSON, so integrators in
+/// other languages can build decoders
+/// returns:
+/// * JSON object mapping an enum name to the list of its variants' indexes and names
+pub fn enums_json() -> String {
+    let variants = |names: &[&str]| {
+        names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| format!(r#"{{"index":{index},"name":{}}}"#, json_string(name)))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    format!(
+        r#"{{"PaymentInterval":[{}],"Error":[{}]}}"#,
+        variants(PAYMENT_INTERVAL_VARIANTS),
+        variants(ERROR_VARIANTS)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Extracts variant names of an enum defined in the smart contract's source
+    fn contract_enum_variants(name: &str) -> Vec<String> {
+        let source = include_str!("../../contracts/subscriptions/lib.rs");
+        source
+            .lines()
+            .skip_while(|line| line.trim() != format!("pub enum {name} {{"))
+            .skip(1)
+            .take_while(|line| line.trim() != "}")
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .map(|line| {
+                line.split(|c: char| c == '(' || c == ',')
+                    .next()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_enums_json() {
+        let json: serde_json::Value = serde_json::from_str(&enums_json()).unwrap();
+        assert_eq!(
+            json["PaymentInterval"][0],
+            serde_json::json!({"index": 0, "name": "Week"})
+        );
+        assert_eq!(
+            json["PaymentInterval"][1],
+            serde_json::json!({"index": 1, "name": "Month"})
+        );
+        assert_eq!(
+            json["Error"][0],
+            serde_json::json!({"index": 0, "name": "NotAuthorized"})
+        );
+    }
+
+    #[test]
+    fn test_enums_mirror_contract() {
+        assert_eq!(
+            contract_enum_variants("PaymentInterval"),
+            PAYMENT_INTERVAL_VARIANTS
+        );
+        assert_eq!(contract_enum_variants("Error"), ERROR_VARIANTS);
+    }
+}
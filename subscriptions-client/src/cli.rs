@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 
 use aleph_client::AccountId;
-use clap::{Parser, Subcommand};
+use clap::{parser::ValueSource, ArgMatches, Parser, Subcommand};
+
+use crate::subscription_contract_ops::EventsFormat;
 
 /// Utilities to interact with Aleph Zero chain, especially:
 /// subscriptions smart contract
@@ -16,6 +18,11 @@ pub struct Cli {
     )]
     pub log_level: String,
 
+    /// SS58 network prefix used to display accounts, e.g. of a custom network so addresses
+    /// match its explorers. Accounts are accepted with any prefix
+    #[clap(long, global = true, env = "SS58_PREFIX", value_name = "u16")]
+    pub ss58_prefix: Option<u16>,
+
     #[clap(subcommand)]
     pub commands: Commands,
 }
@@ -27,6 +34,11 @@ pub enum Commands {
         /// Path to file where serialized trusted setup (ZKP requirement) is stored
         #[arg(short='p', long, default_value="setup.dat", value_parser = parsing::parse_path)]
         path: PathBuf,
+
+        /// Directory where trusted setup is stored under a name derived from the verification
+        /// key hash. Overrides `path`
+        #[arg(long, value_name = "Path", value_parser = parsing::parse_path)]
+        store_dir: Option<PathBuf>,
     },
 
     GenerateProof {
@@ -45,6 +57,75 @@ pub enum Commands {
         /// Age of a person associated with account for which ZKP proof is generated
         #[arg(long, value_name = "unsigned integer")]
         age: u64,
+
+        /// Directory where ZKP proof is stored under a name derived from the account. Overrides
+        /// `proof_path`
+        #[arg(long, value_name = "Path", value_parser = parsing::parse_path)]
+        store_dir: Option<PathBuf>,
+    },
+
+    /// Verify locally if a zero knowledge proof has been generated for an expected account
+    CheckProofAccount {
+        /// Path to file with serialized trusted setup
+        #[arg(short='s', long, default_value="setup.dat", value_parser = parsing::parse_path)]
+        setup_path: PathBuf,
+
+        /// Path to a file with binary proof
+        #[arg(short='p', long, default_value="proof.dat", value_parser = parsing::parse_path)]
+        proof_path: PathBuf,
+
+        /// SS58 address of an account for which the proof is expected to be generated
+        #[arg(short = 'a', long, value_name = "AccountId", value_parser = parsing::parse_account)]
+        account: AccountId,
+    },
+
+    /// Verify locally if a zero knowledge proof proves a minimum age for an account defined by
+    /// a given seed, before submitting it to the subscriptions smart contract
+    VerifyProof {
+        /// Path to file with serialized trusted setup
+        #[arg(short='s', long, default_value="setup.dat", value_parser = parsing::parse_path)]
+        setup_path: PathBuf,
+
+        /// Path to a file with binary proof
+        #[arg(short='p', long, default_value="proof.dat", value_parser = parsing::parse_path)]
+        proof_path: PathBuf,
+
+        /// Seed of an account for which the proof is expected to be generated
+        #[arg(long, value_name = "Seed of an account for which proof is generated")]
+        seed: String,
+
+        /// Minimum age the proof is expected to prove
+        #[arg(long, default_value = "18", value_name = "unsigned integer")]
+        age: u64,
+    },
+
+    /// Compare public inputs used to generate a proof with public inputs built by the
+    /// subscriptions smart contract
+    DiffPublicInputs {
+        /// Seed of an account for which proof is generated
+        #[arg(long, value_name = "Seed of an account for which proof is generated")]
+        seed: String,
+
+        /// Minimum required age configured in the subscriptions smart contract
+        #[arg(long, default_value = "18", value_name = "u128")]
+        min_age: u128,
+    },
+
+    /// Print SCALE indexes and names of the subscriptions smart contract's enums as JSON
+    Abi,
+
+    /// Print size of the minimum age circuit and the recommended maximum polynomial degree (k)
+    CircuitInfo,
+
+    /// Check if proofs generated with one trusted setup verify with another
+    DiffSetups {
+        /// Path to file with the first serialized trusted setup
+        #[arg(long, value_name = "Path", value_parser = parsing::parse_path)]
+        left: PathBuf,
+
+        /// Path to file with the second serialized trusted setup
+        #[arg(long, value_name = "Path", value_parser = parsing::parse_path)]
+        right: PathBuf,
     },
 
     RegisterVK {
@@ -53,7 +134,46 @@ pub enum Commands {
         setup_path: PathBuf,
 
         /// Webservice endpoint address of the Aleph Zero node
-        #[arg(short = 'n', long, default_value = "ws://localhost:9944")]
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// Seed of an account that submits and pays for verification key registration on aleph
+        /// chain
+        #[arg(long, value_name = "Seed of an account registering verification key")]
+        seed: String,
+    },
+
+    /// Verify locally if a trusted setup matches a known verification key hash, e.g. the hash
+    /// the smart contract has been deployed with
+    CheckSetup {
+        /// Path to file with serialized trusted setup
+        #[arg(short='s', long, default_value="setup.dat", value_parser = parsing::parse_path)]
+        setup_path: PathBuf,
+
+        /// Expected hash of the verification key
+        #[arg(long, value_name = "Hash")]
+        vk_hash: String,
+    },
+
+    /// Generate trusted setup and register its verification key on aleph chain in one step
+    SetupAndRegister {
+        /// Path to file where serialized trusted setup is stored. The setup is not stored when
+        /// not set
+        #[arg(short='p', long, value_name = "Path", value_parser = parsing::parse_path)]
+        path: Option<PathBuf>,
+
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
         node_address: String,
 
         /// Seed of an account that submits and pays for verification key registration on aleph
@@ -66,15 +186,228 @@ pub enum Commands {
     /// proof for minimum required age
     AddSubscription {
         /// Webservice endpoint process of the Aleph Zero node
-        #[arg(short = 'n', long, default_value = "ws://localhost:9944")]
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Path to a file with binary proof
+        #[arg(short='p', long, default_value="proof.dat", value_parser = parsing::parse_path)]
+        proof_path: PathBuf,
+
+        /// Directory where ZKP proof of the account defined by a given seed is resolved from.
+        /// Overrides `proof_path`
+        #[arg(long, value_name = "Path", value_parser = parsing::parse_path)]
+        store_dir: Option<PathBuf>,
+
+        /// Seed of an account requesting new subscription. The provided proof must be generated
+        /// for account defined by a given seed
+        #[arg(long, value_name = "Seed of an account requesting a new subscription")]
+        seed: String,
+
+        /// Subscription payment interval: Week|Month|Custom(<blocks>)
+        #[arg(
+            long,
+            default_value = "Week",
+            value_name = "Week|Month|Custom(<blocks>)"
+        )]
+        payment_interval: String,
+
+        /// Subscription number of intervals: must be > 0
+        #[arg(long, default_value = "1", value_name = "u32")]
+        intervals: u32,
+
+        /// Subscription for notifications requires external channel handle, e.g. Telegram channel
+        /// id
+        #[arg(long, default_value = "chat_id:123456", value_name = "String")]
+        external_channel_handle: String,
+
+        /// Age proven by the proof, selecting the smart contract's verification key, e.g. 21 for
+        /// a 21+ tier. Defaults to the smart contract's minimum required age
+        #[arg(long, value_name = "u128")]
+        required_age: Option<u128>,
+    },
+
+    /// Cancel subscription of an account defined by a given seed, refunding unpaid intervals
+    CancelSubscription {
+        /// Webservice endpoint process of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Seed of an account cancelling its subscription
+        #[arg(long, value_name = "Seed of an account cancelling its subscription")]
+        seed: String,
+    },
+
+    /// Settle payments of active subscriptions, all of them or a range of them. Only the owner
+    /// of the subscriptions smart contract is allowed to do it
+    PaymentSettlement {
+        /// Webservice endpoint process of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Seed of the owner of the subscriptions smart contract
+        #[arg(long, value_name = "Seed of the smart contract's owner")]
+        seed: String,
+
+        /// Index of the first settled active subscription. Settles all active subscriptions
+        /// when not set
+        #[arg(long, value_name = "u32", requires = "end")]
+        start: Option<u32>,
+
+        /// Index after the last settled active subscription
+        #[arg(long, value_name = "u32", requires = "start")]
+        end: Option<u32>,
+    },
+
+    /// Serve HTTP endpoint generating proofs for new subscribers and registering their
+    /// subscriptions
+    #[cfg(feature = "gateway")]
+    GatewayServe {
+        /// Path to file with serialized trusted setup, loaded once at startup
+        #[arg(short='s', long, default_value="setup.dat", value_parser = parsing::parse_path)]
+        setup_path: PathBuf,
+
+        /// Address the gateway listens on
+        #[arg(long, default_value = "127.0.0.1:8080", value_name = "SocketAddr")]
+        listen: std::net::SocketAddr,
+
+        /// Maximum number of generated proofs cached for retried requests, 0 disables caching
+        #[arg(long, default_value = "1024", value_name = "usize")]
+        proof_cache_size: usize,
+
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+    },
+
+    /// Quote the full cost of a new subscription, including the estimated transaction fee
+    QuoteSubscription {
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
         node_address: String,
 
         /// On chain account id of the subscription smart contract
-        #[arg(short = 'c', long, value_name = "AccountId")]
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
         contract_account: AccountId,
 
         /// Path to subscription smart contract metadata file
-        #[arg(short='m', long, value_name = "Path", value_parser=parsing::parse_path)]
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
         contract_metadata: PathBuf,
 
         /// Path to a file with binary proof
@@ -86,8 +419,12 @@ pub enum Commands {
         #[arg(long, value_name = "Seed of an account requesting a new subscription")]
         seed: String,
 
-        /// Subscription payment interval: Week|Month
-        #[arg(long, default_value = "Week", value_name = "Week|Month")]
+        /// Subscription payment interval: Week|Month|Custom(<blocks>)
+        #[arg(
+            long,
+            default_value = "Week",
+            value_name = "Week|Month|Custom(<blocks>)"
+        )]
         payment_interval: String,
 
         /// Subscription number of intervals: must be > 0
@@ -98,16 +435,435 @@ pub enum Commands {
         /// id
         #[arg(long, default_value = "chat_id:123456", value_name = "String")]
         external_channel_handle: String,
+
+        /// Age proven by the proof, selecting the smart contract's verification key. Defaults to
+        /// the smart contract's minimum required age
+        #[arg(long, value_name = "u128")]
+        required_age: Option<u128>,
+    },
+
+    /// Poll solvency ratio of the subscriptions smart contract and alert when it drops below
+    /// a threshold
+    MonitorSolvency {
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Solvency ratio in basis points below which an alert is raised, 10000 means the
+        /// contract can exactly honor all refunds
+        #[arg(long, default_value = "10000", value_name = "u32")]
+        threshold: u32,
+
+        /// Interval between polls in seconds
+        #[arg(long, default_value = "60", value_name = "u64")]
+        interval: u64,
+
+        /// Exit with a non-zero code on the first alert instead of logging it
+        #[arg(long)]
+        exit_on_alert: bool,
+    },
+
+    /// Export all active subscriptions of the subscriptions smart contract to a CSV file
+    ExportSubscriptions {
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Path to the CSV file where subscriptions are exported
+        #[arg(
+            short = 'o',
+            long,
+            default_value = "subscriptions.csv",
+            value_parser = parsing::parse_path
+        )]
+        out: PathBuf,
+    },
+
+    /// Reconstruct active subscriptions from the subscriptions smart contract's events
+    Reindex {
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Number of the first scanned block, e.g. the block the smart contract was instantiated
+        #[arg(long, default_value = "0", value_name = "BlockNumber")]
+        from_block: u32,
+    },
+
+    /// Watch events of the subscriptions smart contract emitted in finalized blocks
+    WatchEvents {
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Number of the first watched block, the next finalized block if not set
+        #[arg(long, value_name = "BlockNumber")]
+        from_block: Option<u32>,
+
+        /// Format of the printed events, one event per line
+        #[arg(long, value_enum, default_value = "human")]
+        format: EventsFormat,
+    },
+
+    /// Dry-run registration with a known-valid and a known-invalid proof to confirm the chain
+    /// extension verifying proofs returns the status codes the smart contract expects
+    CheckExtension {
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Path to file with serialized trusted setup the smart contract's verification key has
+        /// been generated from
+        #[arg(short='s', long, default_value="setup.dat", value_parser = parsing::parse_path)]
+        setup_path: PathBuf,
+
+        /// Seed of an account the proofs are generated for. The account must not be subscribed
+        #[arg(long, default_value = "//Alice", value_name = "Seed")]
+        seed: String,
+    },
+
+    /// Print a summary of every payment settlement run of the subscriptions smart contract
+    /// together with the running totals
+    TailSettlements {
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: AccountId,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: PathBuf,
+
+        /// Number of the first watched block, the next finalized block if not set
+        #[arg(long, value_name = "BlockNumber")]
+        from_block: Option<u32>,
     },
+
+    /// Print the effective configuration and the source of each value: flag, env or default
+    ShowConfig {
+        /// Webservice endpoint address of the Aleph Zero node
+        #[arg(
+            short = 'n',
+            long,
+            env = "ALEPH_NODE_ADDRESS",
+            default_value = "ws://localhost:9944"
+        )]
+        node_address: String,
+
+        /// On chain account id of the subscription smart contract
+        #[arg(
+            short = 'c',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_ACCOUNT",
+            value_name = "AccountId",
+            value_parser = parsing::parse_account
+        )]
+        contract_account: Option<AccountId>,
+
+        /// Path to subscription smart contract metadata file
+        #[arg(
+            short = 'm',
+            long,
+            env = "SUBSCRIPTIONS_CONTRACT_METADATA",
+            value_name = "Path",
+            value_parser = parsing::parse_path
+        )]
+        contract_metadata: Option<PathBuf>,
+    },
+}
+
+/// Source of a configuration value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Value passed as a command line flag
+    Flag,
+    /// Value read from an environment variable
+    Env,
+    /// Default value
+    Default,
+    /// Value not set
+    Unset,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self {
+            ConfigSource::Flag => "flag",
+            ConfigSource::Env => "env",
+            ConfigSource::Default => "default",
+            ConfigSource::Unset => "unset",
+        };
+        write!(f, "{source}")
+    }
+}
+
+/// Configuration value resolved from the command line, environment or defaults
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    /// Name of the configuration argument
+    pub name: &'static str,
+    /// Effective value, if set
+    pub value: Option<String>,
+    /// Where the effective value comes from
+    pub source: ConfigSource,
+}
+
+/// Resolves the effective configuration of the `show-config` command
+/// params:
+/// * matches - parsed command line arguments
+/// returns:
+/// * list of configuration values together with their sources
+pub fn resolve_config(matches: &ArgMatches) -> Vec<ConfigEntry> {
+    let mut entries = vec![config_entry(matches, "log_level")];
+    if let Some(matches) = matches.subcommand_matches("show-config") {
+        for name in ["node_address", "contract_account", "contract_metadata"] {
+            entries.push(config_entry(matches, name));
+        }
+    }
+    entries
+}
+
+fn config_entry(matches: &ArgMatches, name: &'static str) -> ConfigEntry {
+    let source = match matches.value_source(name) {
+        Some(ValueSource::CommandLine) => ConfigSource::Flag,
+        Some(ValueSource::EnvVariable) => ConfigSource::Env,
+        Some(ValueSource::DefaultValue) => ConfigSource::Default,
+        _ => ConfigSource::Unset,
+    };
+    let value = matches
+        .get_raw(name)
+        .and_then(|mut values| values.next())
+        .map(|value| value.to_string_lossy().to_string());
+    ConfigEntry {
+        name,
+        value,
+        source,
+    }
 }
 
 mod parsing {
     use std::{path::PathBuf, str::FromStr};
 
+    use aleph_client::AccountId;
     use anyhow::{Context, Result};
 
+    use crate::ss58;
+
     pub(super) fn parse_path(path: &str) -> Result<PathBuf> {
         let path = shellexpand::full(path).context("failed to exapand path")?;
         PathBuf::from_str(&path).context("failed to parse path ")
     }
+
+    /// Parses an account given either inline as SS58 string or as `@path` to a file containing
+    /// the SS58 string, e.g. written by a deployment script
+    pub(super) fn parse_account(account: &str) -> Result<AccountId> {
+        let account = match account.strip_prefix('@') {
+            Some(path) => std::fs::read_to_string(parse_path(path)?)
+                .with_context(|| format!("failed to read account from file: {path}"))?,
+            None => account.to_string(),
+        };
+        ss58::decode(account.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_show_config_flag_wins_over_env() {
+        std::env::set_var("ALEPH_NODE_ADDRESS", "ws://env:9944");
+        let matches = Cli::command()
+            .try_get_matches_from([
+                "subscriptions-client",
+                "show-config",
+                "-n",
+                "ws://flag:9944",
+            ])
+            .unwrap();
+        std::env::remove_var("ALEPH_NODE_ADDRESS");
+
+        let config = resolve_config(&matches);
+        assert_eq!(
+            config[0],
+            ConfigEntry {
+                name: "log_level",
+                value: Some("info".to_string()),
+                source: ConfigSource::Default,
+            }
+        );
+        assert_eq!(
+            config[1],
+            ConfigEntry {
+                name: "node_address",
+                value: Some("ws://flag:9944".to_string()),
+                source: ConfigSource::Flag,
+            }
+        );
+        assert_eq!(config[2].source, ConfigSource::Unset);
+    }
+
+    #[test]
+    fn test_parse_account_from_file() {
+        use std::io::Write;
+
+        use filepath::FilePath;
+
+        let alice = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let mut tmp_file = tempfile::tempfile().unwrap();
+        writeln!(tmp_file, "{alice}").unwrap();
+        let path = tmp_file.path().unwrap();
+
+        let inline = parsing::parse_account(alice).unwrap();
+        let from_file = parsing::parse_account(&format!("@{}", path.display())).unwrap();
+        assert_eq!(inline, from_file);
+        assert!(parsing::parse_account("@/nonexistent/address.txt").is_err());
+    }
 }
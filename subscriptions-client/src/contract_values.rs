@@ -0,0 +1,146 @@
+use aleph_client::{contract::ConvertibleValue, AccountId};
+use anyhow::{anyhow, bail, Context, Result};
+use contract_transcode::Value;
+
+/// Value returned by a smart contract read, decoded from the contract's metadata
+#[derive(Debug, Clone)]
+pub struct ContractValue(pub Value);
+
+impl TryFrom<ConvertibleValue> for ContractValue {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ConvertibleValue) -> Result<Self> {
+        Ok(Self(value.0))
+    }
+}
+
+impl ContractValue {
+    /// Unwraps `Ok` variants of the returned value, i.e. ink! language error and the smart
+    /// contract message result
+    /// returns:
+    /// * value wrapped by `Ok` variants
+    /// * error with the name of the smart contract error, e.g. `NotRegisterred`
+    pub fn into_ok(self) -> Result<Value> {
+        let mut value = self.0;
+        loop {
+            value = match value {
+                Value::Tuple(tuple) if tuple.ident().as_deref() == Some("Ok") => {
+                    tuple.values().next().cloned().unwrap_or(Value::Unit)
+                }
+                Value::Tuple(tuple) if tuple.ident().as_deref() == Some("Err") => {
+                    let err = tuple.values().next().cloned().unwrap_or(Value::Unit);
+                    bail!("smart contract call failed: {}", variant_name(&err)?)
+                }
+                value => return Ok(value),
+            }
+        }
+    }
+
+    /// Retrieves name of the smart contract error wrapped by the returned value, if any
+    /// returns:
+    /// * name of the smart contract error, e.g. `NotRegisterred`, `None` for `Ok` values
+    pub fn error_name(&self) -> Result<Option<String>> {
+        let mut value = &self.0;
+        loop {
+            value = match value {
+                Value::Tuple(tuple) if tuple.ident().as_deref() == Some("Ok") => {
+                    match tuple.values().next() {
+                        Some(value) => value,
+                        None => return Ok(None),
+                    }
+                }
+                Value::Tuple(tuple) if tuple.ident().as_deref() == Some("Err") => {
+                    return match tuple.values().next() {
+                        Some(err) => variant_name(err).map(Some),
+                        None => Ok(Some("Err".to_string())),
+                    };
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Retrieves a field of a decoded struct
+pub fn field<'a>(value: &'a Value, name: &str) -> Result<&'a Value> {
+    match value {
+        Value::Map(map) => map
+            .get_by_str(name)
+            .with_context(|| format!("missing field: {name}")),
+        _ => bail!("expected struct with field {name}, got: {value:?}"),
+    }
+}
+
+/// Retrieves elements of a decoded tuple
+pub fn tuple_elems(value: &Value) -> Result<Vec<&Value>> {
+    match value {
+        Value::Tuple(tuple) => Ok(tuple.values().collect()),
+        _ => bail!("expected tuple, got: {value:?}"),
+    }
+}
+
+/// Retrieves elements of a decoded sequence
+pub fn seq_elems(value: &Value) -> Result<&[Value]> {
+    match value {
+        Value::Seq(seq) => Ok(seq.elems()),
+        _ => bail!("expected sequence, got: {value:?}"),
+    }
+}
+
+/// Retrieves a name of the decoded enum variant, e.g. `Week`
+pub fn variant_name(value: &Value) -> Result<String> {
+    match value {
+        Value::Tuple(tuple) => tuple.ident(),
+        Value::Map(map) => map.ident(),
+        Value::Literal(name) => Some(name.clone()),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("expected enum variant, got: {value:?}"))
+}
+
+/// Converts decoded value to an account
+pub fn as_account(value: &Value) -> Result<AccountId> {
+    match value {
+        Value::Literal(account) | Value::String(account) => crate::ss58::decode(account),
+        _ => {
+            let bytes: [u8; 32] = as_bytes(value)?
+                .try_into()
+                .map_err(|_| anyhow!("invalid account length"))?;
+            Ok(AccountId::from(bytes))
+        }
+    }
+}
+
+/// Converts decoded value to an array of bytes
+pub fn as_bytes(value: &Value) -> Result<Vec<u8>> {
+    match value {
+        Value::Hex(hex) => Ok(hex.bytes().to_vec()),
+        Value::Seq(seq) => seq
+            .elems()
+            .iter()
+            .map(|elem| u8::try_from(as_u128(elem)?).context("invalid byte"))
+            .collect(),
+        _ => bail!("expected bytes, got: {value:?}"),
+    }
+}
+
+/// Converts decoded value to an unsigned integer
+pub fn as_u128(value: &Value) -> Result<u128> {
+    match value {
+        Value::UInt(v) => Ok(*v),
+        _ => bail!("expected unsigned integer, got: {value:?}"),
+    }
+}
+
+/// Converts decoded value to a 32-bit unsigned integer
+pub fn as_u32(value: &Value) -> Result<u32> {
+    u32::try_from(as_u128(value)?).context("integer out of u32 range")
+}
+
+/// Converts decoded value to a boolean
+pub fn as_bool(value: &Value) -> Result<bool> {
+    match value {
+        Value::Bool(v) => Ok(*v),
+        _ => bail!("expected boolean, got: {value:?}"),
+    }
+}
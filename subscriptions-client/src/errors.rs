@@ -0,0 +1,24 @@
+use std::fmt;
+
+/// Failure classes of the client, each reported with a distinct process exit code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// Zero knowledge proof is invalid, e.g. generated for a different account
+    InvalidProof(String),
+    /// Aleph zero node can't be reached
+    Connection(String),
+    /// Aleph zero transaction or smart contract call failed
+    Chain(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::InvalidProof(msg) => write!(f, "invalid proof: {msg}"),
+            ClientError::Connection(msg) => write!(f, "connection failure: {msg}"),
+            ClientError::Chain(msg) => write!(f, "chain error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
@@ -0,0 +1,241 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    min_age_proof_ops::MinAgeProofOps, subscription_contract_ops::SubscriptionContractOps,
+};
+
+/// Submits new subscriptions on behalf of onboarded users
+#[async_trait]
+pub trait SubscriptionSubmitter: Send + Sync {
+    /// Registers new subscription with a given zero knowledge proof
+    /// params:
+    /// * seed - a seed of the subscriber
+    /// * payment_interval - one of WEEK|MONTH
+    /// * intervals - number of payment intervals
+    /// * external_channel_handle - for example Telegram channel handle
+    /// * required_age - age proven by the proof
+    /// * proof - minimum age zero knowledge proof of the subscriber
+    /// returns:
+    /// * hash of the submitted transaction
+    async fn submit_subscription(
+        &self,
+        seed: &str,
+        payment_interval: &str,
+        intervals: u32,
+        external_channel_handle: &str,
+        required_age: u128,
+        proof: Vec<u8>,
+    ) -> Result<String>;
+}
+
+#[async_trait]
+impl SubscriptionSubmitter for SubscriptionContractOps {
+    async fn submit_subscription(
+        &self,
+        seed: &str,
+        payment_interval: &str,
+        intervals: u32,
+        external_channel_handle: &str,
+        required_age: u128,
+        proof: Vec<u8>,
+    ) -> Result<String> {
+        let tx_info = self
+            .add_subscription(
+                seed,
+                payment_interval,
+                intervals,
+                external_channel_handle,
+                required_age,
+                proof,
+                None,
+            )
+            .await?;
+        Ok(format!("{:?}", tx_info.tx_hash))
+    }
+}
+
+/// Request to onboard a new subscriber
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct OnboardRequest {
+    /// Seed of the subscriber, used to generate the proof and sign the registration
+    pub seed: String,
+    /// Age of the subscriber
+    pub age: u64,
+    /// One of WEEK|MONTH
+    pub payment_interval: String,
+    /// Number of payment intervals
+    pub intervals: u32,
+    /// For example Telegram channel handle
+    pub external_channel_handle: String,
+    /// Identifies the request, so a retried request reuses the cached proof
+    #[serde(default)]
+    pub nonce: u64,
+}
+
+/// Response to the onboarding request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OnboardResponse {
+    /// Hash of the subscription registration transaction
+    pub tx_hash: String,
+}
+
+/// Shared state of the gateway's handlers
+struct Gateway<S> {
+    /// Proof operations with the trusted setup loaded once at startup
+    proof_ops: Arc<MinAgeProofOps<18>>,
+    /// Submitter of new subscriptions
+    submitter: Arc<S>,
+}
+
+impl<S> Clone for Gateway<S> {
+    fn clone(&self) -> Self {
+        Self {
+            proof_ops: self.proof_ops.clone(),
+            submitter: self.submitter.clone(),
+        }
+    }
+}
+
+/// Creates HTTP routes of the gateway
+/// params:
+/// * proof_ops - proof operations with loaded trusted setup
+/// * submitter - submitter of new subscriptions
+pub fn router<S: SubscriptionSubmitter + 'static>(
+    proof_ops: MinAgeProofOps<18>,
+    submitter: Arc<S>,
+) -> Router {
+    Router::new()
+        .route("/onboard", post(onboard::<S>))
+        .with_state(Gateway {
+            proof_ops: Arc::new(proof_ops),
+            submitter,
+        })
+}
+
+/// Serves the gateway until the process is stopped
+/// params:
+/// * listen - address the gateway listens on
+/// * proof_ops - proof operations with loaded trusted setup
+/// * submitter - submitter of new subscriptions
+pub async fn serve<S: SubscriptionSubmitter + 'static>(
+    listen: SocketAddr,
+    proof_ops: MinAgeProofOps<18>,
+    submitter: S,
+) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("failed to listen on {listen}"))?;
+    log::info!("Gateway listening on {}", listen);
+    axum::serve(listener, router(proof_ops, Arc::new(submitter)))
+        .await
+        .context("gateway failed")
+}
+
+/// Generates a proof for the subscriber and submits the subscription
+async fn onboard<S: SubscriptionSubmitter + 'static>(
+    State(gateway): State<Gateway<S>>,
+    Json(request): Json<OnboardRequest>,
+) -> Result<Json<OnboardResponse>, (StatusCode, String)> {
+    // proof generation is CPU bound, so it must not block the async runtime
+    let proof_ops = gateway.proof_ops.clone();
+    let (seed, age, nonce) = (request.seed.clone(), request.age, request.nonce);
+    let proof = tokio::task::spawn_blocking(move || proof_ops.prove_with_nonce(&seed, age, nonce))
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, format!("{err:#}")))?;
+
+    let tx_hash = gateway
+        .submitter
+        .submit_subscription(
+            &request.seed,
+            &request.payment_interval,
+            request.intervals,
+            &request.external_channel_handle,
+            gateway.proof_ops.required_age(),
+            proof,
+        )
+        .await
+        .map_err(|err| (StatusCode::BAD_GATEWAY, format!("{err:#}")))?;
+    log::info!("Subscription submitted in transaction: {}", tx_hash);
+
+    Ok(Json(OnboardResponse { tx_hash }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Records submitted subscriptions instead of calling the smart contract
+    #[derive(Default)]
+    struct RecordingSubmitter {
+        submitted: Mutex<Vec<(String, Vec<u8>)>>,
+    }
+
+    #[async_trait]
+    impl SubscriptionSubmitter for RecordingSubmitter {
+        async fn submit_subscription(
+            &self,
+            seed: &str,
+            _payment_interval: &str,
+            _intervals: u32,
+            _external_channel_handle: &str,
+            _required_age: u128,
+            proof: Vec<u8>,
+        ) -> Result<String> {
+            self.submitted
+                .lock()
+                .unwrap()
+                .push((seed.to_string(), proof));
+            Ok("0x01".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_onboard() {
+        let tmp_file_setup = tempfile::tempfile().unwrap();
+        let path_setup = filepath::FilePath::path(&tmp_file_setup).unwrap();
+        let mut proof_ops = MinAgeProofOps::<18>::new();
+        proof_ops.generate_setup(&path_setup).await.unwrap();
+
+        let submitter = Arc::new(RecordingSubmitter::default());
+        let response = router(proof_ops.clone(), submitter.clone())
+            .oneshot(
+                Request::post("/onboard")
+                    .header("content-type", "application/json")
+                    .body(Body::from(concat!(
+                        r#"{"seed":"//Alice","age":23,"payment_interval":"Week","#,
+                        r#""intervals":2,"external_channel_handle":"1111"}"#
+                    )))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"tx_hash":"0x01"}"#);
+
+        let submitted = submitter.submitted.lock().unwrap();
+        assert_eq!(submitted.len(), 1);
+        assert_eq!(submitted[0].0, "//Alice");
+        let alice = aleph_client::keypair_from_string("//Alice")
+            .account_id()
+            .clone();
+        assert!(proof_ops
+            .check_proof_account(&submitted[0].1, &alice)
+            .await
+            .unwrap());
+    }
+}
@@ -1,26 +1,75 @@
-use aleph_client::Connection;
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{bail, Result};
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
 use cli::{Cli, Commands};
 use env_logger::Env;
+use errors::ClientError;
 use min_age_proof_ops::MinAgeProofOps;
 use subscription_contract_ops::SubscriptionContractOps;
 
+mod abi;
 mod cli;
+mod contract_values;
+mod errors;
+#[cfg(feature = "gateway")]
+mod gateway;
 mod min_age_proof_ops;
+mod ss58;
 mod subscription_contract_ops;
 
+/// Exit codes of the client, so scripts can distinguish failure classes
+/// Generic failure
+const EXIT_FAILURE: i32 = 1;
+/// Invalid command line arguments, as reported by clap
+const EXIT_INVALID_ARGS: i32 = 2;
+/// Invalid zero knowledge proof
+const EXIT_INVALID_PROOF: i32 = 3;
+/// Aleph zero node can't be reached
+const EXIT_CONNECTION: i32 = 4;
+/// Aleph zero transaction or smart contract call failed
+const EXIT_CHAIN: i32 = 5;
+
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+async fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
     env_logger::Builder::from_env(Env::default().default_filter_or(&cli.log_level)).init();
+    if let Some(prefix) = cli.ss58_prefix {
+        ss58::set_prefix(prefix);
+    }
 
     log::info!("{:?}", cli);
 
+    if let Err(err) = run(cli, &matches).await {
+        eprintln!("Error: {err:?}");
+        std::process::exit(exit_code(&err));
+    }
+}
+
+/// Maps an error to the client's exit code
+fn exit_code(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<ClientError>() {
+        Some(ClientError::InvalidProof(_)) => EXIT_INVALID_PROOF,
+        Some(ClientError::Connection(_)) => EXIT_CONNECTION,
+        Some(ClientError::Chain(_)) => EXIT_CHAIN,
+        None if err.is::<clap::Error>() => EXIT_INVALID_ARGS,
+        None => EXIT_FAILURE,
+    }
+}
+
+async fn run(cli: Cli, matches: &ArgMatches) -> Result<()> {
     match cli.commands {
-        Commands::GenerateSetup { path } => {
+        Commands::GenerateSetup { path, store_dir } => {
             let mut proof_ops = MinAgeProofOps::<18>::new();
-            proof_ops.generate_setup(&path).await?;
+            let path = match store_dir {
+                Some(store_dir) => proof_ops.store_setup(&store_dir).await?,
+                None => {
+                    let progress = |phase: &str| log::info!("Generating trusted setup: {phase}");
+                    proof_ops
+                        .generate_setup_with_progress(&path, Some(&progress))
+                        .await?;
+                    path
+                }
+            };
             log::info!("Trusted setup stored to file: {:?}", path);
         }
         Commands::GenerateProof {
@@ -28,12 +77,75 @@ async fn main() -> Result<()> {
             proof_path,
             seed,
             age,
+            store_dir,
         } => {
             let mut proof_ops = MinAgeProofOps::<18>::new();
             proof_ops.load_setup(&setup_path).await?;
-            proof_ops.generate_proof(&proof_path, &seed, age).await?;
+            let proof_path = match store_dir {
+                Some(store_dir) => proof_ops.store_proof(&store_dir, &seed, age).await?,
+                None => {
+                    proof_ops.generate_proof(&proof_path, &seed, age).await?;
+                    proof_path
+                }
+            };
             log::info!("ZKP stored to file: {:?}", proof_path);
         }
+        Commands::CheckProofAccount {
+            setup_path,
+            proof_path,
+            account,
+        } => {
+            let mut proof_ops = MinAgeProofOps::<18>::new();
+            proof_ops.load_setup(&setup_path).await?;
+            let proof = proof_ops.load_proof(&proof_path).await?;
+            if !proof_ops.check_proof_account(&proof, &account).await? {
+                bail!(ClientError::InvalidProof(format!(
+                    "ZKP proof has not been generated for account: {}",
+                    account
+                )));
+            }
+            log::info!("ZKP proof has been generated for account: {}", account);
+        }
+        Commands::VerifyProof {
+            setup_path,
+            proof_path,
+            seed,
+            age,
+        } => {
+            let mut proof_ops = MinAgeProofOps::<18>::new();
+            proof_ops.load_setup(&setup_path).await?;
+            let proof = proof_ops.load_proof(&proof_path).await?;
+            proof_ops.verify_proof(&proof, &seed, age)?;
+            println!("ZKP proof is valid: proves age {age} for the given seed");
+        }
+        Commands::DiffPublicInputs { seed, min_age } => {
+            let diff = MinAgeProofOps::<18>::diff_public_inputs(&seed, min_age);
+            println!("{}", diff);
+            if !diff.consistent() {
+                bail!(ClientError::InvalidProof(
+                    "Public inputs of the proof and the smart contract differ".to_string()
+                ));
+            }
+        }
+        Commands::Abi => {
+            println!("{}", abi::enums_json());
+        }
+        Commands::CircuitInfo => {
+            let stats = MinAgeProofOps::<18>::circuit_stats();
+            println!("rows: {}", stats.rows);
+            println!("max degree: {}", stats.max_degree);
+            println!("recommended k: {}", stats.recommended_k);
+        }
+        Commands::DiffSetups { left, right } => {
+            let mut left_ops = MinAgeProofOps::<18>::new();
+            left_ops.load_setup(&left).await?;
+            let mut right_ops = MinAgeProofOps::<18>::new();
+            right_ops.load_setup(&right).await?;
+            if !left_ops.setup_compatible_with(&right_ops)? {
+                bail!("Trusted setups are not compatible");
+            }
+            println!("Trusted setups are compatible");
+        }
         Commands::RegisterVK {
             setup_path,
             node_address,
@@ -41,27 +153,74 @@ async fn main() -> Result<()> {
         } => {
             let mut proof_ops = MinAgeProofOps::<18>::new();
             proof_ops.load_setup(&setup_path).await?;
-            let aleph_conn = Connection::new(&node_address).await;
+            let aleph_conn = subscription_contract_ops::connect(&node_address).await?;
             let vk_hash = proof_ops.register_vk(aleph_conn, &seed).await?;
             log::info!(
                 "Verification key registered on aleph chain with hash: {}",
                 vk_hash
             );
         }
+        Commands::CheckSetup {
+            setup_path,
+            vk_hash,
+        } => {
+            let mut proof_ops = MinAgeProofOps::<18>::new();
+            if !proof_ops
+                .verify_setup_matches_hash(&setup_path, &vk_hash)
+                .await?
+            {
+                bail!(
+                    "Trusted setup doesn't match verification key hash {}, its hash is {}",
+                    vk_hash,
+                    proof_ops.vk_hash()?
+                );
+            }
+            println!("Trusted setup matches verification key hash {}", vk_hash);
+        }
+        Commands::SetupAndRegister {
+            path,
+            node_address,
+            seed,
+        } => {
+            let mut proof_ops = MinAgeProofOps::<18>::new();
+            proof_ops.prepare_setup(path.as_deref()).await?;
+            let aleph_conn = subscription_contract_ops::connect(&node_address).await?;
+            let vk_hash = proof_ops.register_vk(aleph_conn, &seed).await?;
+            println!("vk hash: {}", vk_hash);
+            if let Some(path) = path {
+                println!("setup path: {}", path.display());
+            }
+        }
         Commands::AddSubscription {
             node_address,
             contract_account,
             contract_metadata,
             proof_path,
+            store_dir,
             seed,
             payment_interval,
             intervals,
             external_channel_handle,
+            required_age,
         } => {
             let proof_ops = MinAgeProofOps::<18>::new();
+            let proof_path = match store_dir {
+                Some(store_dir) => {
+                    let account_id = aleph_client::keypair_from_string(&seed)
+                        .account_id()
+                        .clone();
+                    MinAgeProofOps::<18>::resolve_proof(&store_dir, &account_id)?
+                }
+                None => proof_path,
+            };
             let proof = proof_ops.load_proof(&proof_path).await?;
+            let proof_min_age = MinAgeProofOps::<18>::proof_min_age(&proof_path)?;
             let contract_ops =
                 SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            let required_age = match required_age {
+                Some(required_age) => required_age,
+                None => contract_ops.age_policy().await?.0,
+            };
             log::info!("Calling subscription smart contract");
             contract_ops
                 .add_subscription(
@@ -69,11 +228,257 @@ async fn main() -> Result<()> {
                     &payment_interval,
                     intervals,
                     &external_channel_handle,
+                    required_age,
                     proof,
+                    proof_min_age,
                 )
                 .await?;
         }
+        Commands::CancelSubscription {
+            node_address,
+            contract_account,
+            contract_metadata,
+            seed,
+        } => {
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            log::info!("Calling subscription smart contract");
+            contract_ops.cancel_subscription(&seed).await?;
+        }
+        Commands::PaymentSettlement {
+            node_address,
+            contract_account,
+            contract_metadata,
+            seed,
+            start,
+            end,
+        } => {
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            log::info!("Calling subscription smart contract");
+            match start.zip(end) {
+                Some((start, end)) => {
+                    contract_ops
+                        .payment_settlement_range(&seed, start, end)
+                        .await?
+                }
+                None => contract_ops.payment_settlement(&seed).await?,
+            };
+        }
+        #[cfg(feature = "gateway")]
+        Commands::GatewayServe {
+            setup_path,
+            listen,
+            proof_cache_size,
+            node_address,
+            contract_account,
+            contract_metadata,
+        } => {
+            let mut proof_ops = MinAgeProofOps::<18>::new().with_proof_cache(proof_cache_size);
+            proof_ops.load_setup(&setup_path).await?;
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            gateway::serve(listen, proof_ops, contract_ops).await?;
+        }
+        Commands::QuoteSubscription {
+            node_address,
+            contract_account,
+            contract_metadata,
+            proof_path,
+            seed,
+            payment_interval,
+            intervals,
+            external_channel_handle,
+            required_age,
+        } => {
+            let proof = MinAgeProofOps::<18>::new().load_proof(&proof_path).await?;
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            let prices = contract_ops.prices().await?;
+            let required_age = match required_age {
+                Some(required_age) => required_age,
+                None => contract_ops.age_policy().await?.0,
+            };
+            let quote = subscription_contract_ops::quote_subscription(
+                &contract_ops,
+                prices,
+                &seed,
+                &payment_interval,
+                intervals,
+                &external_channel_handle,
+                required_age,
+                &proof,
+            )
+            .await?;
+            println!("{quote}");
+        }
+        Commands::MonitorSolvency {
+            node_address,
+            contract_account,
+            contract_metadata,
+            threshold,
+            interval,
+            exit_on_alert,
+        } => {
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            subscription_contract_ops::monitor_solvency(
+                &contract_ops,
+                threshold,
+                std::time::Duration::from_secs(interval),
+                exit_on_alert,
+                None,
+            )
+            .await?;
+        }
+        Commands::ExportSubscriptions {
+            node_address,
+            contract_account,
+            contract_metadata,
+            out,
+        } => {
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            let exported = contract_ops.export_subscriptions(&out).await?;
+            log::info!("{} subscriptions exported to file: {:?}", exported, out);
+        }
+        Commands::Reindex {
+            node_address,
+            contract_account,
+            contract_metadata,
+            from_block,
+        } => {
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            for sub in contract_ops.reindex(from_block).await? {
+                println!("{} {}", sub.account, sub.external_channel_handle);
+            }
+        }
+        Commands::WatchEvents {
+            node_address,
+            contract_account,
+            contract_metadata,
+            from_block,
+            format,
+        } => {
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            contract_ops
+                .watch_events(from_block, format, std::io::stdout())
+                .await?;
+        }
+        Commands::CheckExtension {
+            node_address,
+            contract_account,
+            contract_metadata,
+            setup_path,
+            seed,
+        } => {
+            let mut proof_ops = MinAgeProofOps::<18>::new();
+            proof_ops.load_setup(&setup_path).await?;
+            let proof = proof_ops.prove(&seed, 18)?;
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            let check = subscription_contract_ops::check_extension(
+                &contract_ops,
+                &seed,
+                proof_ops.required_age(),
+                &proof,
+            )
+            .await?;
+            println!("{check}");
+            if !check.consistent() {
+                bail!(ClientError::Chain(
+                    "chain extension doesn't return the status codes the smart contract expects"
+                        .to_string()
+                ));
+            }
+        }
+        Commands::TailSettlements {
+            node_address,
+            contract_account,
+            contract_metadata,
+            from_block,
+        } => {
+            let contract_ops =
+                SubscriptionContractOps::new(contract_account, &node_address, &contract_metadata)?;
+            contract_ops
+                .tail_settlements(from_block, std::io::stdout())
+                .await?;
+        }
+        Commands::ShowConfig { .. } => {
+            for entry in cli::resolve_config(matches) {
+                println!(
+                    "{}: {} ({})",
+                    entry.name,
+                    entry.value.unwrap_or_default(),
+                    entry.source
+                );
+            }
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use filepath::FilePath;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_invalid_proof_exit_code() {
+        let tmp_file_setup = tempfile::tempfile().unwrap();
+        let path_setup = tmp_file_setup.path().unwrap();
+        let tmp_file_proof = tempfile::tempfile().unwrap();
+        let path_proof = tmp_file_proof.path().unwrap();
+
+        MinAgeProofOps::<18>::new()
+            .generate_setup(&path_setup)
+            .await
+            .unwrap();
+        std::fs::write(&path_proof, [0u8; 60]).unwrap();
+
+        let alice = aleph_client::keypair_from_string("//Alice")
+            .account_id()
+            .to_string();
+        let matches = Cli::command()
+            .try_get_matches_from([
+                "subscriptions-client",
+                "check-proof-account",
+                "-s",
+                path_setup.to_str().unwrap(),
+                "-p",
+                path_proof.to_str().unwrap(),
+                "-a",
+                &alice,
+            ])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+
+        let err = run(cli, &matches).await.unwrap_err();
+        assert_eq!(exit_code(&err), EXIT_INVALID_PROOF);
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let err = Cli::command()
+            .try_get_matches_from(["subscriptions-client", "unknown-command"])
+            .unwrap_err();
+        assert_eq!(exit_code(&err.into()), EXIT_INVALID_ARGS);
+        assert_eq!(
+            exit_code(&ClientError::Connection("ws://127.0.0.1:9944".to_string()).into()),
+            EXIT_CONNECTION
+        );
+        assert_eq!(
+            exit_code(
+                &anyhow::anyhow!("transaction reverted").context(ClientError::Chain(
+                    "add_subscription call failed".to_string()
+                ))
+            ),
+            EXIT_CHAIN
+        );
+        assert_eq!(exit_code(&anyhow::anyhow!("other")), EXIT_FAILURE);
+    }
+}
@@ -1,11 +1,78 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use aleph_client::{
-    pallets::vk_storage::VkStorageUserApi, sp_core::Hasher, BlakeTwo256, Connection,
+    pallets::vk_storage::VkStorageUserApi, sp_core::Hasher, AccountId, BlakeTwo256, Connection,
     SignedConnection,
 };
 use anyhow::{bail, Context, Ok, Result};
-use subscription_proofs::proofs::{MinAgeProof, Setup};
+use subscription_proofs::{
+    cache::{ProofCache, ProofKey},
+    circuits::CircuitStats,
+    proofs::{
+        instances_to_bytes, min_age_public_input_bytes, Account, MinAgeProof, Setup, RANGE_TO,
+    },
+};
+
+use crate::errors::ClientError;
+
+/// Size of a serialized public input (finite field element)
+const PUBLIC_INPUT_SIZE: usize = 32;
+
+/// Key of the minimum age recorded in the proof sidecar file
+const SIDECAR_MIN_AGE_KEY: &str = "min_age";
+
+/// Public inputs of the min age zero knowledge proof encoded by the proof generator and by the
+/// subscriptions smart contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicInputsDiff {
+    /// Public inputs used to generate the proof
+    pub proof_inputs: Vec<u8>,
+    /// Public inputs passed by the smart contract to the proof verification
+    pub contract_inputs: Vec<u8>,
+}
+
+impl PublicInputsDiff {
+    /// Checks if both encodings of public inputs are the same
+    pub fn consistent(&self) -> bool {
+        self.proof_inputs == self.contract_inputs
+    }
+}
+
+impl std::fmt::Display for PublicInputsDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let to_hex = |bs: &[u8]| bs.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        let proof_inputs = self.proof_inputs.chunks(PUBLIC_INPUT_SIZE);
+        let contract_inputs = self.contract_inputs.chunks(PUBLIC_INPUT_SIZE);
+        let inputs = proof_inputs.len().max(contract_inputs.len());
+        for (i, (proof, contract)) in proof_inputs
+            .map(Some)
+            .chain(std::iter::repeat(None))
+            .zip(contract_inputs.map(Some).chain(std::iter::repeat(None)))
+            .take(inputs)
+            .enumerate()
+        {
+            writeln!(
+                f,
+                "input {i}: proof {} | contract {} {}",
+                to_hex(proof.unwrap_or_default()),
+                to_hex(contract.unwrap_or_default()),
+                if proof == contract { "ok" } else { "MISMATCH" }
+            )?;
+        }
+        write!(
+            f,
+            "public inputs {}",
+            if self.consistent() {
+                "consistent"
+            } else {
+                "inconsistent"
+            }
+        )
+    }
+}
 
 /// Provides commands to generate trusted setup and min age zero knowledge proof
 /// params:
@@ -14,13 +81,32 @@ use subscription_proofs::proofs::{MinAgeProof, Setup};
 pub struct MinAgeProofOps<const REQUIRED_AGE: usize> {
     /// Trusted setup
     setup: Option<Setup>,
+    /// Cache of generated proofs, shared by clones
+    proof_cache: Option<Arc<ProofCache>>,
 }
 
 impl<const REQUIRED_AGE: usize> MinAgeProofOps<REQUIRED_AGE> {
     /// Creates an instance of minimum age zero knowledge proof operations
     pub fn new() -> Self {
         assert!(REQUIRED_AGE > 0);
-        Self { setup: None }
+        Self {
+            setup: None,
+            proof_cache: None,
+        }
+    }
+
+    /// Minimum age proven by the generated proofs, as required by the smart contract
+    pub fn required_age(&self) -> u128 {
+        REQUIRED_AGE as u128
+    }
+
+    /// Caches generated proofs, so re-proving the same account, age and nonce returns the
+    /// cached proof instead of proving again, e.g. when a gateway's client retries a request
+    /// params:
+    /// * capacity - maximum number of cached proofs, the least recently used ones are evicted
+    pub fn with_proof_cache(mut self, capacity: usize) -> Self {
+        self.proof_cache = Some(Arc::new(ProofCache::new(capacity)));
+        self
     }
 
     /// Generates trusted setup with max circuit polynomial degree (k) and stores its serialized
@@ -28,21 +114,72 @@ impl<const REQUIRED_AGE: usize> MinAgeProofOps<REQUIRED_AGE> {
     /// params:
     /// * path - file path of where serialized binary setup is stored
     pub async fn generate_setup(&mut self, path: &Path) -> Result<()> {
-        let setup = MinAgeProof::<REQUIRED_AGE>::generate_setup()?;
+        self.generate_setup_with_progress(path, None).await
+    }
+
+    /// Generates trusted setup as `generate_setup` does, reporting the generation phases, i.e.
+    /// `params`, `vk keygen` and `pk keygen`
+    /// params:
+    /// * path - file path of where serialized binary setup is stored
+    /// * progress - optional callback invoked with a name of each generation phase
+    pub async fn generate_setup_with_progress(
+        &mut self,
+        path: &Path,
+        progress: Option<&dyn Fn(&str)>,
+    ) -> Result<()> {
+        let setup = MinAgeProof::<REQUIRED_AGE>::generate_setup_with_progress(progress)?;
         let bs = setup.to_bytes()?;
         self.setup = Some(setup);
         std::fs::write(path, bs).context("failed to write ZKP setup to file")
     }
 
-    /// Loads trusted setup stored under a given path.
+    /// Generates trusted setup, ready to register its verification key without reading it from
+    /// a file again
+    /// params:
+    /// * path - optional file path of where serialized binary setup is stored, the setup is kept
+    /// in memory only when not set
+    /// returns:
+    /// * hash of the verification key serialized to string
+    pub async fn prepare_setup(&mut self, path: Option<&Path>) -> Result<String> {
+        match path {
+            Some(path) => self.generate_setup(path).await?,
+            None => self.setup = Some(MinAgeProof::<REQUIRED_AGE>::generate_setup()?),
+        }
+        self.vk_hash()
+    }
+
+    /// Loads trusted setup stored under a given path. With the `mmap` feature the setup is
+    /// loaded from a memory-mapped file.
     /// params:
     /// * path - path where trusted setup has been serialized
     /// returns:
     /// * Deserialized trusted setup
     pub async fn load_setup(&mut self, path: &Path) -> Result<()> {
+        #[cfg(feature = "mmap")]
+        {
+            self.load_setup_mmap(path).await
+        }
+        #[cfg(not(feature = "mmap"))]
+        {
+            self.setup = None;
+            let bs = std::fs::read(path).context("failed to read ZKP setup from file")?;
+            self.setup = Some(MinAgeProof::<REQUIRED_AGE>::load_setup(bs)?);
+            Ok(())
+        }
+    }
+
+    /// Loads trusted setup from a memory-mapped file, deserializing directly from the mapped
+    /// memory instead of reading the whole file first. Speeds up restarts of long-running
+    /// provers
+    /// params:
+    /// * path - path where trusted setup has been serialized
+    #[cfg(feature = "mmap")]
+    async fn load_setup_mmap(&mut self, path: &Path) -> Result<()> {
         self.setup = None;
-        let bs = std::fs::read(path).context("failed to read ZKP setup from file")?;
-        self.setup = Some(MinAgeProof::<REQUIRED_AGE>::load_setup(bs)?);
+        let file = std::fs::File::open(path).context("failed to open ZKP setup file")?;
+        // Safety: setup file must not be modified while it's being deserialized
+        let mmap = unsafe { memmap2::Mmap::map(&file) }.context("failed to map ZKP setup file")?;
+        self.setup = Some(MinAgeProof::<REQUIRED_AGE>::load_setup_from_slice(&mmap)?);
         Ok(())
     }
 
@@ -52,20 +189,88 @@ impl<const REQUIRED_AGE: usize> MinAgeProofOps<REQUIRED_AGE> {
     /// * seed - seed of account for which proof is generated
     /// * age - age of an owner of the account for which proof is generated
     pub async fn generate_proof(&self, path: &Path, seed: &str, age: u64) -> Result<()> {
+        let bs = self.prove(seed, age)?;
+        std::fs::write(path, bs).context("failed to write ZKP proof to file")?;
+        std::fs::write(
+            Self::sidecar_path(path),
+            format!("{SIDECAR_MIN_AGE_KEY}={REQUIRED_AGE}\n"),
+        )
+        .context("failed to write ZKP proof sidecar to file")?;
+        Ok(())
+    }
+
+    /// Resolves a path of the sidecar file stored next to the proof, i.e. `<proof>.meta`
+    /// params:
+    /// * proof_path - path of the proof
+    pub fn sidecar_path(proof_path: &Path) -> PathBuf {
+        let mut path = proof_path.as_os_str().to_owned();
+        path.push(".meta");
+        PathBuf::from(path)
+    }
+
+    /// Reads minimum age targeted by a proof from its sidecar file
+    /// params:
+    /// * proof_path - path of the proof
+    /// returns:
+    /// * minimum age the proof has been generated for, or `None` when there is no sidecar
+    pub fn proof_min_age(proof_path: &Path) -> Result<Option<u128>> {
+        let path = Self::sidecar_path(proof_path);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let sidecar =
+            std::fs::read_to_string(path).context("failed to read ZKP proof sidecar from file")?;
+        sidecar
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{SIDECAR_MIN_AGE_KEY}=")))
+            .map(|min_age| {
+                min_age
+                    .trim()
+                    .parse()
+                    .context("invalid minimum age in ZKP proof sidecar")
+            })
+            .transpose()
+    }
+
+    /// Generates zero knowlege proof for an account defined by a given seed, without storing it
+    /// params:
+    /// * seed - seed of account for which proof is generated
+    /// * age - age of an owner of the account for which proof is generated
+    /// returns:
+    /// * serialized zero knowledge proof
+    pub fn prove(&self, seed: &str, age: u64) -> Result<Vec<u8>> {
+        self.prove_with_nonce(seed, age, 0)
+    }
+
+    /// Generates zero knowlege proof as `prove` does. The proof is cached under the account, age
+    /// and nonce if proof cache is enabled
+    /// params:
+    /// * seed - seed of account for which proof is generated
+    /// * age - age of an owner of the account for which proof is generated
+    /// * nonce - distinguishes proofs of the same account and age, e.g. a request id
+    /// returns:
+    /// * serialized zero knowledge proof
+    pub fn prove_with_nonce(&self, seed: &str, age: u64, nonce: u64) -> Result<Vec<u8>> {
         let keypair = aleph_client::keypair_from_string(seed);
-        let account_id = keypair.account_id();
+        let account: Account = keypair.account_id().clone().into();
 
-        let proof = MinAgeProof::<REQUIRED_AGE>::new();
-        match &self.setup {
-            Some(setup) => {
-                let bs = proof.generate_proof(setup, age, account_id.as_ref())?;
-                std::fs::write(path, bs).context("failed to write ZKP proof to file")?;
-            }
-            None => {
-                bail!("Missing trusted setup");
+        let Some(setup) = &self.setup else {
+            bail!("Missing trusted setup");
+        };
+        let generate = || MinAgeProof::<REQUIRED_AGE>::new().generate_proof(setup, age, &account);
+        match &self.proof_cache {
+            Some(cache) => {
+                let key = ProofKey {
+                    account,
+                    age,
+                    range_from: REQUIRED_AGE as u64,
+                    range_to: RANGE_TO as u64,
+                    nonce,
+                };
+                cache.get_or_generate(key, generate)
             }
+            None => generate(),
         }
-        Ok(())
     }
 
     /// Loads zero knowledge proof stored under a given path
@@ -78,6 +283,168 @@ impl<const REQUIRED_AGE: usize> MinAgeProofOps<REQUIRED_AGE> {
         Ok(bs)
     }
 
+    /// Checks locally if zero knowledge proof has been generated for a given account.
+    /// The account is a public input of the proof, so the proof verifies only against the
+    /// public inputs of the account it has been generated for.
+    /// params:
+    /// * proof - binary array representing the proof
+    /// * account - account for which the proof is expected to be generated
+    /// returns:
+    /// * true if the proof has been generated for the account, false otherwise
+    pub async fn check_proof_account(&self, proof: &[u8], account: &AccountId) -> Result<bool> {
+        match &self.setup {
+            Some(setup) => Ok(MinAgeProof::<REQUIRED_AGE>::new()
+                .verify_proof(setup, proof, account.clone().into())
+                .is_ok()),
+            None => {
+                bail!("Missing trusted setup");
+            }
+        }
+    }
+
+    /// Verifies locally if zero knowledge proof proves a minimum age for an account defined by
+    /// a given seed, e.g. before paying for a failed `add_subscription` transaction
+    /// params:
+    /// * proof - binary array representing the proof
+    /// * seed - seed of account for which the proof is expected to be generated
+    /// * age - minimum age the proof is expected to prove
+    /// returns:
+    /// * invalid proof error explaining why the proof doesn't verify
+    pub fn verify_proof(&self, proof: &[u8], seed: &str, age: u64) -> Result<()> {
+        let Some(setup) = &self.setup else {
+            bail!("Missing trusted setup");
+        };
+        if age != REQUIRED_AGE as u64 {
+            bail!(ClientError::InvalidProof(format!(
+                "proofs are generated for age {REQUIRED_AGE}, not {age}"
+            )));
+        }
+        let account: Account = aleph_client::keypair_from_string(seed)
+            .account_id()
+            .clone()
+            .into();
+        MinAgeProof::<REQUIRED_AGE>::new()
+            .verify_proof(setup, proof, account)
+            .map_err(|err| ClientError::InvalidProof(format!("{err:#}")).into())
+    }
+
+    /// Compares public inputs used to generate a proof with public inputs built by the
+    /// subscriptions smart contract for an account defined by a given seed
+    /// params:
+    /// * seed - seed of account for which proof is generated
+    /// * min_age - minimum required age configured in the smart contract
+    pub fn diff_public_inputs(seed: &str, min_age: u128) -> PublicInputsDiff {
+        let account: Account = aleph_client::keypair_from_string(seed)
+            .account_id()
+            .clone()
+            .into();
+        PublicInputsDiff {
+            proof_inputs: instances_to_bytes(
+                &MinAgeProof::<REQUIRED_AGE>::new().public_input(account),
+            ),
+            contract_inputs: min_age_public_input_bytes(min_age, &account),
+        }
+    }
+
+    /// Measures the minimum age circuit
+    /// returns:
+    /// * number of required rows, maximum constraint degree and recommended k
+    pub fn circuit_stats() -> CircuitStats {
+        MinAgeProof::<REQUIRED_AGE>::circuit_stats()
+    }
+
+    /// Checks if proofs generated with the loaded trusted setup verify with the other one
+    /// params:
+    /// * other - operations with the other trusted setup loaded
+    /// returns:
+    /// * true if both trusted setups are compatible, false otherwise
+    pub fn setup_compatible_with(&self, other: &Self) -> Result<bool> {
+        match (&self.setup, &other.setup) {
+            (Some(setup), Some(other)) => Ok(setup.proof_compatible_with(other)),
+            _ => {
+                bail!("Missing trusted setup");
+            }
+        }
+    }
+
+    /// Calculates hash of the verification key, as registered in the aleph network's `VkStorage`
+    /// pallet
+    /// returns:
+    /// * hash of the verification key serialized to string
+    pub fn vk_hash(&self) -> Result<String> {
+        match &self.setup {
+            Some(setup) => Ok(format!("{:?}", BlakeTwo256::hash(&setup.vk_to_bytes()))),
+            None => {
+                bail!("Missing trusted setup");
+            }
+        }
+    }
+
+    /// Loads trusted setup stored under a given path and checks if its verification key hash
+    /// matches an expected one, e.g. the hash the smart contract has been deployed with
+    /// params:
+    /// * path - path where trusted setup has been serialized
+    /// * expected_hash - expected hash of the verification key, `0x` prefix is optional
+    /// returns:
+    /// * true if the hashes match, false otherwise
+    pub async fn verify_setup_matches_hash(
+        &mut self,
+        path: &Path,
+        expected_hash: &str,
+    ) -> Result<bool> {
+        self.load_setup(path).await?;
+        let normalize = |hash: &str| hash.trim().trim_start_matches("0x").to_lowercase();
+        Ok(normalize(&self.vk_hash()?) == normalize(expected_hash))
+    }
+
+    /// Generates trusted setup and stores it in a directory under a name derived from the
+    /// verification key hash, i.e. `<vk_hash>.setup`
+    /// params:
+    /// * dir - directory where setups and proofs are stored
+    /// returns:
+    /// * path of the stored setup
+    pub async fn store_setup(&mut self, dir: &Path) -> Result<PathBuf> {
+        let setup = MinAgeProof::<REQUIRED_AGE>::generate_setup()?;
+        let bs = setup.to_bytes()?;
+        self.setup = Some(setup);
+        let path = dir.join(format!("{}.setup", self.vk_hash()?));
+        std::fs::write(&path, bs).context("failed to write ZKP setup to file")?;
+        Ok(path)
+    }
+
+    /// Generates zero knowledge proof for an account defined by a given seed and stores it in a
+    /// directory under a name derived from the account, i.e. `<account>.proof`
+    /// params:
+    /// * dir - directory where setups and proofs are stored
+    /// * seed - seed of account for which proof is generated
+    /// * age - age of an owner of the account for which proof is generated
+    /// returns:
+    /// * path of the stored proof
+    pub async fn store_proof(&self, dir: &Path, seed: &str, age: u64) -> Result<PathBuf> {
+        let account_id = aleph_client::keypair_from_string(seed).account_id().clone();
+        let path = Self::proof_store_path(dir, &account_id);
+        self.generate_proof(&path, seed, age).await?;
+        Ok(path)
+    }
+
+    /// Resolves a path of the proof stored for a given account
+    /// params:
+    /// * dir - directory where setups and proofs are stored
+    /// * account - account for which the proof has been generated
+    /// returns:
+    /// * path of the stored proof
+    pub fn resolve_proof(dir: &Path, account: &AccountId) -> Result<PathBuf> {
+        let path = Self::proof_store_path(dir, account);
+        if !path.is_file() {
+            bail!("Missing ZKP proof for account: {}", account);
+        }
+        Ok(path)
+    }
+
+    fn proof_store_path(dir: &Path, account: &AccountId) -> PathBuf {
+        dir.join(format!("{}.proof", account))
+    }
+
     /// Registers a verification key in the aleph network's `VkStorage` pallet.
     /// Pallet is used for storing a map of verification key hash to verification key
     /// The register is charged for the storage.
@@ -94,14 +461,18 @@ impl<const REQUIRED_AGE: usize> MinAgeProofOps<REQUIRED_AGE> {
             Some(setup) => {
                 let vk_bs = setup.vk_to_bytes();
                 log::info!("Verification key bytes: {:?}", vk_bs.len());
-                let vk_hash = BlakeTwo256::hash(&vk_bs);
+                let vk_hash = self.vk_hash()?;
 
                 let tx_info = signed_conn
                     .store_key(vk_bs, aleph_client::TxStatus::Finalized)
                     .await
-                    .context("failed to register verification key on aleph chain")?;
+                    .with_context(|| {
+                        ClientError::Chain(
+                            "failed to register verification key on aleph chain".to_string(),
+                        )
+                    })?;
                 log::info!("Verification key registration tx info: {:?}", tx_info);
-                Ok(format!("{:?}", vk_hash))
+                Ok(vk_hash)
             }
             None => {
                 bail!("Missing trusted setup");
@@ -114,7 +485,7 @@ impl<const REQUIRED_AGE: usize> MinAgeProofOps<REQUIRED_AGE> {
 mod tests {
     use filepath::FilePath;
 
-    use super::MinAgeProofOps;
+    use super::{ClientError, MinAgeProofOps, PublicInputsDiff};
 
     #[tokio::test]
     async fn test_setup_write_load() {
@@ -127,6 +498,62 @@ mod tests {
         assert!(ops.load_setup(&path).await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_setup_compatible_with() {
+        let tmp_file = tempfile::tempfile().unwrap();
+        let path = tmp_file.path().unwrap();
+
+        let mut ops = MinAgeProofOps::<18>::new();
+        let mut loaded_ops = MinAgeProofOps::<18>::new();
+        assert!(ops.setup_compatible_with(&loaded_ops).is_err());
+
+        ops.generate_setup(&path).await.unwrap();
+        loaded_ops.load_setup(&path).await.unwrap();
+        assert!(ops.setup_compatible_with(&loaded_ops).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_setup() {
+        let tmp_file = tempfile::tempfile().unwrap();
+        let path = tmp_file.path().unwrap();
+
+        // the same verification key hash as generating the setup and loading it to register
+        let mut ops = MinAgeProofOps::<18>::new();
+        let vk_hash = ops.prepare_setup(Some(&path)).await.unwrap();
+        let mut loaded_ops = MinAgeProofOps::<18>::new();
+        loaded_ops.load_setup(&path).await.unwrap();
+        assert_eq!(vk_hash, loaded_ops.vk_hash().unwrap());
+        assert_eq!(vk_hash, ops.vk_hash().unwrap());
+
+        // setup kept in memory only
+        let mut ops = MinAgeProofOps::<18>::new();
+        let vk_hash = ops.prepare_setup(None).await.unwrap();
+        assert_eq!(vk_hash, ops.vk_hash().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_setup_matches_hash() {
+        let tmp_file = tempfile::tempfile().unwrap();
+        let path = tmp_file.path().unwrap();
+
+        let mut ops = MinAgeProofOps::<18>::new();
+        let vk_hash = ops.prepare_setup(Some(&path)).await.unwrap();
+
+        let mut check_ops = MinAgeProofOps::<18>::new();
+        assert!(check_ops
+            .verify_setup_matches_hash(&path, &vk_hash)
+            .await
+            .unwrap());
+        assert!(check_ops
+            .verify_setup_matches_hash(&path, &vk_hash.to_uppercase().replace("0X", ""))
+            .await
+            .unwrap());
+        assert!(!check_ops
+            .verify_setup_matches_hash(&path, &format!("0x{}", "00".repeat(32)))
+            .await
+            .unwrap());
+    }
+
     #[tokio::test]
     async fn test_failed_load_setup() {
         let tmp_file = tempfile::tempfile().unwrap();
@@ -158,4 +585,205 @@ mod tests {
         let proof = std::fs::read(path_proof).unwrap();
         assert!(proof.len() > 0);
     }
+
+    #[tokio::test]
+    async fn test_prove_with_proof_cache() {
+        let tmp_file_setup = tempfile::tempfile().unwrap();
+        let path_setup = tmp_file_setup.path().unwrap();
+
+        let mut ops = MinAgeProofOps::<18>::new();
+        ops.generate_setup(&path_setup).await.unwrap();
+        // proofs are randomized, so proving twice yields different proofs
+        assert_ne!(
+            ops.prove("//Alice", 23).unwrap(),
+            ops.prove("//Alice", 23).unwrap()
+        );
+
+        // while cached proofs are returned without proving again
+        let ops = ops.with_proof_cache(4);
+        let proof = ops.prove_with_nonce("//Alice", 23, 1).unwrap();
+        assert_eq!(ops.prove_with_nonce("//Alice", 23, 1).unwrap(), proof);
+        assert_eq!(
+            ops.clone().prove_with_nonce("//Alice", 23, 1).unwrap(),
+            proof
+        );
+        assert_ne!(ops.prove_with_nonce("//Alice", 23, 2).unwrap(), proof);
+        assert_ne!(ops.prove_with_nonce("//Bob", 23, 1).unwrap(), proof);
+    }
+
+    #[tokio::test]
+    async fn test_proof_sidecar_min_age() {
+        let tmp_file_setup = tempfile::tempfile().unwrap();
+        let path_setup = tmp_file_setup.path().unwrap();
+        let proof_dir = tempfile::tempdir().unwrap();
+        let path_proof = proof_dir.path().join("alice.proof");
+
+        let mut ops = MinAgeProofOps::<18>::new();
+        assert_eq!(
+            MinAgeProofOps::<18>::proof_min_age(&path_proof).unwrap(),
+            None
+        );
+
+        assert!(ops.generate_setup(&path_setup).await.is_ok());
+        assert!(ops.generate_proof(&path_proof, "//Alice", 23).await.is_ok());
+        assert_eq!(
+            MinAgeProofOps::<18>::proof_min_age(&path_proof).unwrap(),
+            Some(18)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_proof_account() {
+        let tmp_file_setup = tempfile::tempfile().unwrap();
+        let path_setup = tmp_file_setup.path().unwrap();
+        let tmp_file_proof = tempfile::tempfile().unwrap();
+        let path_proof = tmp_file_proof.path().unwrap();
+
+        let mut ops = MinAgeProofOps::<18>::new();
+
+        assert!(ops.generate_setup(&path_setup).await.is_ok());
+        assert!(ops.generate_proof(&path_proof, "//Alice", 23).await.is_ok());
+        let proof = ops.load_proof(&path_proof).await.unwrap();
+
+        let alice = aleph_client::keypair_from_string("//Alice")
+            .account_id()
+            .clone();
+        let bob = aleph_client::keypair_from_string("//Bob")
+            .account_id()
+            .clone();
+        assert!(ops.check_proof_account(&proof, &alice).await.unwrap());
+        assert!(!ops.check_proof_account(&proof, &bob).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_proof() {
+        let tmp_file_setup = tempfile::tempfile().unwrap();
+        let path_setup = tmp_file_setup.path().unwrap();
+        let tmp_file_proof = tempfile::tempfile().unwrap();
+        let path_proof = tmp_file_proof.path().unwrap();
+
+        let mut ops = MinAgeProofOps::<18>::new();
+        assert!(ops.verify_proof(&[], "//Alice", 18).is_err());
+
+        assert!(ops.generate_setup(&path_setup).await.is_ok());
+        assert!(ops.generate_proof(&path_proof, "//Alice", 23).await.is_ok());
+        let proof = ops.load_proof(&path_proof).await.unwrap();
+
+        assert!(ops.verify_proof(&proof, "//Alice", 18).is_ok());
+        for err in [
+            ops.verify_proof(&proof, "//Bob", 18).unwrap_err(),
+            ops.verify_proof(&proof, "//Alice", 21).unwrap_err(),
+            ops.verify_proof(&proof[1..], "//Alice", 18).unwrap_err(),
+        ] {
+            assert!(matches!(
+                err.downcast_ref::<ClientError>(),
+                Some(ClientError::InvalidProof(_))
+            ));
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[tokio::test]
+    async fn test_setup_mmap_load() {
+        use subscription_proofs::proofs::MinAgeProof;
+
+        let tmp_file_setup = tempfile::tempfile().unwrap();
+        let path_setup = tmp_file_setup.path().unwrap();
+        let tmp_file_proof = tempfile::tempfile().unwrap();
+        let path_proof = tmp_file_proof.path().unwrap();
+
+        let mut ops = MinAgeProofOps::<18>::new();
+        assert!(ops.generate_setup(&path_setup).await.is_ok());
+        assert!(ops.generate_proof(&path_proof, "//Alice", 23).await.is_ok());
+        let proof = ops.load_proof(&path_proof).await.unwrap();
+
+        let mut read_ops = MinAgeProofOps::<18>::new();
+        let bs = std::fs::read(&path_setup).unwrap();
+        read_ops.setup = Some(MinAgeProof::<18>::load_setup(bs).unwrap());
+        let mut mmap_ops = MinAgeProofOps::<18>::new();
+        mmap_ops.load_setup(&path_setup).await.unwrap();
+
+        let alice = aleph_client::keypair_from_string("//Alice")
+            .account_id()
+            .clone();
+        let bob = aleph_client::keypair_from_string("//Bob")
+            .account_id()
+            .clone();
+        for account in [&alice, &bob] {
+            assert_eq!(
+                read_ops.check_proof_account(&proof, account).await.unwrap(),
+                mmap_ops.check_proof_account(&proof, account).await.unwrap()
+            );
+        }
+        assert!(mmap_ops.check_proof_account(&proof, &alice).await.unwrap());
+        assert_eq!(read_ops.vk_hash().unwrap(), mmap_ops.vk_hash().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_store_proofs() {
+        let store_dir = tempfile::tempdir().unwrap();
+
+        let mut ops = MinAgeProofOps::<18>::new();
+
+        let setup_path = ops.store_setup(store_dir.path()).await.unwrap();
+        assert_eq!(
+            setup_path.file_name().unwrap().to_str().unwrap(),
+            format!("{}.setup", ops.vk_hash().unwrap())
+        );
+
+        let alice_path = ops
+            .store_proof(store_dir.path(), "//Alice", 23)
+            .await
+            .unwrap();
+        let bob_path = ops
+            .store_proof(store_dir.path(), "//Bob", 32)
+            .await
+            .unwrap();
+        assert_ne!(alice_path, bob_path);
+
+        let alice = aleph_client::keypair_from_string("//Alice")
+            .account_id()
+            .clone();
+        let bob = aleph_client::keypair_from_string("//Bob")
+            .account_id()
+            .clone();
+        let charlie = aleph_client::keypair_from_string("//Charlie")
+            .account_id()
+            .clone();
+        assert_eq!(
+            MinAgeProofOps::<18>::resolve_proof(store_dir.path(), &alice).unwrap(),
+            alice_path
+        );
+        assert_eq!(
+            MinAgeProofOps::<18>::resolve_proof(store_dir.path(), &bob).unwrap(),
+            bob_path
+        );
+        assert!(MinAgeProofOps::<18>::resolve_proof(store_dir.path(), &charlie).is_err());
+    }
+
+    #[test]
+    fn test_diff_public_inputs() {
+        let diff = MinAgeProofOps::<18>::diff_public_inputs("//Alice", 18);
+        assert!(diff.consistent());
+        assert!(!MinAgeProofOps::<18>::diff_public_inputs("//Alice", 21).consistent());
+    }
+
+    #[test]
+    fn test_diff_public_inputs_flags_mismatched_padding() {
+        let diff = MinAgeProofOps::<18>::diff_public_inputs("//Alice", 18);
+        // padding placed before each 128-bit chunk instead of after it
+        let mismatched = diff
+            .contract_inputs
+            .chunks(32)
+            .flat_map(|input| [&input[16..], &input[..16]].concat())
+            .collect::<Vec<_>>();
+        let diff = PublicInputsDiff {
+            contract_inputs: mismatched,
+            ..diff
+        };
+
+        assert!(!diff.consistent());
+        assert!(diff.to_string().contains("MISMATCH"));
+        assert!(diff.to_string().ends_with("public inputs inconsistent"));
+    }
 }
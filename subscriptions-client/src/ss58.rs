@@ -0,0 +1,43 @@
+use aleph_client::{
+    sp_core::crypto::{set_default_ss58_version, Ss58AddressFormat, Ss58Codec},
+    AccountId,
+};
+use anyhow::{anyhow, Result};
+
+/// Sets SS58 network prefix used by the client wherever an account is displayed, e.g. in the
+/// CSV export or watched events
+/// params:
+/// * prefix - SS58 network prefix, e.g. of a custom network, so addresses match its explorers
+pub fn set_prefix(prefix: u16) {
+    set_default_ss58_version(Ss58AddressFormat::custom(prefix));
+}
+
+/// Decodes an account from SS58 string encoded with any network prefix
+/// params:
+/// * account - SS58 string
+pub fn decode(account: &str) -> Result<AccountId> {
+    AccountId::from_ss58check_with_version(account)
+        .map(|(account, _)| account)
+        .map_err(|err| anyhow!("invalid account: {err:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_custom_prefix() {
+        let alice = decode("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY").unwrap();
+
+        // encoded with the prefix explicitly, as the default one is shared by tests run in
+        // parallel
+        let generic = alice.to_ss58check_with_version(Ss58AddressFormat::custom(42));
+        let custom = alice.to_ss58check_with_version(Ss58AddressFormat::custom(7));
+        assert_eq!(generic, "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+        assert_ne!(generic, custom);
+
+        // both encodings decode back to the same account
+        assert_eq!(decode(&generic).unwrap(), alice);
+        assert_eq!(decode(&custom).unwrap(), alice);
+    }
+}
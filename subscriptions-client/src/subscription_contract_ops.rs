@@ -1,7 +1,354 @@
-use std::path::Path;
+use std::{io::Write, path::Path};
 
-use aleph_client::{contract::ContractInstance, AccountId, Connection, SignedConnection};
+use aleph_client::{
+    contract::{
+        event::{translate_events, ContractEvent},
+        ContractInstance,
+    },
+    utility::BlockInfo,
+    AccountId, Connection, SignedConnection, TxInfo,
+};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
+use contract_transcode::Value;
+
+use crate::{
+    contract_values::{self, ContractValue},
+    errors::ClientError,
+};
+
+/// Interval of polling the node for newly finalized blocks while watching events
+const EVENTS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Format of the watched events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EventsFormat {
+    /// Human-readable, one event per line
+    Human,
+    /// Newline-delimited JSON, one object per event
+    Ndjson,
+}
+
+/// Active subscription as exposed by the subscriptions smart contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveSubscription {
+    /// Account of the subscriber
+    pub account: AccountId,
+    /// A handle (e.g. chat_id) associated with the user's subscription
+    pub external_channel_handle: String,
+}
+
+impl TryFrom<&Value> for ActiveSubscription {
+    type Error = anyhow::Error;
+
+    /// Converts decoded `ActiveSubscriptionAttr` of the subscriptions smart contract
+    fn try_from(value: &Value) -> Result<Self> {
+        Ok(ActiveSubscription {
+            account: contract_values::as_account(contract_values::field(value, "for_account")?)?,
+            external_channel_handle: String::from_utf8_lossy(&contract_values::as_bytes(
+                contract_values::field(value, "external_channel_handle")?,
+            )?)
+            .to_string(),
+        })
+    }
+}
+
+/// Subscription details exported for reporting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionRecord {
+    /// Account of the subscriber
+    pub account: AccountId,
+    /// A handle (e.g. chat_id) associated with the user's subscription
+    pub external_channel_handle: String,
+    /// Declared payment interval, e.g. Week
+    pub payment_interval: String,
+    /// Number of already paid intervals
+    pub paid_intervals: u32,
+    /// Number of declared payment intervals
+    pub declared_payment_intervals: u32,
+    /// Block at which all declared payment intervals end
+    pub expiry_block: u32,
+}
+
+/// Estimated out-of-pocket cost of a new subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionQuote {
+    /// Price of a single payment interval
+    pub interval_cost: u128,
+    /// Number of declared payment intervals
+    pub intervals: u32,
+    /// Estimated fee of the `add_subscription` transaction
+    pub fee: u128,
+}
+
+impl SubscriptionQuote {
+    /// Cost of all declared payment intervals
+    pub fn subscription_cost(&self) -> u128 {
+        self.interval_cost.saturating_mul(self.intervals as u128)
+    }
+
+    /// Subscription cost together with the transaction fee
+    pub fn total(&self) -> u128 {
+        self.subscription_cost().saturating_add(self.fee)
+    }
+}
+
+impl std::fmt::Display for SubscriptionQuote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "subscription: {} x {} = {}",
+            self.intervals,
+            self.interval_cost,
+            self.subscription_cost()
+        )?;
+        writeln!(f, "estimated fee: {}", self.fee)?;
+        write!(f, "total: {}", self.total())
+    }
+}
+
+/// Provides an estimate of the fee of the `add_subscription` transaction
+#[async_trait]
+pub trait FeeEstimator {
+    /// Estimates fee of registering a new subscription
+    /// params:
+    /// * seed - a seed of the subscriber
+    /// * payment_interval - one of Week|Month
+    /// * intervals - number of payment intervals
+    /// * external_channel_handle - for example Telegram channel handle
+    /// * required_age - age proven by the proof, selecting the smart contract's verification key
+    /// * proof - minimum age zero knowledge proof of the subscriber
+    /// * value - tokens transferred with the transaction
+    /// returns:
+    /// * estimated fee
+    #[allow(clippy::too_many_arguments)]
+    async fn estimate_add_subscription_fee(
+        &self,
+        seed: &str,
+        payment_interval: &str,
+        intervals: u32,
+        external_channel_handle: &str,
+        required_age: u128,
+        proof: &[u8],
+        value: u128,
+    ) -> Result<u128>;
+}
+
+/// Number of blocks of a week payment interval, as defined by the subscriptions smart contract
+const BLOCKS_PER_WEEK: u128 = 3600 * 24 * 7;
+
+/// Quotes the full cost of a new subscription: cost of all declared intervals and the estimated
+/// transaction fee
+/// params:
+/// * estimator - provides an estimate of the transaction fee
+/// * prices - price per week and price per month, as returned by the smart contract. Price of
+/// a custom interval is derived from the price per week
+/// * other params are the same as for `FeeEstimator::estimate_add_subscription_fee`
+#[allow(clippy::too_many_arguments)]
+pub async fn quote_subscription<E: FeeEstimator + ?Sized>(
+    estimator: &E,
+    prices: (u128, u128),
+    seed: &str,
+    payment_interval: &str,
+    intervals: u32,
+    external_channel_handle: &str,
+    required_age: u128,
+    proof: &[u8],
+) -> Result<SubscriptionQuote> {
+    let interval = payment_interval.to_lowercase();
+    let custom_blocks = interval
+        .strip_prefix("custom(")
+        .and_then(|blocks| blocks.strip_suffix(')'))
+        .and_then(|blocks| blocks.trim().parse::<u128>().ok());
+    let interval_cost = match (interval.as_str(), custom_blocks) {
+        ("week", _) => prices.0,
+        ("month", _) => prices.1,
+        (_, Some(blocks)) if blocks > 0 => (prices.0 / BLOCKS_PER_WEEK)
+            .checked_mul(blocks)
+            .context("payment interval cost overflow")?,
+        _ => anyhow::bail!("unsupported payment interval: {payment_interval}"),
+    };
+    let mut quote = SubscriptionQuote {
+        interval_cost,
+        intervals,
+        fee: 0,
+    };
+    quote.fee = estimator
+        .estimate_add_subscription_fee(
+            seed,
+            payment_interval,
+            intervals,
+            external_channel_handle,
+            required_age,
+            proof,
+            quote.subscription_cost(),
+        )
+        .await?;
+    Ok(quote)
+}
+
+/// Dry-runs `add_subscription` with a given proof, without submitting the transaction
+#[async_trait]
+pub trait SubscriptionDryRunner {
+    /// Dry-runs registration of a weekly subscription
+    /// params:
+    /// * seed - a seed of the subscriber
+    /// * required_age - age proven by the proof, selecting the smart contract's verification key
+    /// * proof - minimum age zero knowledge proof of the subscriber
+    /// returns:
+    /// * name of the smart contract error, `None` when the registration succeeds
+    async fn dry_run_add_subscription(
+        &self,
+        seed: &str,
+        required_age: u128,
+        proof: &[u8],
+    ) -> Result<Option<String>>;
+}
+
+/// Outcome of the verification of a proof by the chain extension, as observed by a dry run of
+/// `add_subscription`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationOutcome {
+    /// The proof has been verified
+    Accepted,
+    /// The proof has been rejected by the verification
+    Rejected,
+    /// The registration has failed before the proof could be verified
+    Inconclusive(String),
+}
+
+impl VerificationOutcome {
+    /// Classifies result of a dry run of `add_subscription`. Payment is collected after the proof
+    /// is verified, so payment errors mean the proof has been accepted
+    /// params:
+    /// * error - name of the smart contract error, `None` when the registration succeeds
+    pub fn from_contract_error(error: Option<&str>) -> Self {
+        match error {
+            None
+            | Some("SubscriptionCostTooHigh")
+            | Some("TokenTransferFailed")
+            | Some("NativeTransferNotAccepted") => VerificationOutcome::Accepted,
+            Some("InvalidProofForMinAgeRequired") => VerificationOutcome::Rejected,
+            Some(error) => VerificationOutcome::Inconclusive(error.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for VerificationOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationOutcome::Accepted => write!(f, "accepted"),
+            VerificationOutcome::Rejected => write!(f, "rejected"),
+            VerificationOutcome::Inconclusive(error) => {
+                write!(f, "inconclusive, failed before verification: {error}")
+            }
+        }
+    }
+}
+
+/// Outcomes of a known-valid and a known-invalid proof submitted by a dry run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionCheck {
+    /// Outcome of the valid proof
+    pub valid: VerificationOutcome,
+    /// Outcome of the invalid proof
+    pub invalid: VerificationOutcome,
+}
+
+impl ExtensionCheck {
+    /// Checks if the chain extension accepts the valid proof and rejects the invalid one, i.e.
+    /// it returns the status codes the smart contract expects
+    pub fn consistent(&self) -> bool {
+        self.valid == VerificationOutcome::Accepted && self.invalid == VerificationOutcome::Rejected
+    }
+}
+
+impl std::fmt::Display for ExtensionCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "valid proof: {}", self.valid)?;
+        write!(f, "invalid proof: {}", self.invalid)
+    }
+}
+
+/// Confirms the wiring of the chain extension verifying proofs, by dry-running `add_subscription`
+/// with a known-valid proof and with its tampered copy
+/// params:
+/// * runner - dry-runs `add_subscription`
+/// * seed - a seed of the subscriber the proof has been generated for. The subscriber must not
+/// be subscribed, otherwise the registration fails before the verification
+/// * required_age - age proven by the proof
+/// * valid_proof - proof generated for the subscriber and the required age
+/// returns:
+/// * outcomes of both proofs
+pub async fn check_extension<R: SubscriptionDryRunner + ?Sized>(
+    runner: &R,
+    seed: &str,
+    required_age: u128,
+    valid_proof: &[u8],
+) -> Result<ExtensionCheck> {
+    let mut invalid_proof = valid_proof.to_vec();
+    let last = invalid_proof.last_mut().context("empty proof")?;
+    *last ^= 0xff;
+
+    let valid = runner
+        .dry_run_add_subscription(seed, required_age, valid_proof)
+        .await?;
+    let invalid = runner
+        .dry_run_add_subscription(seed, required_age, &invalid_proof)
+        .await?;
+    Ok(ExtensionCheck {
+        valid: VerificationOutcome::from_contract_error(valid.as_deref()),
+        invalid: VerificationOutcome::from_contract_error(invalid.as_deref()),
+    })
+}
+
+/// Provides solvency ratio of the subscriptions smart contract
+#[async_trait]
+pub trait SolvencySource {
+    /// Retrieves ratio of the contract's balance to the tokens refundable to active subscribers,
+    /// in basis points
+    async fn solvency_ratio_bps(&self) -> Result<u32>;
+}
+
+/// Polls solvency ratio of the subscriptions smart contract and alerts when it drops below
+/// a threshold
+/// params:
+/// * source - provides the solvency ratio
+/// * threshold_bps - solvency ratio in basis points below which an alert is raised
+/// * interval - interval between polls
+/// * exit_on_alert - returns an error on the first alert instead of logging it
+/// * max_polls - number of polls, unlimited if not set
+/// returns:
+/// * number of raised alerts, or an error on the first alert when `exit_on_alert` is set
+pub async fn monitor_solvency<S: SolvencySource + ?Sized>(
+    source: &S,
+    threshold_bps: u32,
+    interval: std::time::Duration,
+    exit_on_alert: bool,
+    max_polls: Option<usize>,
+) -> Result<usize> {
+    let mut alerts = 0;
+    let mut polls = 0;
+    while max_polls.map_or(true, |max_polls| polls < max_polls) {
+        if polls > 0 {
+            tokio::time::sleep(interval).await;
+        }
+        polls += 1;
+
+        let ratio = source.solvency_ratio_bps().await?;
+        if ratio >= threshold_bps {
+            log::debug!("Solvency ratio: {} bps", ratio);
+            continue;
+        }
+        alerts += 1;
+        let alert = format!("solvency ratio {ratio} bps is below threshold {threshold_bps} bps");
+        if exit_on_alert {
+            anyhow::bail!(alert);
+        }
+        log::warn!("{}", alert);
+    }
+    Ok(alerts)
+}
 
 /// Provides commands interactive with subscription smart contract
 pub struct SubscriptionContractOps {
@@ -18,6 +365,37 @@ impl std::fmt::Debug for SubscriptionContractOps {
     }
 }
 
+/// Connects to the aleph zero node
+/// params:
+/// * node_address - aleph network connection address
+/// returns:
+/// * connection to the node, or `ClientError::Connection` when the node can't be reached
+pub async fn connect(node_address: &str) -> Result<Connection> {
+    // aleph client panics when it fails to connect, so the panic is caught by a separate task
+    let address = node_address.to_string();
+    tokio::spawn(async move { Connection::new(&address).await })
+        .await
+        .map_err(|_| ClientError::Connection(format!("failed to connect to {node_address}")).into())
+}
+
+/// Checks if a proof has been generated for the age required by the subscription, e.g. the
+/// minimum age currently required by the subscriptions smart contract. A proof generated for
+/// a stale policy fails on-chain verification without a clear reason, so it's rejected before
+/// submission.
+/// params:
+/// * proof_min_age - minimum age the proof has been generated for
+/// * required_age - age required by the subscription
+pub fn check_proof_min_age(proof_min_age: u128, required_age: u128) -> Result<()> {
+    if proof_min_age != required_age {
+        return Err(ClientError::InvalidProof(format!(
+            "proof generated for age {proof_min_age}, but the smart contract requires age \
+             {required_age}; regenerate your proof for age {required_age}"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
 impl SubscriptionContractOps {
     /// Creates new instance of the subscription smart contract client
     /// params:
@@ -31,7 +409,7 @@ impl SubscriptionContractOps {
             .to_str()
             .context("contract's metadata not set")?;
 
-        let conn = futures::executor::block_on(aleph_client::Connection::new(node_address));
+        let conn = futures::executor::block_on(connect(node_address))?;
 
         Ok(Self {
             contract: ContractInstance::new(address, metadata_path)?,
@@ -48,16 +426,29 @@ impl SubscriptionContractOps {
     /// * payment_interval - one of WEEK|MONTH
     /// * intervals - number of payment intervals
     /// * external_channel_handle - for example Telegram channel handle
-    /// * proof - zero knowledge proof requried to proof that the called is older then minimum
-    /// required age
+    /// * required_age - age proven by the proof, selecting the smart contract's verification
+    /// key. Must not be lower than the smart contract's minimum required age
+    /// * proof - zero knowledge proof requried to proof that the called is older then required
+    /// age
+    /// * proof_min_age - minimum age the proof has been generated for, if known. The submission
+    /// is refused when it differs from the required age
+    /// returns:
+    /// * info of the submitted transaction
+    #[allow(clippy::too_many_arguments)]
     pub async fn add_subscription(
         &self,
         seed: &str,
         payment_interval: &str,
         intervals: u32,
         external_channel_handle: &str,
+        required_age: u128,
         proof: Vec<u8>,
-    ) -> Result<()> {
+        proof_min_age: Option<u128>,
+    ) -> Result<TxInfo> {
+        if let Some(proof_min_age) = proof_min_age {
+            check_proof_min_age(proof_min_age, required_age)?;
+        }
+
         let keypair = aleph_client::keypair_from_string(seed);
         let signed_conn = SignedConnection::from_connection(self.conn.clone(), keypair);
 
@@ -70,12 +461,1068 @@ impl SubscriptionContractOps {
                     format!("{payment_interval}"),
                     format!("{intervals}"),
                     format!("\"{external_channel_handle}\""),
+                    format!("{required_age}"),
                     format!("{proof:?}"),
                 ],
             )
-            .await?;
+            .await
+            .with_context(|| ClientError::Chain("add_subscription call failed".to_string()))?;
         log::info!("Add subscription transaction info: {:?}", tx_info);
 
-        Ok(())
+        Ok(tx_info)
+    }
+
+    /// Cancels subscription of a caller by calling the subscriptions smart contract. Unpaid
+    /// intervals are refunded to the caller.
+    /// The `cancel_subscription` message is payable, only so tokens transferred by mistake are
+    /// returned together with the refund, hence no tokens are transferred with the call.
+    /// params:
+    /// * seed - a seed of a caller
+    /// returns:
+    /// * info of the submitted transaction
+    pub async fn cancel_subscription(&self, seed: &str) -> Result<TxInfo> {
+        let keypair = aleph_client::keypair_from_string(seed);
+        let signed_conn = SignedConnection::from_connection(self.conn.clone(), keypair);
+
+        let tx_info = self
+            .contract
+            .contract_exec0(&signed_conn, "cancel_subscription")
+            .await
+            .with_context(|| ClientError::Chain("cancel_subscription call failed".to_string()))?;
+        log::info!("Cancel subscription transaction info: {:?}", tx_info);
+
+        Ok(tx_info)
+    }
+
+    /// Settles payments of all active subscriptions by calling the subscriptions smart contract.
+    /// Only the owner of the smart contract is allowed to call it
+    /// params:
+    /// * seed - a seed of the owner
+    /// returns:
+    /// * info of the submitted transaction
+    pub async fn payment_settlement(&self, seed: &str) -> Result<TxInfo> {
+        let keypair = aleph_client::keypair_from_string(seed);
+        let signed_conn = SignedConnection::from_connection(self.conn.clone(), keypair);
+
+        let tx_info = self
+            .contract
+            .contract_exec0(&signed_conn, "payment_settlement")
+            .await
+            .with_context(|| ClientError::Chain("payment_settlement call failed".to_string()))?;
+        log::info!("Payment settlement transaction info: {:?}", tx_info);
+
+        Ok(tx_info)
+    }
+
+    /// Settles payments of a range of active subscriptions by calling the subscriptions smart
+    /// contract, so the gas of a single call is bounded when there are many active subscriptions.
+    /// Only the owner of the smart contract is allowed to call it
+    /// params:
+    /// * seed - a seed of the owner
+    /// * start - index of the first settled active subscription
+    /// * end - index after the last settled active subscription
+    /// returns:
+    /// * info of the submitted transaction
+    pub async fn payment_settlement_range(
+        &self,
+        seed: &str,
+        start: u32,
+        end: u32,
+    ) -> Result<TxInfo> {
+        let keypair = aleph_client::keypair_from_string(seed);
+        let signed_conn = SignedConnection::from_connection(self.conn.clone(), keypair);
+
+        let tx_info = self
+            .contract
+            .contract_exec(
+                &signed_conn,
+                "payment_settlement_range",
+                &[format!("{start}"), format!("{end}")],
+            )
+            .await
+            .with_context(|| {
+                ClientError::Chain("payment_settlement_range call failed".to_string())
+            })?;
+        log::info!("Payment settlement transaction info: {:?}", tx_info);
+
+        Ok(tx_info)
+    }
+
+    /// Retrieves prices of both payment intervals of the subscriptions smart contract
+    /// returns:
+    /// * price per week and price per month
+    pub async fn prices(&self) -> Result<(u128, u128)> {
+        let value: ContractValue = self
+            .contract
+            .contract_read0(&self.conn, "prices")
+            .await
+            .with_context(|| ClientError::Chain("prices call failed".to_string()))?;
+        let prices = value.into_ok()?;
+        match contract_values::tuple_elems(&prices)?[..] {
+            [week, month] => Ok((
+                contract_values::as_u128(week)?,
+                contract_values::as_u128(month)?,
+            )),
+            _ => anyhow::bail!("unexpected prices: {prices:?}"),
+        }
+    }
+
+    /// Retrieves age policy of the subscriptions smart contract
+    /// returns:
+    /// * minimum required age and maximum age proven by the zero knowledge proof
+    pub async fn age_policy(&self) -> Result<(u128, u128)> {
+        let value: ContractValue = self
+            .contract
+            .contract_read0(&self.conn, "age_policy")
+            .await
+            .with_context(|| ClientError::Chain("age_policy call failed".to_string()))?;
+        let policy = value.into_ok()?;
+        match contract_values::tuple_elems(&policy)?[..] {
+            [min_age, max_age] => Ok((
+                contract_values::as_u128(min_age)?,
+                contract_values::as_u128(max_age)?,
+            )),
+            _ => anyhow::bail!("unexpected age policy: {policy:?}"),
+        }
+    }
+
+    /// Retrieves active subscriptions from the subscriptions smart contract
+    /// returns:
+    /// * list of active subscriptions
+    /// * flag set when there are more active subscriptions than returned
+    pub async fn get_active_subscriptions(&self) -> Result<(Vec<ActiveSubscription>, bool)> {
+        let value: ContractValue = self
+            .contract
+            .contract_read0(&self.conn, "get_active_subscriptions")
+            .await
+            .with_context(|| {
+                ClientError::Chain("get_active_subscriptions call failed".to_string())
+            })?;
+        let value = value.into_ok()?;
+        let elems = contract_values::tuple_elems(&value)?;
+        let [subs, more] = elems.as_slice() else {
+            anyhow::bail!("unexpected active subscriptions: {value:?}");
+        };
+
+        let subs = contract_values::seq_elems(subs)?
+            .iter()
+            .map(ActiveSubscription::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok((subs, contract_values::as_bool(more)?))
+    }
+
+    /// Reconstructs active subscriptions from the smart contract's events, e.g. when an off-chain
+    /// index has been lost
+    /// params:
+    /// * from_block - number of the first scanned block, e.g. the contract's instantiation block
+    /// returns:
+    /// * active subscriptions in the order of registration
+    pub async fn reindex(&self, from_block: u32) -> Result<Vec<ActiveSubscription>> {
+        let head = self.finalized_block_number().await?;
+
+        let mut events = vec![];
+        for block in from_block..=head {
+            events.extend(self.block_events(block).await?);
+            log::debug!("Block {} of {} scanned", block, head);
+        }
+
+        Ok(reconstruct_subscriptions(events))
+    }
+
+    /// Watches events of the subscriptions smart contract emitted in finalized blocks and writes
+    /// them to the output, one line per event
+    /// params:
+    /// * from_block - number of the first watched block, the next finalized block if not set
+    /// * format - format of the written events
+    /// * writer - output of the events
+    pub async fn watch_events<W: Write>(
+        &self,
+        from_block: Option<u32>,
+        format: EventsFormat,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut next_block = match from_block {
+            Some(from_block) => from_block,
+            None => self.finalized_block_number().await? + 1,
+        };
+        loop {
+            let head = self.finalized_block_number().await?;
+            for block in next_block..=head {
+                for event in self.block_events(block).await? {
+                    writeln!(writer, "{}", event.format(format, block))?;
+                }
+                writer.flush().context("failed to write events")?;
+            }
+            next_block = next_block.max(head + 1);
+            tokio::time::sleep(EVENTS_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Watches payment settlement events of the subscriptions smart contract emitted in finalized
+    /// blocks and writes a summary of every settlement run together with the running totals,
+    /// one line per run. Settlement events emitted in the same block make up a single run
+    /// params:
+    /// * from_block - number of the first watched block, the next finalized block if not set
+    /// * writer - output of the summaries
+    pub async fn tail_settlements<W: Write>(
+        &self,
+        from_block: Option<u32>,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut summary = SettlementSummary::default();
+        let mut next_block = match from_block {
+            Some(from_block) => from_block,
+            None => self.finalized_block_number().await? + 1,
+        };
+        loop {
+            let head = self.finalized_block_number().await?;
+            for block in next_block..=head {
+                let mut events = vec![];
+                for event in self.block_contract_events(block).await? {
+                    if let Some(event) = SettlementEvent::try_from_contract_event(&event)? {
+                        events.push(event);
+                    }
+                }
+                if events.is_empty() {
+                    continue;
+                }
+                let run = SettlementRun::from_events(&events);
+                summary.add(&run);
+                writeln!(writer, "block {block}: {run} | {summary}")?;
+                writer.flush().context("failed to write settlements")?;
+            }
+            next_block = next_block.max(head + 1);
+            tokio::time::sleep(EVENTS_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Retrieves number of the last finalized block
+    async fn finalized_block_number(&self) -> Result<u32> {
+        let head = self
+            .conn
+            .get_finalized_block_hash()
+            .await
+            .with_context(|| ClientError::Chain("failed to get finalized block".to_string()))?;
+        self.conn
+            .get_block_number(head)
+            .await?
+            .context("missing finalized block number")
+    }
+
+    /// Retrieves events of the subscriptions smart contract emitted in a given block
+    async fn block_events(&self, block: u32) -> Result<Vec<SubscriptionEvent>> {
+        let mut events = vec![];
+        for event in self.block_contract_events(block).await? {
+            if let Some(event) = SubscriptionEvent::try_from_contract_event(&event)? {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Retrieves decoded events of the subscriptions smart contract emitted in a given block
+    async fn block_contract_events(&self, block: u32) -> Result<Vec<ContractEvent>> {
+        let block_hash = self
+            .conn
+            .get_block_hash(block)
+            .await?
+            .with_context(|| format!("missing block: {block}"))?;
+        let block_events = self
+            .conn
+            .as_client()
+            .events()
+            .at(block_hash)
+            .await
+            .with_context(|| ClientError::Chain(format!("failed to get events: {block}")))?;
+
+        translate_events(block_events.iter(), &[&self.contract], None)
+            .into_iter()
+            .collect()
+    }
+
+    /// Retrieves details of the subscription associated with a given account
+    /// params:
+    /// * subscription - active subscription
+    /// returns:
+    /// * subscription details
+    pub async fn subscription_record(
+        &self,
+        subscription: &ActiveSubscription,
+    ) -> Result<SubscriptionRecord> {
+        let value: ContractValue = self
+            .contract
+            .contract_read(
+                &self.conn,
+                "coverage_summary",
+                &[subscription.account.to_string()],
+            )
+            .await
+            .with_context(|| ClientError::Chain("coverage_summary call failed".to_string()))?;
+        let summary = value.into_ok()?;
+
+        Ok(SubscriptionRecord {
+            account: subscription.account.clone(),
+            external_channel_handle: subscription.external_channel_handle.clone(),
+            payment_interval: contract_values::variant_name(contract_values::field(
+                &summary,
+                "payment_interval",
+            )?)?,
+            paid_intervals: contract_values::as_u32(contract_values::field(
+                &summary,
+                "paid_intervals",
+            )?)?,
+            declared_payment_intervals: contract_values::as_u32(contract_values::field(
+                &summary,
+                "declared_payment_intervals",
+            )?)?,
+            expiry_block: contract_values::as_u32(contract_values::field(
+                &summary,
+                "expiry_block",
+            )?)?,
+        })
+    }
+
+    /// Exports all active subscriptions to a CSV file
+    /// params:
+    /// * out - path of the CSV file
+    /// returns:
+    /// * number of exported subscriptions
+    pub async fn export_subscriptions(&self, out: &Path) -> Result<usize> {
+        let (subs, more) = self.get_active_subscriptions().await?;
+        if more {
+            log::warn!("Not all active subscriptions have been returned by the smart contract");
+        }
+
+        let mut records = vec![];
+        for sub in &subs {
+            records.push(self.subscription_record(sub).await?);
+        }
+
+        let file = std::fs::File::create(out).context("failed to create CSV file")?;
+        write_subscriptions_csv(file, &records)?;
+        Ok(records.len())
+    }
+}
+
+#[async_trait]
+impl SolvencySource for SubscriptionContractOps {
+    async fn solvency_ratio_bps(&self) -> Result<u32> {
+        let value: ContractValue = self
+            .contract
+            .contract_read0(&self.conn, "solvency_ratio_bps")
+            .await
+            .with_context(|| ClientError::Chain("solvency_ratio_bps call failed".to_string()))?;
+        contract_values::as_u32(&value.into_ok()?)
+    }
+}
+
+#[async_trait]
+impl FeeEstimator for SubscriptionContractOps {
+    /// Dry-runs `add_subscription` and sums up required gas and storage deposit. The estimate
+    /// assumes the 1:1 weight to fee ratio, so it approximates the final fee
+    async fn estimate_add_subscription_fee(
+        &self,
+        seed: &str,
+        payment_interval: &str,
+        intervals: u32,
+        external_channel_handle: &str,
+        required_age: u128,
+        proof: &[u8],
+        value: u128,
+    ) -> Result<u128> {
+        let sender = aleph_client::keypair_from_string(seed).account_id().clone();
+        let result = self
+            .contract
+            .dry_run_any(
+                &self.conn,
+                "add_subscription",
+                &[
+                    format!("{payment_interval}"),
+                    format!("{intervals}"),
+                    format!("\"{external_channel_handle}\""),
+                    format!("{required_age}"),
+                    format!("{proof:?}"),
+                ],
+                sender,
+                value,
+            )
+            .await
+            .with_context(|| ClientError::Chain("add_subscription dry run failed".to_string()))?;
+        Ok((result.gas_required.ref_time() as u128)
+            .saturating_add(result.storage_deposit.charge_or_zero()))
+    }
+}
+
+#[async_trait]
+impl SubscriptionDryRunner for SubscriptionContractOps {
+    async fn dry_run_add_subscription(
+        &self,
+        seed: &str,
+        required_age: u128,
+        proof: &[u8],
+    ) -> Result<Option<String>> {
+        let sender = aleph_client::keypair_from_string(seed).account_id().clone();
+        let value: ContractValue = self
+            .contract
+            .contract_read_as(
+                &self.conn,
+                "add_subscription",
+                &[
+                    "Week".to_string(),
+                    "1".to_string(),
+                    "\"check_extension\"".to_string(),
+                    format!("{required_age}"),
+                    format!("{proof:?}"),
+                ],
+                sender,
+            )
+            .await
+            .with_context(|| ClientError::Chain("add_subscription dry run failed".to_string()))?;
+        value.error_name()
+    }
+}
+
+/// Event of the subscriptions smart contract changing the set of active subscriptions or their
+/// channel handles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionEvent {
+    /// New subscription has been added
+    NewSubscription(ActiveSubscription),
+    /// Subscription has been cancelled by the subscriber
+    CancelledSubscription(AccountId),
+    /// Subscriptions have been cancelled during the payment settlement
+    CancelledSubscriptions(Vec<AccountId>),
+    /// Channel handle of a subscription has been changed to the given one
+    UpdatedChannelHandle(ActiveSubscription),
+}
+
+impl SubscriptionEvent {
+    /// Converts decoded contract event
+    /// returns:
+    /// * event changing the set of active subscriptions or their channel handles, `None` for
+    /// other events
+    pub fn try_from_contract_event(event: &ContractEvent) -> Result<Option<Self>> {
+        let field = |name: &str| {
+            event
+                .data
+                .get(name)
+                .with_context(|| format!("missing event field: {name}"))
+        };
+        let event = match event.name.as_deref() {
+            Some("NewSubscription") => SubscriptionEvent::NewSubscription(ActiveSubscription {
+                account: contract_values::as_account(field("for_account")?)?,
+                external_channel_handle: String::from_utf8_lossy(&contract_values::as_bytes(
+                    field("external_channel_handle")?,
+                )?)
+                .to_string(),
+            }),
+            Some("UpdatedChannelHandle") => {
+                SubscriptionEvent::UpdatedChannelHandle(ActiveSubscription {
+                    account: contract_values::as_account(field("for_account")?)?,
+                    external_channel_handle: String::from_utf8_lossy(&contract_values::as_bytes(
+                        field("external_channel_handle")?,
+                    )?)
+                    .to_string(),
+                })
+            }
+            Some("CancelledSubscription") => SubscriptionEvent::CancelledSubscription(
+                contract_values::as_account(field("for_account")?)?,
+            ),
+            Some("CancelledSubscriptions") => SubscriptionEvent::CancelledSubscriptions(
+                contract_values::seq_elems(field("for_accounts")?)?
+                    .iter()
+                    .map(|sub| Ok(ActiveSubscription::try_from(sub)?.account))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            _ => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+
+    /// Formats the event emitted in a given block
+    /// params:
+    /// * format - human-readable or newline-delimited JSON
+    /// * block - number of the block the event has been emitted in
+    pub fn format(&self, format: EventsFormat, block: u32) -> String {
+        match format {
+            EventsFormat::Human => self.to_human(block),
+            EventsFormat::Ndjson => self.to_ndjson(block),
+        }
+    }
+
+    fn to_human(&self, block: u32) -> String {
+        match self {
+            SubscriptionEvent::NewSubscription(sub) => format!(
+                "block {block}: NewSubscription {} {}",
+                sub.account, sub.external_channel_handle
+            ),
+            SubscriptionEvent::CancelledSubscription(account) => {
+                format!("block {block}: CancelledSubscription {account}")
+            }
+            SubscriptionEvent::CancelledSubscriptions(accounts) => format!(
+                "block {block}: CancelledSubscriptions {}",
+                accounts
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            SubscriptionEvent::UpdatedChannelHandle(sub) => format!(
+                "block {block}: UpdatedChannelHandle {} {}",
+                sub.account, sub.external_channel_handle
+            ),
+        }
+    }
+
+    fn to_ndjson(&self, block: u32) -> String {
+        match self {
+            SubscriptionEvent::NewSubscription(sub) => format!(
+                r#"{{"event":"NewSubscription","account":{},"handle":{},"block":{block}}}"#,
+                json_string(&sub.account.to_string()),
+                json_string(&sub.external_channel_handle)
+            ),
+            SubscriptionEvent::CancelledSubscription(account) => format!(
+                r#"{{"event":"CancelledSubscription","account":{},"block":{block}}}"#,
+                json_string(&account.to_string())
+            ),
+            SubscriptionEvent::CancelledSubscriptions(accounts) => format!(
+                r#"{{"event":"CancelledSubscriptions","accounts":[{}],"block":{block}}}"#,
+                accounts
+                    .iter()
+                    .map(|account| json_string(&account.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            SubscriptionEvent::UpdatedChannelHandle(sub) => format!(
+                r#"{{"event":"UpdatedChannelHandle","account":{},"handle":{},"block":{block}}}"#,
+                json_string(&sub.account.to_string()),
+                json_string(&sub.external_channel_handle)
+            ),
+        }
+    }
+}
+
+/// Event of the subscriptions smart contract emitted by the payment settlement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettlementEvent {
+    /// Subscription has been charged and stays active
+    PaymentSettled {
+        /// Account of the charged subscriber
+        account: AccountId,
+        /// Number of intervals charged during the settlement
+        intervals_charged: u32,
+        /// Tokens transferred to the owner during the settlement
+        amount: u128,
+    },
+    /// Subscriptions have been cancelled, because of insufficient funds
+    CancelledForNonPayment(Vec<AccountId>),
+}
+
+impl SettlementEvent {
+    /// Converts decoded contract event
+    /// returns:
+    /// * payment settlement event, `None` for other events, including cancellations for other
+    /// reasons than non-payment
+    pub fn try_from_contract_event(event: &ContractEvent) -> Result<Option<Self>> {
+        let field = |name: &str| {
+            event
+                .data
+                .get(name)
+                .with_context(|| format!("missing event field: {name}"))
+        };
+        let event = match event.name.as_deref() {
+            Some("PaymentSettled") => SettlementEvent::PaymentSettled {
+                account: contract_values::as_account(field("for_account")?)?,
+                intervals_charged: contract_values::as_u32(field("intervals_charged")?)?,
+                amount: contract_values::as_u128(field("amount")?)?,
+            },
+            Some("CancelledSubscriptions")
+                if contract_values::variant_name(field("reason")?)? == "NonPayment" =>
+            {
+                SettlementEvent::CancelledForNonPayment(
+                    contract_values::seq_elems(field("for_accounts")?)?
+                        .iter()
+                        .map(|sub| Ok(ActiveSubscription::try_from(sub)?.account))
+                        .collect::<Result<Vec<_>>>()?,
+                )
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+}
+
+/// Summary of a single payment settlement run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SettlementRun {
+    /// Number of charged subscriptions
+    pub charged: u32,
+    /// Number of subscriptions cancelled, because of insufficient funds
+    pub cancelled: u32,
+    /// Tokens transferred to the owner
+    pub collected: u128,
+}
+
+impl SettlementRun {
+    /// Summarizes settlement events emitted by a single payment settlement run
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a SettlementEvent>) -> Self {
+        let mut run = SettlementRun::default();
+        for event in events {
+            match event {
+                SettlementEvent::PaymentSettled { amount, .. } => {
+                    run.charged += 1;
+                    run.collected = run.collected.saturating_add(*amount);
+                }
+                SettlementEvent::CancelledForNonPayment(accounts) => {
+                    run.cancelled += accounts.len() as u32;
+                }
+            }
+        }
+        run
+    }
+}
+
+impl std::fmt::Display for SettlementRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "charged {}, cancelled {}, collected {}",
+            self.charged, self.cancelled, self.collected
+        )
+    }
+}
+
+/// Running totals of payment settlement runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SettlementSummary {
+    /// Number of settlement runs
+    pub runs: u32,
+    /// Number of charges across all runs
+    pub charged: u64,
+    /// Number of subscriptions cancelled, because of insufficient funds, across all runs
+    pub cancelled: u64,
+    /// Tokens transferred to the owner across all runs
+    pub collected: u128,
+}
+
+impl SettlementSummary {
+    /// Adds a settlement run to the totals
+    pub fn add(&mut self, run: &SettlementRun) {
+        self.runs += 1;
+        self.charged += run.charged as u64;
+        self.cancelled += run.cancelled as u64;
+        self.collected = self.collected.saturating_add(run.collected);
+    }
+}
+
+impl std::fmt::Display for SettlementSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "total of {} runs: charged {}, cancelled {}, collected {}",
+            self.runs, self.charged, self.cancelled, self.collected
+        )
+    }
+}
+
+/// Encodes a string as JSON string literal
+pub(crate) fn json_string(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len() + 2);
+    encoded.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            '\n' => encoded.push_str("\\n"),
+            '\r' => encoded.push_str("\\r"),
+            '\t' => encoded.push_str("\\t"),
+            c if c.is_control() => encoded.push_str(&format!("\\u{:04x}", c as u32)),
+            c => encoded.push(c),
+        }
+    }
+    encoded.push('"');
+    encoded
+}
+
+/// Reconstructs active subscriptions by replaying events of the subscriptions smart contract
+/// params:
+/// * events - events in the order of emission
+/// returns:
+/// * active subscriptions in the order of registration
+pub fn reconstruct_subscriptions(
+    events: impl IntoIterator<Item = SubscriptionEvent>,
+) -> Vec<ActiveSubscription> {
+    let mut subs: Vec<ActiveSubscription> = vec![];
+    for event in events {
+        match event {
+            SubscriptionEvent::NewSubscription(sub) => subs.push(sub),
+            SubscriptionEvent::CancelledSubscription(account) => {
+                subs.retain(|sub| sub.account != account)
+            }
+            SubscriptionEvent::CancelledSubscriptions(accounts) => {
+                subs.retain(|sub| !accounts.contains(&sub.account))
+            }
+            SubscriptionEvent::UpdatedChannelHandle(updated) => {
+                if let Some(sub) = subs.iter_mut().find(|sub| sub.account == updated.account) {
+                    sub.external_channel_handle = updated.external_channel_handle;
+                }
+            }
+        }
+    }
+    subs
+}
+
+/// Writes subscription records in the CSV format
+/// params:
+/// * writer - CSV output
+/// * records - subscription records
+pub fn write_subscriptions_csv<W: Write>(
+    mut writer: W,
+    records: &[SubscriptionRecord],
+) -> Result<()> {
+    writeln!(writer, "account,handle,interval,paid,declared,expiry")?;
+    for record in records {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            record.account,
+            csv_field(&record.external_channel_handle),
+            record.payment_interval,
+            record.paid_intervals,
+            record.declared_payment_intervals,
+            record.expiry_block
+        )?;
+    }
+    writer.flush().context("failed to write CSV")
+}
+
+/// Quotes CSV field if it contains a separator, a quote or a new line
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockSolvencySource(std::sync::Mutex<Vec<u32>>);
+
+    #[async_trait]
+    impl SolvencySource for MockSolvencySource {
+        async fn solvency_ratio_bps(&self) -> Result<u32> {
+            Ok(self.0.lock().unwrap().remove(0))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_monitor_solvency() {
+        let no_wait = std::time::Duration::ZERO;
+
+        // alerts are logged when the ratio drops below the threshold
+        let source = MockSolvencySource(vec![12_000, 9_999, 10_000, 5_000].into());
+        let alerts = monitor_solvency(&source, 10_000, no_wait, false, Some(4))
+            .await
+            .unwrap();
+        assert_eq!(alerts, 2);
+
+        // or the first alert ends monitoring with an error
+        let source = MockSolvencySource(vec![12_000, 9_000, 12_000].into());
+        let err = monitor_solvency(&source, 10_000, no_wait, true, Some(3))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("9000 bps is below threshold"));
+        assert_eq!(source.0.lock().unwrap().len(), 1);
+    }
+
+    /// Mocks the chain extension, verifying only a known proof
+    struct MockDryRunner(Vec<u8>);
+
+    #[async_trait]
+    impl SubscriptionDryRunner for MockDryRunner {
+        async fn dry_run_add_subscription(
+            &self,
+            _seed: &str,
+            _required_age: u128,
+            proof: &[u8],
+        ) -> Result<Option<String>> {
+            if proof != self.0 {
+                return Ok(Some("InvalidProofForMinAgeRequired".to_string()));
+            }
+            // native tokens are not transferred by the dry run
+            Ok(Some("SubscriptionCostTooHigh".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_extension() {
+        let proof = vec![1u8, 2, 3];
+        let check = check_extension(&MockDryRunner(proof.clone()), "//Alice", 18, &proof)
+            .await
+            .unwrap();
+        assert_eq!(check.valid, VerificationOutcome::Accepted);
+        assert_eq!(check.invalid, VerificationOutcome::Rejected);
+        assert!(check.consistent());
+
+        // an extension rejecting every proof
+        let check = check_extension(&MockDryRunner(vec![]), "//Alice", 18, &proof)
+            .await
+            .unwrap();
+        assert_eq!(check.valid, VerificationOutcome::Rejected);
+        assert!(!check.consistent());
+
+        assert_eq!(
+            VerificationOutcome::from_contract_error(None),
+            VerificationOutcome::Accepted
+        );
+        assert_eq!(
+            VerificationOutcome::from_contract_error(Some("AlreadyRegisterred")),
+            VerificationOutcome::Inconclusive("AlreadyRegisterred".to_string())
+        );
+        assert!(check_extension(&MockDryRunner(vec![]), "//Alice", 18, &[])
+            .await
+            .is_err());
+    }
+
+    struct MockFeeEstimator(u128);
+
+    #[async_trait]
+    impl FeeEstimator for MockFeeEstimator {
+        async fn estimate_add_subscription_fee(
+            &self,
+            _seed: &str,
+            _payment_interval: &str,
+            _intervals: u32,
+            _external_channel_handle: &str,
+            _required_age: u128,
+            _proof: &[u8],
+            _value: u128,
+        ) -> Result<u128> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_subscription() {
+        let estimator = MockFeeEstimator(1_000);
+        let prices = (604_800, 2_592_000);
+
+        let quote = quote_subscription(&estimator, prices, "//Alice", "Month", 3, "chat", 18, &[])
+            .await
+            .unwrap();
+        assert_eq!(quote.interval_cost, 2_592_000);
+        assert_eq!(quote.subscription_cost(), 3 * 2_592_000);
+        assert_eq!(quote.total(), 3 * 2_592_000 + 1_000);
+
+        let quote = quote_subscription(&estimator, prices, "//Alice", "Week", 1, "chat", 18, &[])
+            .await
+            .unwrap();
+        assert_eq!(quote.total(), 604_800 + 1_000);
+
+        // custom intervals are priced per block
+        let quote = quote_subscription(
+            &estimator,
+            prices,
+            "//Alice",
+            "Custom(100)",
+            2,
+            "chat",
+            18,
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(quote.interval_cost, 100);
+        assert_eq!(quote.total(), 2 * 100 + 1_000);
+
+        for interval in ["Year", "Custom(0)", "Custom(week)", "Custom(100"] {
+            assert!(quote_subscription(
+                &estimator,
+                prices,
+                "//Alice",
+                interval,
+                1,
+                "chat",
+                18,
+                &[]
+            )
+            .await
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_events_ndjson() {
+        let alice = aleph_client::keypair_from_string("//Alice")
+            .account_id()
+            .clone();
+        let bob = aleph_client::keypair_from_string("//Bob")
+            .account_id()
+            .clone();
+        let events = [
+            SubscriptionEvent::NewSubscription(ActiveSubscription {
+                account: alice.clone(),
+                external_channel_handle: "chat \"123\"".to_string(),
+            }),
+            SubscriptionEvent::CancelledSubscriptions(vec![alice.clone(), bob.clone()]),
+        ];
+
+        let lines = events
+            .iter()
+            .map(|event| event.format(EventsFormat::Ndjson, 42))
+            .collect::<Vec<_>>();
+        for line in &lines {
+            assert!(!line.contains('\n'));
+        }
+
+        let new: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(new["event"], "NewSubscription");
+        assert_eq!(new["account"], alice.to_string());
+        assert_eq!(new["handle"], "chat \"123\"");
+        assert_eq!(new["block"], 42);
+
+        let cancelled: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(cancelled["event"], "CancelledSubscriptions");
+        assert_eq!(
+            cancelled["accounts"],
+            serde_json::json!([alice.to_string(), bob.to_string()])
+        );
+        assert_eq!(cancelled["block"], 42);
+    }
+
+    #[tokio::test]
+    async fn test_stale_proof_min_age_rejected() {
+        use filepath::FilePath;
+
+        use crate::min_age_proof_ops::MinAgeProofOps;
+
+        let tmp_file_setup = tempfile::tempfile().unwrap();
+        let path_setup = tmp_file_setup.path().unwrap();
+        let proof_dir = tempfile::tempdir().unwrap();
+        let path_proof = proof_dir.path().join("alice.proof");
+
+        let mut ops = MinAgeProofOps::<18>::new();
+        ops.generate_setup(&path_setup).await.unwrap();
+        ops.generate_proof(&path_proof, "//Alice", 23)
+            .await
+            .unwrap();
+        let proof_min_age = MinAgeProofOps::<18>::proof_min_age(&path_proof)
+            .unwrap()
+            .unwrap();
+
+        // the smart contract's policy has been changed to 21
+        assert!(check_proof_min_age(proof_min_age, 18).is_ok());
+        let err = check_proof_min_age(proof_min_age, 21).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ClientError>(),
+            Some(ClientError::InvalidProof(_))
+        ));
+        assert!(err.to_string().contains("regenerate your proof for age 21"));
+    }
+
+    #[test]
+    fn test_write_subscriptions_csv() {
+        let alice = aleph_client::keypair_from_string("//Alice")
+            .account_id()
+            .clone();
+        let records = vec![SubscriptionRecord {
+            account: alice.clone(),
+            external_channel_handle: "chat_id:123,456".to_string(),
+            payment_interval: "Week".to_string(),
+            paid_intervals: 1,
+            declared_payment_intervals: 3,
+            expiry_block: 1814400,
+        }];
+
+        let mut out = vec![];
+        write_subscriptions_csv(&mut out, &records).unwrap();
+
+        let csv = String::from_utf8(out).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "account,handle,interval,paid,declared,expiry");
+        assert_eq!(
+            lines[1],
+            format!("{alice},\"chat_id:123,456\",Week,1,3,1814400")
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_reconstruct_subscriptions() {
+        let [alice, bob, charlie, dave] = ["//Alice", "//Bob", "//Charlie", "//Dave"]
+            .map(|seed| aleph_client::keypair_from_string(seed).account_id().clone());
+        let sub = |account: &AccountId, handle: &str| ActiveSubscription {
+            account: account.clone(),
+            external_channel_handle: handle.to_string(),
+        };
+
+        let events = vec![
+            SubscriptionEvent::NewSubscription(sub(&alice, "1111")),
+            SubscriptionEvent::NewSubscription(sub(&bob, "2222")),
+            SubscriptionEvent::NewSubscription(sub(&charlie, "3333")),
+            SubscriptionEvent::CancelledSubscription(bob.clone()),
+            SubscriptionEvent::UpdatedChannelHandle(sub(&charlie, "6666")),
+            SubscriptionEvent::NewSubscription(sub(&dave, "4444")),
+            SubscriptionEvent::CancelledSubscriptions(vec![alice.clone(), dave.clone()]),
+            SubscriptionEvent::NewSubscription(sub(&alice, "5555")),
+            // handles of cancelled subscriptions are ignored
+            SubscriptionEvent::UpdatedChannelHandle(sub(&bob, "7777")),
+        ];
+
+        assert_eq!(
+            reconstruct_subscriptions(events),
+            vec![sub(&charlie, "6666"), sub(&alice, "5555")]
+        );
+    }
+
+    #[test]
+    fn test_settlement_summary() {
+        let [alice, bob, charlie] = ["//Alice", "//Bob", "//Charlie"]
+            .map(|seed| aleph_client::keypair_from_string(seed).account_id().clone());
+        let settled = |account: &AccountId, intervals_charged: u32, amount: u128| {
+            SettlementEvent::PaymentSettled {
+                account: account.clone(),
+                intervals_charged,
+                amount,
+            }
+        };
+        let runs = [
+            vec![settled(&alice, 1, 100), settled(&bob, 2, 200)],
+            vec![
+                settled(&alice, 1, 100),
+                SettlementEvent::CancelledForNonPayment(vec![bob.clone(), charlie.clone()]),
+            ],
+            vec![SettlementEvent::CancelledForNonPayment(vec![alice.clone()])],
+        ];
+
+        let mut summary = SettlementSummary::default();
+        let mut totals = vec![];
+        for events in &runs {
+            summary.add(&SettlementRun::from_events(events));
+            totals.push(summary);
+        }
+
+        assert_eq!(
+            SettlementRun::from_events(&runs[1]),
+            SettlementRun {
+                charged: 1,
+                cancelled: 2,
+                collected: 100,
+            }
+        );
+        assert_eq!(
+            totals
+                .iter()
+                .map(|s| (s.runs, s.charged, s.cancelled, s.collected))
+                .collect::<Vec<_>>(),
+            vec![(1, 2, 0, 300), (2, 3, 2, 400), (3, 3, 3, 400)]
+        );
+        assert_eq!(
+            summary.to_string(),
+            "total of 3 runs: charged 3, cancelled 3, collected 400"
+        );
     }
 }